@@ -0,0 +1,10 @@
+-- Ranked feed snapshots
+-- Each row is one ranked list of profiles for a user; GET /feed pages through it with a cursor
+CREATE TABLE feed_snapshots (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    profile_ids UUID[] NOT NULL, -- in ranked order
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_feed_snapshots_user ON feed_snapshots(user_id, created_at DESC);
@@ -0,0 +1,20 @@
+-- Precomputed feed candidate pools
+-- Built by the in-process feed pool job; GET /feed reads from here and falls back to live ranking
+
+-- One row per user whose pool has been requested or built
+CREATE TABLE feed_pools (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    computed_at TIMESTAMP WITH TIME ZONE, -- NULL until first built
+    requested_at TIMESTAMP WITH TIME ZONE DEFAULT NOW() -- pool is stale if requested after it was computed
+);
+
+-- Ranked candidates for each pool
+CREATE TABLE feed_pool_candidates (
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    candidate_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL, -- 0 = best
+    PRIMARY KEY (user_id, candidate_id)
+);
+
+CREATE INDEX idx_feed_pool_candidates_candidate ON feed_pool_candidates(candidate_id);
+CREATE INDEX idx_users_last_active ON users(last_active DESC);
@@ -0,0 +1,19 @@
+-- Append-only log of every interaction, so undos can be audited
+-- `interactions` keeps the current state per pair; this table keeps every change
+CREATE TABLE interaction_history (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    from_user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    to_user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    action VARCHAR(20) NOT NULL, -- 'LIKE', 'PASS'
+    context_type VARCHAR(20), -- 'IMAGE', 'PROMPT'
+    context_id VARCHAR(255),
+    comment TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    undone_at TIMESTAMP WITH TIME ZONE -- set by POST /interact/undo
+);
+
+CREATE INDEX idx_interaction_history_from_user ON interaction_history(from_user_id, created_at DESC);
+
+-- Existing interactions become the first history entries
+INSERT INTO interaction_history (from_user_id, to_user_id, action, context_type, context_id, comment, created_at)
+SELECT from_user_id, to_user_id, action, context_type, context_id, comment, created_at FROM interactions;
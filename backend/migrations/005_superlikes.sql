@@ -0,0 +1,9 @@
+-- Super-likes ("Roses")
+-- interactions.action / interaction_history.action can now also be 'SUPERLIKE'
+
+-- Per-user super-like allowance, topped back up every replenish period
+CREATE TABLE superlike_allowances (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    remaining INTEGER NOT NULL,
+    replenished_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
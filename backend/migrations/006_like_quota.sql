@@ -0,0 +1,6 @@
+-- Daily LIKE quota
+-- The quota resets at midnight in the user's time zone
+ALTER TABLE users ADD COLUMN time_zone VARCHAR(64) NOT NULL DEFAULT 'UTC';
+
+-- Daily LIKE limit granted by an entitlement (e.g. a subscription); NULL = default limit
+ALTER TABLE users ADD COLUMN daily_like_limit INTEGER;
@@ -0,0 +1,10 @@
+-- User blocks
+-- A block hides both users from each other everywhere; the blocked user is never told
+CREATE TABLE blocks (
+    blocker_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    blocked_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (blocker_id, blocked_id)
+);
+
+CREATE INDEX idx_blocks_blocked ON blocks(blocked_id);
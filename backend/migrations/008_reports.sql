@@ -0,0 +1,30 @@
+-- Reports and the moderation queue
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Set by moderation actions (enforced separately)
+ALTER TABLE users ADD COLUMN account_status VARCHAR(20) NOT NULL DEFAULT 'ACTIVE'
+    CHECK (account_status IN ('ACTIVE', 'BANNED'));
+
+CREATE TABLE reports (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    reporter_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    target_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    context_type VARCHAR(20), -- 'IMAGE', 'PROMPT', 'MESSAGE'
+    context_id UUID,
+    -- Copy of the reported image url / prompt / message, kept if the original is edited or deleted
+    context_snapshot TEXT,
+    reason VARCHAR(30) NOT NULL,
+    details TEXT,
+    status VARCHAR(20) NOT NULL DEFAULT 'OPEN'
+        CHECK (status IN ('OPEN', 'ASSIGNED', 'RESOLVED', 'DISMISSED')),
+    assigned_to UUID REFERENCES users(id) ON DELETE SET NULL,
+    assigned_at TIMESTAMP WITH TIME ZONE,
+    resolution_action VARCHAR(20), -- 'NONE', 'REMOVE_IMAGE', 'REMOVE_PROMPT', 'BAN_USER'
+    resolution_note TEXT,
+    resolved_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    resolved_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_reports_status ON reports(status, created_at);
+CREATE INDEX idx_reports_target ON reports(target_user_id);
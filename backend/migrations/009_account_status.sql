@@ -0,0 +1,19 @@
+-- Ban and shadow-ban enforcement
+-- BANNED: can't sign in or use the API. SHADOW_BANNED: hidden from everyone else, own app keeps working
+ALTER TABLE users DROP CONSTRAINT users_account_status_check;
+ALTER TABLE users ADD CONSTRAINT users_account_status_check
+    CHECK (account_status IN ('ACTIVE', 'BANNED', 'SHADOW_BANNED'));
+
+-- Every account status change, newest last
+CREATE TABLE account_status_audit (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    old_status VARCHAR(20) NOT NULL,
+    new_status VARCHAR(20) NOT NULL,
+    changed_by UUID REFERENCES users(id) ON DELETE SET NULL, -- the admin, NULL for system changes
+    reason TEXT,
+    report_id UUID REFERENCES reports(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_account_status_audit_user ON account_status_audit(user_id, created_at);
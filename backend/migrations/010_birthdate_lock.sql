@@ -0,0 +1,2 @@
+-- Birthdates can only be set once; support can allow one more change
+ALTER TABLE profiles ADD COLUMN birthdate_unlocked BOOLEAN NOT NULL DEFAULT FALSE;
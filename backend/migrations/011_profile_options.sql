@@ -0,0 +1,81 @@
+-- Controlled vocabularies for profile fields
+-- POST /profile only accepts these values; GET /meta/options lists them
+CREATE TABLE profile_options (
+    field VARCHAR(50) NOT NULL,
+    value VARCHAR(100) NOT NULL,
+    display_order INTEGER NOT NULL,
+    PRIMARY KEY (field, value)
+);
+
+INSERT INTO profile_options (field, value, display_order) VALUES
+    ('gender', 'Man', 0),
+    ('gender', 'Woman', 1),
+    ('gender', 'Non-binary', 2),
+
+    ('sexuality', 'Straight', 0),
+    ('sexuality', 'Gay', 1),
+    ('sexuality', 'Lesbian', 2),
+    ('sexuality', 'Bisexual', 3),
+    ('sexuality', 'Pansexual', 4),
+    ('sexuality', 'Queer', 5),
+    ('sexuality', 'Asexual', 6),
+
+    ('politics', 'Liberal', 0),
+    ('politics', 'Moderate', 1),
+    ('politics', 'Conservative', 2),
+    ('politics', 'Not political', 3),
+    ('politics', 'Other', 4),
+
+    ('religion', 'Agnostic', 0),
+    ('religion', 'Atheist', 1),
+    ('religion', 'Buddhist', 2),
+    ('religion', 'Catholic', 3),
+    ('religion', 'Christian', 4),
+    ('religion', 'Hindu', 5),
+    ('religion', 'Jewish', 6),
+    ('religion', 'Muslim', 7),
+    ('religion', 'Sikh', 8),
+    ('religion', 'Spiritual', 9),
+    ('religion', 'Other', 10),
+
+    ('drinks', 'Yes', 0),
+    ('drinks', 'Sometimes', 1),
+    ('drinks', 'No', 2),
+
+    ('smokes', 'Yes', 0),
+    ('smokes', 'Sometimes', 1),
+    ('smokes', 'No', 2),
+
+    ('relationship_type', 'Monogamy', 0),
+    ('relationship_type', 'Non-monogamy', 1),
+    ('relationship_type', 'Figuring out my relationship type', 2),
+
+    ('dating_intention', 'Life partner', 0),
+    ('dating_intention', 'Long-term relationship', 1),
+    ('dating_intention', 'Long-term relationship, open to short', 2),
+    ('dating_intention', 'Short-term relationship, open to long', 3),
+    ('dating_intention', 'Short-term relationship', 4),
+    ('dating_intention', 'Figuring out my dating goals', 5);
+
+-- Bring existing rows in line with the vocabularies
+UPDATE profiles SET drinks = 'Sometimes' WHERE drinks = 'Socially';
+UPDATE profiles SET smokes = 'Sometimes' WHERE smokes = 'Socially';
+UPDATE profiles SET relationship_type = 'Figuring out my relationship type'
+    WHERE relationship_type = 'Figuring out my dating goals';
+
+-- Other values get their stored spelling if they match one ignoring case and spaces (as POST /profile does),
+-- anything still outside the vocabulary is cleared so the profile can be saved again
+DO $$
+DECLARE
+    f TEXT;
+BEGIN
+    FOREACH f IN ARRAY ARRAY['gender', 'sexuality', 'politics', 'religion', 'drinks', 'smokes',
+                             'relationship_type', 'dating_intention'] LOOP
+        EXECUTE format(
+            'UPDATE profiles p SET %1$I = o.value FROM profile_options o
+             WHERE o.field = %1$L AND LOWER(o.value) = LOWER(TRIM(p.%1$I)) AND p.%1$I <> o.value', f);
+        EXECUTE format(
+            'UPDATE profiles SET %1$I = NULL
+             WHERE %1$I NOT IN (SELECT value FROM profile_options WHERE field = %1$L)', f);
+    END LOOP;
+END $$;
@@ -0,0 +1,3 @@
+-- Profile fields the user has hidden from other users
+-- Hidden fields are left out of public profile views but still used for filtering and ranking
+ALTER TABLE profiles ADD COLUMN hidden_fields TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,5 @@
+-- Key of the stored file in the media store (MEDIA_STORE)
+-- NULL for rows created before uploads went through the store
+ALTER TABLE user_images ADD COLUMN storage_key TEXT;
+
+CREATE UNIQUE INDEX idx_user_images_storage_key ON user_images(storage_key);
@@ -0,0 +1,8 @@
+-- Renditions built by the image pipeline
+-- `url` is the full-size rendition; `storage_key` is now the key prefix the renditions live under
+-- NULL for images uploaded before the pipeline existed
+ALTER TABLE user_images ADD COLUMN thumbnail_url TEXT;
+ALTER TABLE user_images ADD COLUMN card_url TEXT;
+ALTER TABLE user_images ADD COLUMN blurhash TEXT;
+ALTER TABLE user_images ADD COLUMN width INTEGER;
+ALTER TABLE user_images ADD COLUMN height INTEGER;
@@ -0,0 +1,35 @@
+-- Profile image order: 0..n-1 per user with no gaps or duplicates
+-- Both checks are deferred so a reorder or delete + compact can pass through intermediate states in one transaction
+
+-- Close gaps and duplicates left by earlier deletes
+UPDATE user_images ui SET display_order = o.new_order
+FROM (
+    SELECT id, (ROW_NUMBER() OVER (PARTITION BY user_id ORDER BY display_order, created_at) - 1)::INT AS new_order
+    FROM user_images
+) o
+WHERE ui.id = o.id AND ui.display_order <> o.new_order;
+
+ALTER TABLE user_images ADD CONSTRAINT user_images_display_order_range CHECK (display_order >= 0 AND display_order < 6);
+ALTER TABLE user_images ADD CONSTRAINT user_images_display_order_unique
+    UNIQUE (user_id, display_order) DEFERRABLE INITIALLY DEFERRED;
+
+-- Unique orders with max = count - 1 means the user's orders are exactly 0..n-1
+CREATE FUNCTION check_user_images_dense() RETURNS TRIGGER AS $$
+DECLARE
+    owner UUID := CASE WHEN TG_OP = 'DELETE' THEN OLD.user_id ELSE NEW.user_id END;
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM user_images WHERE user_id = owner
+        HAVING MAX(display_order) <> COUNT(*) - 1
+    ) THEN
+        RAISE EXCEPTION 'user_images display_order for % is not dense', owner
+            USING ERRCODE = 'check_violation', CONSTRAINT = 'user_images_display_order_dense';
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER user_images_display_order_dense
+    AFTER INSERT OR UPDATE OF display_order, user_id OR DELETE ON user_images
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION check_user_images_dense();
@@ -0,0 +1,8 @@
+-- Image moderation
+-- New uploads start PENDING until the image classifier approves or rejects them; only APPROVED images are shown to other users
+-- Images already on profiles were live before moderation existed, so they start APPROVED
+ALTER TABLE user_images ADD COLUMN moderation_status VARCHAR(20) NOT NULL DEFAULT 'APPROVED'
+    CHECK (moderation_status IN ('PENDING', 'APPROVED', 'REJECTED'));
+ALTER TABLE user_images ALTER COLUMN moderation_status SET DEFAULT 'PENDING';
+ALTER TABLE user_images ADD COLUMN rejection_reason TEXT; -- shown to the owner, set when REJECTED
+ALTER TABLE user_images ADD COLUMN moderated_at TIMESTAMP WITH TIME ZONE;
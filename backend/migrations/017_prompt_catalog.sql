@@ -0,0 +1,59 @@
+-- Server-managed prompt questions
+-- Users answer questions from this catalog (GET /prompts/catalog) instead of writing their own.
+-- Ids are stable slugs so clients can localise questions and analytics can group answers by question.
+CREATE TABLE prompt_categories (
+    id VARCHAR(50) PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    display_order INTEGER NOT NULL
+);
+
+CREATE TABLE prompt_questions (
+    id VARCHAR(50) PRIMARY KEY,
+    category_id VARCHAR(50) NOT NULL REFERENCES prompt_categories(id),
+    text TEXT NOT NULL UNIQUE,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE, -- inactive questions keep existing answers but can't be picked
+    display_order INTEGER NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+INSERT INTO prompt_categories (id, name, display_order) VALUES
+    ('about_me', 'About me', 0),
+    ('getting_personal', 'Getting personal', 1),
+    ('dating', 'Dating', 2),
+    ('legacy', 'Legacy', 3);
+
+INSERT INTO prompt_questions (id, category_id, text, display_order) VALUES
+    ('simple_pleasures', 'about_me', 'My simple pleasures', 0),
+    ('unusual_skills', 'about_me', 'Unusual skills', 1),
+    ('convinced_that', 'about_me', 'I''m convinced that', 2),
+    ('typical_sunday', 'about_me', 'A typical Sunday', 3),
+    ('go_to_karaoke', 'about_me', 'My go-to karaoke song', 4),
+
+    ('never_shut_up_about', 'getting_personal', 'I won''t shut up about', 0),
+    ('geek_out_on', 'getting_personal', 'I geek out on', 1),
+    ('biggest_risk', 'getting_personal', 'The biggest risk I''ve taken', 2),
+    ('happy_place', 'getting_personal', 'My happy place', 3),
+
+    ('win_me_over', 'dating', 'The way to win me over is', 0),
+    ('looking_for', 'dating', 'I''m looking for', 1),
+    ('dating_me_is_like', 'dating', 'Dating me is like', 2),
+    ('green_flags', 'dating', 'Green flags I look for', 3),
+    ('ideal_first_date', 'dating', 'My ideal first date', 4);
+
+-- Free-text questions that aren't in the catalog are kept as inactive legacy questions,
+-- so existing answers still show but nobody new can pick them
+INSERT INTO prompt_questions (id, category_id, text, is_active, display_order)
+SELECT 'legacy_' || LEFT(MD5(q.text), 12), 'legacy', q.text, FALSE, 0
+FROM (SELECT DISTINCT TRIM(question) AS text FROM user_prompts) q
+WHERE NOT EXISTS (SELECT 1 FROM prompt_questions pq WHERE LOWER(pq.text) = LOWER(q.text));
+
+ALTER TABLE user_prompts ADD COLUMN question_id VARCHAR(50) REFERENCES prompt_questions(id);
+
+UPDATE user_prompts up SET question_id = pq.id
+FROM prompt_questions pq
+WHERE LOWER(pq.text) = LOWER(TRIM(up.question));
+
+ALTER TABLE user_prompts ALTER COLUMN question_id SET NOT NULL;
+ALTER TABLE user_prompts DROP COLUMN question;
+
+CREATE INDEX idx_user_prompts_question ON user_prompts(question_id);
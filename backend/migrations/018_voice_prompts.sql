@@ -0,0 +1,7 @@
+-- Voice-note answers
+-- A prompt can be answered with a recording instead of, or as well as, text
+ALTER TABLE user_prompts ALTER COLUMN answer DROP NOT NULL;
+ALTER TABLE user_prompts ADD COLUMN audio_key TEXT UNIQUE; -- media store key of the recording
+ALTER TABLE user_prompts ADD COLUMN audio_url TEXT;
+ALTER TABLE user_prompts ADD COLUMN audio_duration_ms INTEGER;
+ALTER TABLE user_prompts ADD CONSTRAINT user_prompts_answered CHECK (answer IS NOT NULL OR audio_url IS NOT NULL);
@@ -0,0 +1,18 @@
+-- Short videos
+-- A media slot can hold a clip instead of a photo. The row's url and renditions are then the clip's poster frame
+-- (what the image classifier looks at) and the clip itself is stored next to them (media::video_key).
+ALTER TABLE user_images ADD COLUMN media_type VARCHAR(10) NOT NULL DEFAULT 'IMAGE'
+    CHECK (media_type IN ('IMAGE', 'VIDEO'));
+ALTER TABLE user_images ADD COLUMN video_url TEXT;
+ALTER TABLE user_images ADD COLUMN video_duration_ms INTEGER;
+ALTER TABLE user_images ADD CONSTRAINT user_images_video CHECK ((media_type = 'VIDEO') = (video_url IS NOT NULL));
+
+-- A prompt can also be answered with a clip, instead of a voice note
+ALTER TABLE user_prompts ADD COLUMN video_key TEXT UNIQUE; -- media store key prefix of the clip and its poster
+ALTER TABLE user_prompts ADD COLUMN video_url TEXT;
+ALTER TABLE user_prompts ADD COLUMN video_poster_url TEXT;
+ALTER TABLE user_prompts ADD COLUMN video_duration_ms INTEGER;
+ALTER TABLE user_prompts DROP CONSTRAINT user_prompts_answered;
+ALTER TABLE user_prompts ADD CONSTRAINT user_prompts_answered
+    CHECK (answer IS NOT NULL OR audio_url IS NOT NULL OR video_url IS NOT NULL);
+ALTER TABLE user_prompts ADD CONSTRAINT user_prompts_one_recording CHECK (audio_url IS NULL OR video_url IS NULL);
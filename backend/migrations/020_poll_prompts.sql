@@ -0,0 +1,28 @@
+-- Poll prompts
+-- Poll questions come from the catalog like any other, but are answered with 2-4 options the user writes
+-- instead of text or a recording. Whoever likes the prompt can vote for one of the options.
+ALTER TABLE prompt_questions ADD COLUMN kind VARCHAR(10) NOT NULL DEFAULT 'TEXT'
+    CHECK (kind IN ('TEXT', 'POLL'));
+
+INSERT INTO prompt_categories (id, name, display_order) VALUES ('polls', 'Polls', 3);
+UPDATE prompt_categories SET display_order = 4 WHERE id = 'legacy';
+
+INSERT INTO prompt_questions (id, category_id, text, kind, display_order) VALUES
+    ('pick_our_first_date', 'polls', 'Pick our first date', 'POLL', 0),
+    ('settle_a_debate', 'polls', 'Help me settle a debate', 'POLL', 1),
+    ('guess_my_order', 'polls', 'Guess my coffee order', 'POLL', 2),
+    ('where_next', 'polls', 'Where should I travel next', 'POLL', 3);
+
+ALTER TABLE user_prompts ADD COLUMN poll_options TEXT[];
+ALTER TABLE user_prompts ADD CONSTRAINT user_prompts_poll_options
+    CHECK (poll_options IS NULL OR cardinality(poll_options) BETWEEN 2 AND 4);
+ALTER TABLE user_prompts DROP CONSTRAINT user_prompts_answered;
+ALTER TABLE user_prompts ADD CONSTRAINT user_prompts_answered
+    CHECK (answer IS NOT NULL OR audio_url IS NOT NULL OR video_url IS NOT NULL OR poll_options IS NOT NULL);
+-- A poll is only its options
+ALTER TABLE user_prompts ADD CONSTRAINT user_prompts_poll_only
+    CHECK (poll_options IS NULL OR (answer IS NULL AND audio_url IS NULL AND video_url IS NULL));
+
+-- The option a like voted for, copied so it still reads right if the poll is edited later
+ALTER TABLE interactions ADD COLUMN poll_answer TEXT;
+ALTER TABLE interaction_history ADD COLUMN poll_answer TEXT;
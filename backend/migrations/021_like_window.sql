@@ -0,0 +1,5 @@
+-- Anchored LIKE quota windows
+-- The window is fixed when it opens, from the time zone in effect then, so changing time zone
+-- (POST /profile) only moves the next window, not the one in progress.
+ALTER TABLE users ADD COLUMN like_window_start TIMESTAMP WITH TIME ZONE;
+ALTER TABLE users ADD COLUMN like_window_end TIMESTAMP WITH TIME ZONE;
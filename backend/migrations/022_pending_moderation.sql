@@ -0,0 +1,3 @@
+-- Images waiting for moderation
+-- The feed pool job retries images the classifier couldn't judge at upload time
+CREATE INDEX idx_user_images_pending ON user_images(created_at) WHERE moderation_status = 'PENDING';
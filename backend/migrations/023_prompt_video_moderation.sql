@@ -0,0 +1,13 @@
+-- Video prompt moderation
+-- A video answer's poster frame goes through the image classifier like a profile image;
+-- other users only get the clip and its poster once it's APPROVED. NULL when the prompt has no video.
+ALTER TABLE user_prompts ADD COLUMN video_moderation_status VARCHAR(20)
+    CHECK (video_moderation_status IN ('PENDING', 'APPROVED', 'REJECTED'));
+ALTER TABLE user_prompts ADD COLUMN video_rejection_reason TEXT; -- shown to the owner, set when REJECTED
+
+-- Videos answered before moderation covered them are picked up by the retry in the feed pool job
+UPDATE user_prompts SET video_moderation_status = 'PENDING' WHERE video_key IS NOT NULL;
+ALTER TABLE user_prompts ADD CONSTRAINT user_prompts_video_moderation
+    CHECK ((video_key IS NULL) = (video_moderation_status IS NULL));
+
+CREATE INDEX idx_user_prompts_video_pending ON user_prompts(created_at) WHERE video_moderation_status = 'PENDING';
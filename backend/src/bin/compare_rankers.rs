@@ -0,0 +1,79 @@
+//! Feed ranker comparison harness
+//! Run with: cargo run --bin compare_rankers
+//!
+//! Ranks the seed profiles for every seed user with each ranker and prints the
+//! resulting feeds side by side. No database is needed and the clock is fixed,
+//! so the output is the same on every run and can be diffed between changes.
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use backend::db::seed::{self, SeedRankingUser};
+use backend::routes::feed::ranker::{Ranker, RankingWeights, TableOrderRanker, WeightedRanker};
+
+/// Number of profiles shown per viewer
+const TOP_N: usize = 5;
+
+/// Fixed "now" so recency scores don't drift between runs
+fn fixed_now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap()
+}
+
+/// Rank the other seed users for `viewer`, filtered by the viewer's gender preference
+fn feed_for(ranker: &dyn Ranker, viewer: &SeedRankingUser, users: &[SeedRankingUser], now: DateTime<Utc>) -> Vec<String> {
+    let candidates = users
+        .iter()
+        .filter(|u| u.candidate.profile.user_id != viewer.candidate.profile.user_id)
+        .filter(|u| {
+            viewer.gender_preference.is_empty()
+                || u.candidate
+                    .profile
+                    .gender
+                    .as_ref()
+                    .is_some_and(|g| viewer.gender_preference.contains(g))
+        })
+        .map(|u| u.candidate.clone())
+        .collect();
+
+    ranker
+        .rank(&viewer.viewer, candidates, now)
+        .into_iter()
+        .take(TOP_N)
+        .map(|c| c.profile.user_id)
+        .collect()
+}
+
+fn main() {
+    let now = fixed_now();
+    let users = seed::seed_ranking_users(now);
+
+    // (label, ranker) - the first entry is the baseline the others are compared to
+    let rankers: Vec<(String, Box<dyn Ranker>)> = vec![
+        (TableOrderRanker.name().to_string(), Box::new(TableOrderRanker)),
+        ("default".to_string(), Box::new(WeightedRanker::default())),
+        ("env".to_string(), Box::new(WeightedRanker::new(RankingWeights::from_env()))),
+    ];
+
+    let baseline_name = rankers[0].0.clone();
+    let mut total_overlap = vec![0usize; rankers.len()];
+
+    for viewer in &users {
+        println!("Viewer: {}", viewer.candidate.profile.user_id);
+
+        let feeds: Vec<Vec<String>> = rankers
+            .iter()
+            .map(|(_, r)| feed_for(r.as_ref(), viewer, &users, now))
+            .collect();
+
+        for (i, ((label, _), feed)) in rankers.iter().zip(&feeds).enumerate() {
+            let overlap = feed.iter().filter(|id| feeds[0].contains(id)).count();
+            total_overlap[i] += overlap;
+            println!("  {:<12} {}", label, feed.join(", "));
+        }
+        println!();
+    }
+
+    println!("Top {} overlap with {} (summed over {} viewers):", TOP_N, baseline_name, users.len());
+    for ((label, _), overlap) in rankers.iter().zip(&total_overlap) {
+        println!("  {:<12} {}", label, overlap);
+    }
+}
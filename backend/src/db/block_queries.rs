@@ -0,0 +1,39 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Block a user: record the block and remove any match between the two
+/// (the match's messages go with it)
+pub async fn block_user(pool: &PgPool, blocker_id: &Uuid, blocked_id: &Uuid) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO blocks (blocker_id, blocked_id) VALUES ($1, $2) ON CONFLICT (blocker_id, blocked_id) DO NOTHING"
+    )
+    .bind(blocker_id)
+    .bind(blocked_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "DELETE FROM matches WHERE user1_id = LEAST($1::UUID, $2::UUID) AND user2_id = GREATEST($1::UUID, $2::UUID)"
+    )
+    .bind(blocker_id)
+    .bind(blocked_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Remove a block (does not bring back a removed match)
+pub async fn unblock_user(pool: &PgPool, blocker_id: &Uuid, blocked_id: &Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM blocks WHERE blocker_id = $1 AND blocked_id = $2")
+        .bind(blocker_id)
+        .bind(blocked_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// How long a feed snapshot can be paged through before the client has to start over
+pub const SNAPSHOT_TTL_HOURS: i64 = 24;
+
+/// Store a ranked list of profile ids for a user
+/// Also drops the user's expired snapshots
+/// Returns the new snapshot id
+pub async fn create_snapshot(pool: &PgPool, user_id: &Uuid, profile_ids: &[Uuid]) -> Result<Uuid, sqlx::Error> {
+    sqlx::query(
+        "DELETE FROM feed_snapshots WHERE user_id = $1 AND created_at < NOW() - make_interval(hours => $2::INT)"
+    )
+    .bind(user_id)
+    .bind(SNAPSHOT_TTL_HOURS as i32)
+    .execute(pool)
+    .await?;
+
+    let row: (Uuid,) = sqlx::query_as(
+        "INSERT INTO feed_snapshots (user_id, profile_ids) VALUES ($1, $2) RETURNING id"
+    )
+    .bind(user_id)
+    .bind(profile_ids)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.0)
+}
+
+/// Get a snapshot owned by the user
+/// Returns (profile_ids, created_at), None if it doesn't exist or belongs to someone else
+pub async fn get_snapshot(
+    pool: &PgPool,
+    snapshot_id: &Uuid,
+    user_id: &Uuid,
+) -> Result<Option<(Vec<Uuid>, DateTime<Utc>)>, sqlx::Error> {
+    let row: Option<(Vec<Uuid>, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT profile_ids, created_at FROM feed_snapshots WHERE id = $1 AND user_id = $2"
+    )
+    .bind(snapshot_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+/// How long a precomputed feed pool is used before the job rebuilds it
+pub const POOL_MAX_AGE_HOURS: i64 = 24;
+
+/// Users only get a precomputed pool if they were active this recently
+pub const POOL_ACTIVE_DAYS: i64 = 30;
+
+/// Get the user's precomputed candidate ids in ranked order
+/// Returns None if there is no pool yet or it is stale
+pub async fn get_fresh_pool(pool: &PgPool, user_id: &Uuid) -> Result<Option<Vec<Uuid>>, sqlx::Error> {
+    let fresh: Option<(Uuid,)> = sqlx::query_as(
+        r#"SELECT user_id FROM feed_pools
+           WHERE user_id = $1
+             AND computed_at IS NOT NULL
+             AND (requested_at IS NULL OR requested_at <= computed_at)
+             AND computed_at > NOW() - make_interval(hours => $2::INT)"#
+    )
+    .bind(user_id)
+    .bind(POOL_MAX_AGE_HOURS as i32)
+    .fetch_optional(pool)
+    .await?;
+
+    if fresh.is_none() {
+        return Ok(None);
+    }
+
+    // Skip candidates the user has interacted with, blocked (either way) or who were banned or became incomplete since the pool was built
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"SELECT c.candidate_id FROM feed_pool_candidates c
+           WHERE c.user_id = $1
+             AND NOT EXISTS (
+                 SELECT 1 FROM interactions x WHERE x.from_user_id = $1 AND x.to_user_id = c.candidate_id
+             )
+             AND NOT EXISTS (
+                 SELECT 1 FROM blocks b
+                 WHERE (b.blocker_id = $1 AND b.blocked_id = c.candidate_id)
+                    OR (b.blocker_id = c.candidate_id AND b.blocked_id = $1)
+             )
+             AND EXISTS (
+                 SELECT 1 FROM users cu WHERE cu.id = c.candidate_id AND cu.account_status = 'ACTIVE' AND cu.is_profile_complete
+             )
+           ORDER BY c.position"#
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Some(rows.into_iter().map(|r| r.0).collect()))
+}
+
+/// Replace the user's pool with a new ranked list
+/// `computed_at` is when the ranking started, so refreshes requested while it ran keep the pool stale
+pub async fn save_pool(
+    pool: &PgPool,
+    user_id: &Uuid,
+    candidate_ids: &[Uuid],
+    computed_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM feed_pool_candidates WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        r#"INSERT INTO feed_pool_candidates (user_id, candidate_id, position)
+           SELECT $1, ids.candidate_id, (ids.position - 1)::INT
+           FROM UNNEST($2::UUID[]) WITH ORDINALITY AS ids(candidate_id, position)"#
+    )
+    .bind(user_id)
+    .bind(candidate_ids)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"INSERT INTO feed_pools (user_id, computed_at, requested_at) VALUES ($1, $2, NULL)
+           ON CONFLICT (user_id) DO UPDATE SET computed_at = $2"#
+    )
+    .bind(user_id)
+    .bind(computed_at)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Mark pools stale after the user's preferences or profile changed:
+/// the user's own pool, and every pool the user appears in as a candidate
+pub async fn request_pool_refresh(pool: &PgPool, user_id: &Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"INSERT INTO feed_pools (user_id, requested_at) VALUES ($1, NOW())
+           ON CONFLICT (user_id) DO UPDATE SET requested_at = NOW()"#
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"UPDATE feed_pools SET requested_at = NOW()
+           WHERE user_id IN (SELECT user_id FROM feed_pool_candidates WHERE candidate_id = $1)"#
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Get active (and not banned) users whose pool is missing, stale or older than a day
+/// Returns (user_id, preferences), stale/oldest first
+pub async fn get_users_needing_pool(pool: &PgPool, limit: i64) -> Result<Vec<(Uuid, serde_json::Value)>, sqlx::Error> {
+    let rows: Vec<(Uuid, serde_json::Value)> = sqlx::query_as(
+        r#"SELECT u.id, u.preferences
+           FROM users u
+           LEFT JOIN feed_pools f ON f.user_id = u.id
+           WHERE u.last_active > NOW() - make_interval(days => $1::INT)
+             AND u.account_status <> 'BANNED'
+             AND u.preferences IS NOT NULL AND u.preferences <> '{}'::JSONB
+             AND (f.computed_at IS NULL
+                  OR f.requested_at > f.computed_at
+                  OR f.computed_at < NOW() - make_interval(hours => $2::INT))
+           ORDER BY f.computed_at ASC NULLS FIRST
+           LIMIT $3"#
+    )
+    .bind(POOL_ACTIVE_DAYS as i32)
+    .bind(POOL_MAX_AGE_HOURS as i32)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Get profiles the user recently un-did an interaction with (most recent first)
+/// These go back to the top of the feed
+pub async fn get_rewound_profiles(pool: &PgPool, user_id: &Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"SELECT h.to_user_id
+           FROM interaction_history h
+           WHERE h.from_user_id = $1
+             AND h.undone_at > NOW() - make_interval(hours => $2::INT)
+             AND NOT EXISTS (
+                 SELECT 1 FROM interactions x WHERE x.from_user_id = $1 AND x.to_user_id = h.to_user_id
+             )
+             AND NOT EXISTS (
+                 SELECT 1 FROM blocks b
+                 WHERE (b.blocker_id = $1 AND b.blocked_id = h.to_user_id)
+                    OR (b.blocker_id = h.to_user_id AND b.blocked_id = $1)
+             )
+             AND EXISTS (
+                 SELECT 1 FROM users cu WHERE cu.id = h.to_user_id AND cu.account_status = 'ACTIVE' AND cu.is_profile_complete
+             )
+           GROUP BY h.to_user_id
+           ORDER BY MAX(h.undone_at) DESC"#
+    )
+    .bind(user_id)
+    .bind(SNAPSHOT_TTL_HOURS as i32)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
+/// Get which of `user_ids` must no longer be shown to the user:
+/// blocked in either direction, or banned/shadow-banned/incomplete since the feed was built
+pub async fn get_hidden_among(pool: &PgPool, user_id: &Uuid, user_ids: &[Uuid]) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        r#"SELECT u.id FROM users u
+           WHERE u.id = ANY($2)
+             AND (u.account_status <> 'ACTIVE'
+                  OR u.is_profile_complete IS NOT TRUE
+                  OR EXISTS (
+                      SELECT 1 FROM blocks b
+                      WHERE (b.blocker_id = $1 AND b.blocked_id = u.id)
+                         OR (b.blocker_id = u.id AND b.blocked_id = $1)
+                  ))"#
+    )
+    .bind(user_id)
+    .bind(user_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
@@ -1,6 +1,8 @@
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
+use crate::models::outputs::{ImageRow, OwnedImageRow};
+
 /// Count the number of images for a user
 pub async fn count_images(pool: &PgPool, user_id: &Uuid) -> Result<i64, sqlx::Error> {
     let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user_images WHERE user_id = $1")
@@ -11,37 +13,390 @@ pub async fn count_images(pool: &PgPool, user_id: &Uuid) -> Result<i64, sqlx::Er
     Ok(row.0)
 }
 
-/// Upload a profile image (max 6 allowed)
-pub async fn upload_profile_images(pool: &PgPool, user_id: &Uuid, image_url: &str) -> Result<(), sqlx::Error> {
-    let count = count_images(pool, user_id).await?;
+/// Check that an image belongs to the user
+pub async fn image_belongs_to(pool: &PgPool, image_id: &Uuid, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+    let row: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM user_images WHERE id = $1 AND user_id = $2")
+        .bind(image_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.is_some())
+}
+
+/// Check that an image belongs to the user and has passed moderation (other users can only see and like those)
+pub async fn approved_image_belongs_to(pool: &PgPool, image_id: &Uuid, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+    let row: Option<(Uuid,)> = sqlx::query_as(
+        "SELECT id FROM user_images WHERE id = $1 AND user_id = $2 AND moderation_status = 'APPROVED'"
+    )
+    .bind(image_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.is_some())
+}
+
+/// Get the user's rejected images
+/// Returns (display_order, rejection_reason), first image first
+pub async fn get_rejected_images(pool: &PgPool, user_id: &Uuid) -> Result<Vec<(i32, Option<String>)>, sqlx::Error> {
+    let rows: Vec<(i32, Option<String>)> = sqlx::query_as(
+        "SELECT display_order, rejection_reason FROM user_images WHERE user_id = $1 AND moderation_status = 'REJECTED' ORDER BY display_order"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Get the display orders of the user's images still waiting for moderation, first image first
+pub async fn get_pending_images(pool: &PgPool, user_id: &Uuid) -> Result<Vec<i32>, sqlx::Error> {
+    let rows: Vec<(i32,)> = sqlx::query_as(
+        "SELECT display_order FROM user_images WHERE user_id = $1 AND moderation_status = 'PENDING' ORDER BY display_order"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
+/// Get images left PENDING by every user (the classifier failed, or hasn't finished yet)
+/// Returns (user_id, image_id, storage_key), oldest first
+pub async fn get_unmoderated_images(pool: &PgPool, limit: i64) -> Result<Vec<(Uuid, Uuid, String)>, sqlx::Error> {
+    let rows: Vec<(Uuid, Uuid, String)> = sqlx::query_as(
+        r#"SELECT user_id, id, storage_key FROM user_images
+           WHERE moderation_status = 'PENDING' AND storage_key IS NOT NULL
+           ORDER BY created_at
+           LIMIT $1"#
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
 
-    if count >= 6 {
-        return Err(sqlx::Error::Protocol("Maximum 6 images allowed".to_string()));
+    Ok(rows)
+}
+
+/// Save the moderation result for an image
+/// Only applies while the image still holds the file that was classified (`storage_key`);
+/// returns false if it was replaced or deleted in the meantime
+pub async fn set_moderation(
+    pool: &PgPool,
+    image_id: &Uuid,
+    storage_key: &str,
+    status: &str,
+    rejection_reason: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"UPDATE user_images SET moderation_status = $3, rejection_reason = $4, moderated_at = NOW()
+           WHERE id = $1 AND storage_key = $2"#
+    )
+    .bind(image_id)
+    .bind(storage_key)
+    .bind(status)
+    .bind(rejection_reason)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Most images a profile can have
+pub const MAX_IMAGES: i64 = 6;
+
+/// A processed upload ready to be added to a profile
+pub struct NewImage {
+    /// Media store key prefix the renditions are stored under
+    pub storage_key: String,
+    /// Full-size rendition
+    pub url: String,
+    pub thumbnail_url: String,
+    pub card_url: String,
+    pub blurhash: String,
+    pub width: i32,
+    pub height: i32,
+    pub caption: Option<String>,
+    /// Set for a video slot; the renditions above are then its poster frame
+    pub video: Option<NewVideo>,
+}
+
+/// The clip stored for a video slot (at media::video_key of the slot's storage key)
+pub struct NewVideo {
+    pub url: String,
+    pub duration_ms: i32,
+}
+
+impl NewImage {
+    /// "IMAGE" or "VIDEO"
+    pub fn media_type(&self) -> &'static str {
+        if self.video.is_some() {
+            "VIDEO"
+        } else {
+            "IMAGE"
+        }
+    }
+}
+
+/// Add an image to the end of the user's profile
+/// Returns (id, display_order), None if the user already has MAX_IMAGES
+/// The user row is locked while counting, so concurrent uploads take the slots one at a time
+pub async fn add_image(pool: &PgPool, user_id: &Uuid, image: &NewImage) -> Result<Option<(Uuid, i32)>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user_images WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if count >= MAX_IMAGES {
+        return Ok(None);
     }
 
     let display_order = count as i32;
 
-    sqlx::query(
-        "INSERT INTO user_images (user_id, url, display_order) VALUES ($1, $2, $3)"
+    let row: (Uuid,) = sqlx::query_as(
+        r#"INSERT INTO user_images (user_id, url, storage_key, thumbnail_url, card_url, blurhash, width, height, caption, display_order,
+                                   media_type, video_url, video_duration_ms)
+           VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) RETURNING id"#
     )
     .bind(user_id)
-    .bind(image_url)
+    .bind(&image.url)
+    .bind(&image.storage_key)
+    .bind(&image.thumbnail_url)
+    .bind(&image.card_url)
+    .bind(&image.blurhash)
+    .bind(image.width)
+    .bind(image.height)
+    .bind(&image.caption)
     .bind(display_order)
-    .execute(pool)
+    .bind(image.media_type())
+    .bind(image.video.as_ref().map(|v| &v.url))
+    .bind(image.video.as_ref().map(|v| v.duration_ms))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(Some((row.0, display_order)))
+}
+
+/// Whether an error is the image order constraints catching a change that raced with this one
+/// (checked at commit, e.g. an upload while a delete is moving the other images up)
+pub fn is_order_conflict(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .and_then(|e| e.constraint())
+        .is_some_and(|constraint| constraint.starts_with("user_images_display_order"))
+}
+
+/// Get the user's image that has `url` as one of its renditions
+/// Returns (id, url, display_order)
+pub async fn find_image_by_url(pool: &PgPool, user_id: &Uuid, url: &str) -> Result<Option<(Uuid, String, i32)>, sqlx::Error> {
+    let row: Option<(Uuid, String, i32)> = sqlx::query_as(
+        "SELECT id, url, display_order FROM user_images WHERE user_id = $1 AND $2 IN (url, thumbnail_url, card_url)"
+    )
+    .bind(user_id)
+    .bind(url)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+/// Replace the file behind one of the user's images, keeping its slot (a photo can replace a video and the reverse)
+/// The caption is only changed if the new image has one; the new file goes back to PENDING moderation
+/// Returns (display_order, previous storage_key), None if the image doesn't belong to the user
+pub async fn replace_image(
+    pool: &PgPool,
+    user_id: &Uuid,
+    image_id: &Uuid,
+    image: &NewImage,
+) -> Result<Option<(i32, Option<String>)>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let old: Option<(i32, Option<String>)> = sqlx::query_as(
+        "SELECT display_order, storage_key FROM user_images WHERE id = $1 AND user_id = $2 FOR UPDATE"
+    )
+    .bind(image_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if old.is_none() {
+        return Ok(None);
+    }
+
+    sqlx::query(
+        r#"UPDATE user_images
+           SET url = $3, storage_key = $4, thumbnail_url = $5, card_url = $6, blurhash = $7, width = $8, height = $9,
+               caption = COALESCE($10, caption), media_type = $11, video_url = $12, video_duration_ms = $13, created_at = NOW(),
+               moderation_status = 'PENDING', rejection_reason = NULL, moderated_at = NULL
+           WHERE id = $1 AND user_id = $2"#
+    )
+    .bind(image_id)
+    .bind(user_id)
+    .bind(&image.url)
+    .bind(&image.storage_key)
+    .bind(&image.thumbnail_url)
+    .bind(&image.card_url)
+    .bind(&image.blurhash)
+    .bind(image.width)
+    .bind(image.height)
+    .bind(&image.caption)
+    .bind(image.media_type())
+    .bind(image.video.as_ref().map(|v| &v.url))
+    .bind(image.video.as_ref().map(|v| v.duration_ms))
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(old)
+}
+
+/// Put the user's images in the given order (`image_ids[0]` first)
+/// Returns false if `image_ids` isn't exactly the user's images, each once
+pub async fn reorder_images(pool: &PgPool, user_id: &Uuid, image_ids: &[Uuid]) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let current: Vec<(Uuid,)> = sqlx::query_as("SELECT id FROM user_images WHERE user_id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let mut current: Vec<Uuid> = current.into_iter().map(|r| r.0).collect();
+    let mut requested = image_ids.to_vec();
+    current.sort();
+    requested.sort();
+
+    if current != requested {
+        return Ok(false);
+    }
+
+    // The unique (user_id, display_order) constraint is deferred, so swapping slots is fine
+    sqlx::query(
+        r#"UPDATE user_images ui SET display_order = (o.position - 1)::INT
+           FROM UNNEST($2::UUID[]) WITH ORDINALITY AS o(id, position)
+           WHERE ui.id = o.id AND ui.user_id = $1"#
+    )
+    .bind(user_id)
+    .bind(image_ids)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Renumber the user's images 0..n-1, keeping their order
+/// Call in the same transaction as a delete: the dense-order constraint is checked at commit
+pub async fn compact_order(conn: &mut PgConnection, user_id: &Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"UPDATE user_images ui SET display_order = o.new_order
+           FROM (
+               SELECT id, (ROW_NUMBER() OVER (ORDER BY display_order) - 1)::INT AS new_order
+               FROM user_images WHERE user_id = $1
+           ) o
+           WHERE ui.id = o.id AND ui.display_order <> o.new_order"#
+    )
+    .bind(user_id)
+    .execute(conn)
     .await?;
 
     Ok(())
 }
 
-/// Delete a profile image by display order
-pub async fn delete_profile_images(pool: &PgPool, user_id: &Uuid, display_order: i32) -> Result<(), sqlx::Error> {
-    sqlx::query("DELETE FROM user_images WHERE user_id = $1 AND display_order = $2")
+/// Set or clear (None) an image's caption
+/// Returns false if the image doesn't belong to the user
+pub async fn set_caption(pool: &PgPool, user_id: &Uuid, image_id: &Uuid, caption: Option<&str>) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE user_images SET caption = $3 WHERE id = $1 AND user_id = $2")
+        .bind(image_id)
         .bind(user_id)
-        .bind(display_order)
+        .bind(caption)
         .execute(pool)
         .await?;
 
-    Ok(())
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete one of the user's images, moving the ones after it up
+/// Returns Some(storage_key) so the caller can delete the stored files, None if the image isn't the user's
+pub async fn delete_image(pool: &PgPool, user_id: &Uuid, image_id: &Uuid) -> Result<Option<Option<String>>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let row: Option<(Option<String>,)> = sqlx::query_as(
+        "DELETE FROM user_images WHERE id = $1 AND user_id = $2 RETURNING storage_key"
+    )
+    .bind(image_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if row.is_none() {
+        return Ok(None);
+    }
+
+    compact_order(&mut tx, user_id).await?;
+
+    tx.commit().await?;
+
+    Ok(row.map(|r| r.0))
+}
+
+/// Get the media store key of an image, None if it doesn't exist or predates the media store
+pub async fn get_storage_key(pool: &PgPool, image_id: &Uuid) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(Option<String>,)> = sqlx::query_as("SELECT storage_key FROM user_images WHERE id = $1")
+        .bind(image_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.and_then(|r| r.0))
+}
+
+/// Check that another user may load a stored file: it must belong to an APPROVED image, a voice note or an
+/// APPROVED prompt video, of an active user, with no block between them either way
+/// `parent` is the key's directory, which is the storage key of the image or video prompt a rendition belongs to
+pub async fn media_visible_to(pool: &PgPool, viewer_id: &Uuid, key: &str, parent: &str) -> Result<bool, sqlx::Error> {
+    let row: Option<(Uuid,)> = sqlx::query_as(
+        r#"SELECT owner_id FROM (
+               SELECT user_id AS owner_id FROM user_images
+               WHERE storage_key IN ($2, $3) AND moderation_status = 'APPROVED'
+               UNION ALL
+               SELECT user_id FROM user_prompts
+               WHERE audio_key = $2 OR (video_key = $3 AND video_moderation_status = 'APPROVED')
+           ) media
+           WHERE EXISTS (SELECT 1 FROM users u WHERE u.id = media.owner_id AND u.account_status = 'ACTIVE')
+             AND NOT EXISTS (
+                 SELECT 1 FROM blocks b
+                 WHERE (b.blocker_id = $1 AND b.blocked_id = media.owner_id)
+                    OR (b.blocker_id = media.owner_id AND b.blocked_id = $1)
+             )
+           LIMIT 1"#
+    )
+    .bind(viewer_id)
+    .bind(key)
+    .bind(parent)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.is_some())
+}
+
+/// Get the media store keys of all the user's images
+pub async fn get_storage_keys(pool: &PgPool, user_id: &Uuid) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT storage_key FROM user_images WHERE user_id = $1 AND storage_key IS NOT NULL"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| r.0).collect())
 }
 
 /// Get all images for a user
@@ -56,10 +411,12 @@ pub async fn get_user_images(pool: &PgPool, user_id: &Uuid) -> Result<Vec<(Uuid,
     Ok(rows)
 }
 
-///
-pub async fn get_images(pool: &PgPool, user_id: &Uuid) -> Result<Vec<(Uuid, String, i32)>, sqlx::Error> {
-    let rows: Vec<(Uuid, String, i32)> = sqlx::query_as(
-        "SELECT id, url, display_order FROM user_images WHERE user_id = $1 ORDER BY display_order"
+/// Get all images for a user with their renditions and moderation status (used by the user's own profile view)
+pub async fn get_images(pool: &PgPool, user_id: &Uuid) -> Result<Vec<ImageRow>, sqlx::Error> {
+    let rows: Vec<ImageRow> = sqlx::query_as(
+        r#"SELECT id, url, display_order, thumbnail_url, card_url, blurhash, caption, moderation_status, rejection_reason,
+                  media_type, video_url, video_duration_ms
+           FROM user_images WHERE user_id = $1 ORDER BY display_order"#
     )
     .bind(user_id)
     .fetch_all(pool)
@@ -68,43 +425,18 @@ pub async fn get_images(pool: &PgPool, user_id: &Uuid) -> Result<Vec<(Uuid, Stri
     Ok(rows)
 }
 
-use std::fs;
-use std::path::Path;
-
-use actix_multipart::form::{MultipartForm, json::Json as MpJson, tempfile::TempFile};
-use actix_web::{HttpResponse, Responder};
-use serde::Deserialize;
-
-use crate::models::outputs::StatusResponse;
-
-#[derive(Deserialize, Debug)]
-struct Metadata {
-    name: String,
-}
+/// Get the approved images of several users (what other users get to see)
+/// Returns (user_id, image) ordered by user, then display order
+pub async fn get_approved_images(pool: &PgPool, user_ids: &[Uuid]) -> Result<Vec<(Uuid, ImageRow)>, sqlx::Error> {
+    let rows: Vec<OwnedImageRow> = sqlx::query_as(
+        r#"SELECT user_id, id, url, display_order, thumbnail_url, card_url, blurhash, caption, moderation_status, rejection_reason,
+                  media_type, video_url, video_duration_ms
+           FROM user_images WHERE user_id = ANY($1) AND moderation_status = 'APPROVED'
+           ORDER BY user_id, display_order"#
+    )
+    .bind(user_ids)
+    .fetch_all(pool)
+    .await?;
 
-#[derive(Debug, MultipartForm)]
-pub struct ImageUpload {
-    #[multipart(limit = "50mb")]
-    file: TempFile,
-    metadata: MpJson<Metadata>,
+    Ok(rows.into_iter().map(|r| (r.user_id, r.image)).collect())
 }
-
-pub async fn upload_image_file(MultipartForm(form): MultipartForm<ImageUpload>) -> impl Responder {
-    let file_name = form.file.file_name.as_ref().map(|s| s.as_str()).unwrap_or("file upload error");
-
-    let dest_path = Path::new("./uploads").join(file_name);
-
-    match fs::copy(form.file.file.path(), &dest_path) {
-        Ok(_) => {
-            println!("File Uploaded!!!!");
-            HttpResponse::Ok().json(StatusResponse {
-                status: "success".to_string(),
-                message: Some("File uploaded successfully".to_string()),
-            })
-        },
-        Err(_e) => HttpResponse::InternalServerError().json(StatusResponse {
-            status: "error".to_string(),
-            message: Some("Failed to upload file".to_string()),
-        })
-    }
-}
\ No newline at end of file
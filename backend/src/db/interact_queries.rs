@@ -1,34 +1,489 @@
-use sqlx::PgPool;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
-use crate::models::inputs::InteractRequest;
+use crate::models::inputs::{ContextType, InteractAction, LikesOrder};
+use crate::models::outputs::LikeReceivedRow;
+use crate::models::state::SuperlikeConfig;
 
+/// Result of recording an interaction
+#[derive(Debug)]
+pub enum InteractOutcome {
+    /// Recorded; carries the super-likes left when the action was a SUPERLIKE,
+    /// and the LIKEs left in the quota window when it was a LIKE
+    Recorded { superlikes_remaining: Option<i32>, likes_remaining: Option<i64> },
+    /// A SUPERLIKE was sent with no allowance left (nothing recorded)
+    SuperlikeAllowanceExhausted,
+    /// A LIKE was sent with the daily quota used up (nothing recorded)
+    LikeLimitReached { resets_at: DateTime<Utc> },
+}
+
+/// What the sender can still spend: their super-like allowance and their daily LIKE limit
+#[derive(Debug)]
+pub struct InteractLimits<'a> {
+    pub superlikes: &'a SuperlikeConfig,
+    pub daily_likes: i64,
+}
+
+/// What a like was on: an image or prompt of the target, and the option voted for when the prompt is a poll
+#[derive(Debug)]
+pub struct LikeContext {
+    pub context_type: ContextType,
+    pub context_id: Uuid,
+    pub poll_answer: Option<String>,
+}
+
+/// Record (or replace) the interaction from one user to another
+/// A SUPERLIKE spends one from the user's allowance in the same transaction; a LIKE is counted
+/// against the daily limit with the sender's row locked, so parallel likes can't overshoot it
 pub async fn interact(
     pool: &PgPool,
     from_user_id: &Uuid,
     to_user_id: &Uuid,
-    body: &InteractRequest,
-) -> Result<(), sqlx::Error> {
-    // Extract context_type and context_id from the optional context
-    let (context_type, context_id) = match &body.context {
-        Some(ctx) => (Some(ctx.r#type.clone()), Some(ctx.id.clone())),
-        None => (None, None),
+    action: InteractAction,
+    context: Option<LikeContext>,
+    comment: Option<&str>,
+    limits: &InteractLimits<'_>,
+) -> Result<InteractOutcome, sqlx::Error> {
+    // Split the optional context into context_type, context_id and poll_answer columns
+    let (context_type, context_id, poll_answer) = match context {
+        Some(ctx) => (Some(ctx.context_type.as_str()), Some(ctx.context_id.to_string()), ctx.poll_answer),
+        None => (None, None, None),
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let likes_remaining = if action == InteractAction::Like {
+        sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+            .bind(from_user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let (used, _, resets_at) = like_usage(&mut tx, from_user_id).await?;
+        if used >= limits.daily_likes {
+            return Ok(InteractOutcome::LikeLimitReached { resets_at });
+        }
+        Some(limits.daily_likes - used - 1)
+    } else {
+        None
+    };
+
+    let superlikes_remaining = if action == InteractAction::SuperLike {
+        match spend_superlike(&mut tx, from_user_id, limits.superlikes).await? {
+            Some(remaining) => Some(remaining),
+            None => return Ok(InteractOutcome::SuperlikeAllowanceExhausted),
+        }
+    } else {
+        None
     };
 
     sqlx::query(
-        r#"INSERT INTO interactions (from_user_id, to_user_id, action, context_type, context_id, comment) 
-           VALUES ($1, $2, $3, $4, $5, $6)
+        r#"INSERT INTO interactions (from_user_id, to_user_id, action, context_type, context_id, comment, poll_answer) 
+           VALUES ($1, $2, $3, $4, $5, $6, $7)
            ON CONFLICT (from_user_id, to_user_id) 
-           DO UPDATE SET action = $3, context_type = $4, context_id = $5, comment = $6"#
+           DO UPDATE SET action = $3, context_type = $4, context_id = $5, comment = $6, poll_answer = $7"#
+    )
+    .bind(from_user_id)
+    .bind(to_user_id)
+    .bind(action.as_str())
+    .bind(context_type)
+    .bind(&context_id)
+    .bind(comment)
+    .bind(&poll_answer)
+    .execute(&mut *tx)
+    .await?;
+
+    // Keep every interaction in the history so undos can be audited
+    sqlx::query(
+        r#"INSERT INTO interaction_history (from_user_id, to_user_id, action, context_type, context_id, comment, poll_answer)
+           VALUES ($1, $2, $3, $4, $5, $6, $7)"#
     )
     .bind(from_user_id)
     .bind(to_user_id)
-    .bind(&body.action)
-    .bind(&context_type)
+    .bind(action.as_str())
+    .bind(context_type)
     .bind(&context_id)
-    .bind(&body.comment)
-    .execute(pool)
+    .bind(comment)
+    .bind(&poll_answer)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(InteractOutcome::Recorded { superlikes_remaining, likes_remaining })
+}
+
+/// Top up the allowance if the replenish period has passed, then spend one super-like
+/// Returns the number left, or None if there were none to spend
+async fn spend_superlike(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: &Uuid,
+    config: &SuperlikeConfig,
+) -> Result<Option<i32>, sqlx::Error> {
+    sqlx::query(
+        r#"INSERT INTO superlike_allowances (user_id, remaining, replenished_at) VALUES ($1, $2, NOW())
+           ON CONFLICT (user_id) DO UPDATE
+           SET remaining = $2, replenished_at = NOW()
+           WHERE superlike_allowances.replenished_at <= NOW() - make_interval(hours => $3::INT)"#
+    )
+    .bind(user_id)
+    .bind(config.allowance)
+    .bind(config.replenish_hours)
+    .execute(&mut **tx)
+    .await?;
+
+    let row: Option<(i32,)> = sqlx::query_as(
+        "UPDATE superlike_allowances SET remaining = remaining - 1 WHERE user_id = $1 AND remaining > 0 RETURNING remaining"
+    )
+    .bind(user_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    Ok(row.map(|r| r.0))
+}
+
+/// Get which of `user_ids` have super-liked the user
+pub async fn get_superlikers(pool: &PgPool, user_id: &Uuid, user_ids: &[Uuid]) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows: Vec<(Uuid,)> = sqlx::query_as(
+        "SELECT from_user_id FROM interactions WHERE to_user_id = $1 AND from_user_id = ANY($2) AND action = 'SUPERLIKE'"
+    )
+    .bind(user_id)
+    .bind(user_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| r.0).collect())
+}
+
+/// Count the LIKEs in the user's current quota window
+/// A window runs from one local midnight to the next, in the time zone in effect when it opened;
+/// once it has ended the next one opens from the current time zone, never overlapping the last
+/// Returns (likes used in the window, granted daily limit, when the window ends)
+pub async fn get_like_usage(
+    pool: &PgPool,
+    user_id: &Uuid,
+) -> Result<(i64, Option<i32>, DateTime<Utc>), sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    like_usage(&mut conn, user_id).await
+}
+
+/// Same as get_like_usage, on the caller's connection so it can join a transaction
+async fn like_usage(
+    conn: &mut PgConnection,
+    user_id: &Uuid,
+) -> Result<(i64, Option<i32>, DateTime<Utc>), sqlx::Error> {
+    sqlx::query(
+        r#"UPDATE users
+           SET like_window_start = GREATEST(
+                   date_trunc('day', NOW() AT TIME ZONE time_zone) AT TIME ZONE time_zone,
+                   COALESCE(like_window_end, '-infinity')
+               ),
+               like_window_end = (date_trunc('day', NOW() AT TIME ZONE time_zone) + INTERVAL '1 day') AT TIME ZONE time_zone
+           WHERE id = $1 AND (like_window_end IS NULL OR like_window_end <= NOW())"#
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    let row: (i64, Option<i32>, DateTime<Utc>) = sqlx::query_as(
+        r#"SELECT
+               (SELECT COUNT(*) FROM interaction_history h
+                WHERE h.from_user_id = u.id
+                  AND h.action = 'LIKE'
+                  AND h.undone_at IS NULL
+                  AND h.created_at >= u.like_window_start),
+               u.daily_like_limit,
+               u.like_window_end
+           FROM users u
+           WHERE u.id = $1"#
+    )
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(row)
+}
+
+/// Result of trying to undo the user's last interaction
+#[derive(Debug)]
+pub enum UndoOutcome {
+    /// The interaction was reverted; carries the undos left today
+    Undone { to_user_id: Uuid, action: String, undos_remaining: i64 },
+    /// The daily undo limit is used up (nothing undone)
+    LimitReached,
+    /// The user has no interaction left to undo
+    NothingToUndo,
+    /// The last interaction is older than the undo window
+    WindowExpired,
+    /// The last interaction was a like that already became a match
+    AlreadyMatched,
+}
+
+/// Undo the user's most recent interaction if it is younger than `window_secs`
+/// and fewer than `daily_limit` undos were made since midnight in the user's time zone
+/// The user's row is locked while counting, so parallel undos can't overshoot the limit
+/// The history row is kept and stamped with `undone_at`; the pair's current state goes back to
+/// the previous interaction in the history, or is removed if there was none
+pub async fn undo_last_interaction(
+    pool: &PgPool,
+    user_id: &Uuid,
+    window_secs: i64,
+    daily_limit: i64,
+    superlikes: &SuperlikeConfig,
+) -> Result<UndoOutcome, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let (used_today,): (i64,) = sqlx::query_as(
+        r#"SELECT COUNT(*) FROM interaction_history h
+           JOIN users u ON u.id = h.from_user_id
+           WHERE h.from_user_id = $1
+             AND h.undone_at >= date_trunc('day', NOW() AT TIME ZONE u.time_zone) AT TIME ZONE u.time_zone"#
+    )
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if used_today >= daily_limit {
+        return Ok(UndoOutcome::LimitReached);
+    }
+
+    let last: Option<(Uuid, Uuid, String, DateTime<Utc>, bool)> = sqlx::query_as(
+        r#"SELECT id, to_user_id, action, created_at, created_at > NOW() - make_interval(secs => $2::FLOAT8) AS in_window
+           FROM interaction_history
+           WHERE from_user_id = $1 AND undone_at IS NULL
+           ORDER BY created_at DESC
+           LIMIT 1
+           FOR UPDATE"#
+    )
+    .bind(user_id)
+    .bind(window_secs as f64)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((history_id, to_user_id, action, created_at, in_window)) = last else {
+        return Ok(UndoOutcome::NothingToUndo);
+    };
+
+    if !in_window {
+        return Ok(UndoOutcome::WindowExpired);
+    }
+
+    let matched: Option<(Uuid,)> = sqlx::query_as(
+        "SELECT id FROM matches WHERE user1_id = LEAST($1::UUID, $2::UUID) AND user2_id = GREATEST($1::UUID, $2::UUID)"
+    )
+    .bind(user_id)
+    .bind(to_user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if matched.is_some() {
+        return Ok(UndoOutcome::AlreadyMatched);
+    }
+
+    sqlx::query("UPDATE interaction_history SET undone_at = NOW() WHERE id = $1")
+        .bind(history_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // Restore the previous interaction with the same person, if any
+    let restored = sqlx::query(
+        r#"UPDATE interactions i
+           SET action = h.action, context_type = h.context_type, context_id = h.context_id, comment = h.comment,
+               poll_answer = h.poll_answer
+           FROM (
+               SELECT action, context_type, context_id, comment, poll_answer
+               FROM interaction_history
+               WHERE from_user_id = $1 AND to_user_id = $2 AND undone_at IS NULL
+               ORDER BY created_at DESC
+               LIMIT 1
+           ) h
+           WHERE i.from_user_id = $1 AND i.to_user_id = $2"#
+    )
+    .bind(user_id)
+    .bind(to_user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    // Otherwise the pair goes back to having no interaction
+    if restored.rows_affected() == 0 {
+        sqlx::query("DELETE FROM interactions WHERE from_user_id = $1 AND to_user_id = $2")
+            .bind(user_id)
+            .bind(to_user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // An undone super-like goes back into the allowance, unless it was spent from an allowance
+    // that has since been topped up; the refund never takes it above the full allowance
+    if action == InteractAction::SuperLike.as_str() {
+        sqlx::query(
+            r#"UPDATE superlike_allowances SET remaining = LEAST(remaining + 1, $2)
+               WHERE user_id = $1 AND replenished_at <= $3"#
+        )
+        .bind(user_id)
+        .bind(superlikes.allowance)
+        .bind(created_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(UndoOutcome::Undone { to_user_id, action, undos_remaining: daily_limit - used_today - 1 })
+}
+
+/// What a like did to the pair
+#[derive(Debug)]
+pub enum MatchOutcome {
+    /// No like back (or a block between them): nothing happens
+    NotMutual,
+    /// The two are matched (a new match, or the one they already had)
+    Matched(Uuid),
+    /// A like back from a user who isn't active (e.g. shadow-banned): no match is created,
+    /// but their own app should carry on as if there was one
+    Withheld,
+}
+
+/// If `to_user_id` has already liked `from_user_id` (and neither blocked the other), create their match
+/// Only matches two active users; a like back from an inactive user is Withheld, and a like back
+/// to one is NotMutual so the sender can't tell
+/// A new match opens its chat with the poll votes the two likes carried, from whoever voted, oldest first
+pub async fn create_match_if_mutual(
+    pool: &PgPool,
+    from_user_id: &Uuid,
+    to_user_id: &Uuid,
+) -> Result<MatchOutcome, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // (sender active, target active) when the like is mutual
+    let mutual: Option<(bool, bool)> = sqlx::query_as(
+        r#"SELECT
+               EXISTS (SELECT 1 FROM users WHERE id = $1 AND account_status = 'ACTIVE'),
+               EXISTS (SELECT 1 FROM users WHERE id = $2 AND account_status = 'ACTIVE')
+           WHERE EXISTS (
+               SELECT 1 FROM interactions
+               WHERE from_user_id = $2 AND to_user_id = $1 AND action IN ('LIKE', 'SUPERLIKE')
+           )
+           AND NOT EXISTS (
+               SELECT 1 FROM blocks b
+               WHERE (b.blocker_id = $1 AND b.blocked_id = $2) OR (b.blocker_id = $2 AND b.blocked_id = $1)
+           )"#
+    )
+    .bind(from_user_id)
+    .bind(to_user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    match mutual {
+        Some((true, true)) => {}
+        Some((false, _)) => return Ok(MatchOutcome::Withheld),
+        Some((true, false)) | None => return Ok(MatchOutcome::NotMutual),
+    }
+
+    // Pairs are stored with the smaller id first so UNIQUE(user1_id, user2_id) holds
+    // xmax is 0 on a freshly inserted row, and set when the conflict clause touched an existing match
+    let (match_id, created): (Uuid, bool) = sqlx::query_as(
+        r#"INSERT INTO matches (user1_id, user2_id)
+           VALUES (LEAST($1::UUID, $2::UUID), GREATEST($1::UUID, $2::UUID))
+           ON CONFLICT (user1_id, user2_id) DO UPDATE SET user1_id = matches.user1_id
+           RETURNING id, xmax = 0 AS created"#
+    )
+    .bind(from_user_id)
+    .bind(to_user_id)
+    .fetch_one(&mut *tx)
     .await?;
 
-    Ok(())
+    if created {
+        // The question is looked up now; the vote itself was copied when the like was sent
+        sqlx::query(
+            r#"INSERT INTO messages (match_id, sender_id, text, created_at)
+               SELECT $1, i.from_user_id,
+                      CONCAT('Voted "', i.poll_answer, '"', ' on "' || pq.text || '"', E'\n' || i.comment),
+                      i.created_at
+               FROM interactions i
+               LEFT JOIN user_prompts up
+                   ON i.context_type = 'PROMPT' AND up.id::TEXT = i.context_id AND up.user_id = i.to_user_id
+               LEFT JOIN prompt_questions pq ON pq.id = up.question_id
+               WHERE ((i.from_user_id = $2 AND i.to_user_id = $3) OR (i.from_user_id = $3 AND i.to_user_id = $2))
+                 AND i.action IN ('LIKE', 'SUPERLIKE')
+                 AND i.poll_answer IS NOT NULL
+               ORDER BY i.created_at"#
+        )
+        .bind(match_id)
+        .bind(from_user_id)
+        .bind(to_user_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(MatchOutcome::Matched(match_id))
+}
+
+/// Get the likes the user has received and not yet responded to
+/// Includes the liked image or prompt (which belong to the user) and the comment
+pub async fn get_likes_received(
+    pool: &PgPool,
+    user_id: &Uuid,
+    order: LikesOrder,
+) -> Result<Vec<LikeReceivedRow>, sqlx::Error> {
+    // Super-likes always come first
+    let order_by = match order {
+        LikesOrder::Recent => "is_superlike DESC, i.created_at DESC",
+        LikesOrder::Oldest => "is_superlike DESC, i.created_at ASC",
+    };
+
+    let query = format!(
+        r#"
+        SELECT p.user_id::TEXT as user_id, p.name, p.bio, p.birthdate::TEXT, p.pronouns, p.gender, p.sexuality, p.height,
+            NULL as location, p.job, p.company, p.school, p.ethnicity, p.politics, p.religion,
+            p.relationship_type, p.dating_intention, p.drinks, p.smokes, p.hidden_fields,
+            (i.action = 'SUPERLIKE') AS is_superlike,
+            i.context_type, i.context_id, i.comment, i.poll_answer, i.created_at,
+            ui.url AS image_url, ui.display_order AS image_order,
+            ui.thumbnail_url AS image_thumbnail_url, ui.card_url AS image_card_url, ui.blurhash AS image_blurhash, ui.caption AS image_caption,
+            ui.media_type AS image_media_type, ui.video_url AS image_video_url, ui.video_duration_ms AS image_video_duration_ms,
+            up.question_id AS prompt_question_id, pq.text AS prompt_question, up.answer AS prompt_answer,
+            up.audio_url AS prompt_audio_url, up.audio_duration_ms AS prompt_audio_duration_ms,
+            up.video_url AS prompt_video_url, up.video_poster_url AS prompt_video_poster_url,
+            up.video_duration_ms AS prompt_video_duration_ms, up.poll_options AS prompt_poll_options,
+            up.display_order AS prompt_order
+        FROM interactions i
+        JOIN profiles p ON p.user_id = i.from_user_id
+        LEFT JOIN user_images ui
+            ON i.context_type = 'IMAGE' AND ui.id::TEXT = i.context_id AND ui.user_id = i.to_user_id
+        LEFT JOIN user_prompts up
+            ON i.context_type = 'PROMPT' AND up.id::TEXT = i.context_id AND up.user_id = i.to_user_id
+        LEFT JOIN prompt_questions pq ON pq.id = up.question_id
+        WHERE i.to_user_id = $1
+          AND i.action IN ('LIKE', 'SUPERLIKE')
+          AND NOT EXISTS (
+              SELECT 1 FROM interactions r
+              WHERE r.from_user_id = $1 AND r.to_user_id = i.from_user_id
+          )
+          AND NOT EXISTS (
+              SELECT 1 FROM blocks b
+              WHERE (b.blocker_id = $1 AND b.blocked_id = i.from_user_id)
+                 OR (b.blocker_id = i.from_user_id AND b.blocked_id = $1)
+          )
+          AND EXISTS (
+              SELECT 1 FROM users lu WHERE lu.id = i.from_user_id AND lu.account_status = 'ACTIVE'
+          )
+        ORDER BY {}
+    "#,
+        order_by
+    );
+
+    let rows = sqlx::query_as::<_, LikeReceivedRow>(&query)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows)
 }
@@ -3,4 +3,8 @@ pub mod profile_queries;
 pub mod images_queries;
 pub mod prompt_queries;
 pub mod seed;
-pub mod interact_queries;
\ No newline at end of file
+pub mod interact_queries;
+pub mod feed_queries;
+pub mod block_queries;
+pub mod report_queries;
+pub mod option_queries;
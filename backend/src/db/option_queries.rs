@@ -0,0 +1,8 @@
+use sqlx::PgPool;
+
+/// Get every allowed profile option as (field, value), in display order
+pub async fn get_profile_options(pool: &PgPool) -> Result<Vec<(String, String)>, sqlx::Error> {
+    sqlx::query_as("SELECT field, value FROM profile_options ORDER BY field, display_order")
+        .fetch_all(pool)
+        .await
+}
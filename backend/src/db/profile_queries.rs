@@ -1,30 +1,35 @@
+use crate::db::user_queries;
 use crate::models::inputs::UpdateProfileRequest;
 use crate::models::outputs::{ProfileDetails, SuggestionProfile};
-use sqlx::PgPool;
+use crate::routes::feed::ranker::{Candidate, Viewer};
+use chrono::NaiveDate;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
 /// Check if a profile exists for a user
-pub async fn check_profile_exists(pool: &PgPool, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+pub async fn check_profile_exists(conn: &mut PgConnection, user_id: &Uuid) -> Result<bool, sqlx::Error> {
     let row: Option<(Uuid,)> = sqlx::query_as("SELECT user_id FROM profiles WHERE user_id = $1")
         .bind(user_id)
-        .fetch_optional(pool)
+        .fetch_optional(conn)
         .await?;
 
     Ok(row.is_some())
 }
 
 /// Create a new profile for a user
+/// `birthdate` is the validated `req.birthdate`
 pub async fn create_profile(
-    pool: &PgPool,
+    conn: &mut PgConnection,
     user_id: &Uuid,
     req: &UpdateProfileRequest,
+    birthdate: Option<NaiveDate>,
 ) -> Result<(), sqlx::Error> {
     sqlx::query(
         r#"INSERT INTO profiles (
             user_id, name, bio, pronouns, gender, sexuality, height, 
             job, company, school, ethnicity, politics, religion,
-            relationship_type, dating_intention, drinks, smokes
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)"#,
+            relationship_type, dating_intention, drinks, smokes, birthdate
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)"#,
     )
     .bind(user_id)
     .bind(&req.name)
@@ -43,17 +48,20 @@ pub async fn create_profile(
     .bind(&req.dating_intention)
     .bind(&req.drinks)
     .bind(&req.smokes)
-    .execute(pool)
+    .bind(birthdate)
+    .execute(conn)
     .await?;
 
     Ok(())
 }
 
 /// Update an existing profile
+/// Setting the birthdate uses up a support unlock
 pub async fn update_profile(
-    pool: &PgPool,
+    conn: &mut PgConnection,
     user_id: &Uuid,
     req: &UpdateProfileRequest,
+    birthdate: Option<NaiveDate>,
 ) -> Result<(), sqlx::Error> {
     sqlx::query(
         r#"UPDATE profiles SET
@@ -72,7 +80,9 @@ pub async fn update_profile(
             relationship_type = COALESCE($14, relationship_type),
             dating_intention = COALESCE($15, dating_intention),
             drinks = COALESCE($16, drinks),
-            smokes = COALESCE($17, smokes)
+            smokes = COALESCE($17, smokes),
+            birthdate = COALESCE($18, birthdate),
+            birthdate_unlocked = birthdate_unlocked AND $18::DATE IS NULL
         WHERE user_id = $1"#,
     )
     .bind(user_id)
@@ -92,27 +102,41 @@ pub async fn update_profile(
     .bind(&req.dating_intention)
     .bind(&req.drinks)
     .bind(&req.smokes)
-    .execute(pool)
+    .bind(birthdate)
+    .execute(conn)
     .await?;
 
     Ok(())
 }
 
 /// Create or update a profile (upsert)
+/// `visibility` is (hide, show) and, like the time zone (kept on the users table), is applied in the same transaction as the fields
 pub async fn upsert_profile(
     pool: &PgPool,
     user_id: &Uuid,
     req: &UpdateProfileRequest,
+    birthdate: Option<NaiveDate>,
+    visibility: Option<(&[String], &[String])>,
 ) -> Result<bool, sqlx::Error> {
-    let exists = check_profile_exists(pool, user_id).await?;
+    let mut tx = pool.begin().await?;
 
+    let exists = check_profile_exists(&mut tx, user_id).await?;
     if exists {
-        update_profile(pool, user_id, req).await?;
-        Ok(false) // was not new
+        update_profile(&mut tx, user_id, req, birthdate).await?;
     } else {
-        create_profile(pool, user_id, req).await?;
-        Ok(true) // was new
+        create_profile(&mut tx, user_id, req, birthdate).await?;
+    }
+
+    if let Some((hide, show)) = visibility {
+        set_field_visibility(&mut tx, user_id, hide, show).await?;
+    }
+
+    if let Some(time_zone) = &req.time_zone {
+        user_queries::update_time_zone(&mut tx, user_id, time_zone).await?;
     }
+
+    tx.commit().await?;
+    Ok(!exists)
 }
 
 /// Delete user account (profile + user)
@@ -154,6 +178,7 @@ pub async fn check_profile_attributes_filled(
         SELECT 
             (CASE WHEN name IS NULL THEN 1 ELSE 0 END +
              CASE WHEN bio IS NULL THEN 1 ELSE 0 END +
+             CASE WHEN birthdate IS NULL THEN 1 ELSE 0 END +
              CASE WHEN pronouns IS NULL THEN 1 ELSE 0 END +
              CASE WHEN gender IS NULL THEN 1 ELSE 0 END +
              CASE WHEN sexuality IS NULL THEN 1 ELSE 0 END +
@@ -182,9 +207,9 @@ pub async fn check_profile_attributes_filled(
 pub async fn get_profile(pool: &PgPool, user_id: &Uuid) -> Result<ProfileDetails, sqlx::Error> {
     let row = sqlx::query_as::<_, ProfileDetails>(
         r#"
-        SELECT name, bio, birthdate::TEXT, pronouns, gender, sexuality, height,
+        SELECT name, bio, birthdate::TEXT, DATE_PART('year', AGE(birthdate))::INT AS age, pronouns, gender, sexuality, height,
             NULL as location, job, company, school, ethnicity, politics, religion,
-            relationship_type, dating_intention, drinks, smokes
+            relationship_type, dating_intention, drinks, smokes, hidden_fields
         FROM profiles WHERE user_id = $1
     "#,
     )
@@ -195,46 +220,135 @@ pub async fn get_profile(pool: &PgPool, user_id: &Uuid) -> Result<ProfileDetails
     Ok(row)
 }
 
-/// Get profile suggestions based on user preferences
-/// For now: filters by gender_preference only, excludes current user
-pub async fn get_suggestions(
+/// Get the viewer's own matching signals for ranking
+/// Location is stored as POINT(longitude, latitude)
+pub async fn get_viewer(pool: &PgPool, user_id: &Uuid) -> Result<Viewer, sqlx::Error> {
+    let row = sqlx::query_as::<_, Viewer>(
+        r#"
+        SELECT relationship_type, dating_intention,
+            location[1] AS latitude, location[0] AS longitude
+        FROM profiles WHERE user_id = $1
+    "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.unwrap_or_default())
+}
+
+/// Get feed candidates with their ranking signals
+/// Filters by gender_preference (if any), excludes current user, anyone they already liked or passed, blocks either way, banned/shadow-banned users and incomplete profiles
+pub async fn get_feed_candidates(
     pool: &PgPool,
     gender_preference: Option<Vec<String>>,
     user_id: &Uuid,
+    limit: i64,
+) -> Result<Vec<Candidate>, sqlx::Error> {
+    let candidates = sqlx::query_as::<_, Candidate>(
+        r#"
+        SELECT p.user_id::TEXT as user_id, p.name, p.bio, p.birthdate::TEXT, p.pronouns, p.gender, p.sexuality, p.height,
+            NULL as location, p.job, p.company, p.school, p.ethnicity, p.politics, p.religion,
+            p.relationship_type, p.dating_intention, p.drinks, p.smokes, p.hidden_fields,
+            u.last_active,
+            p.location[1] AS latitude, p.location[0] AS longitude,
+            (SELECT COUNT(*) FROM user_images i WHERE i.user_id = p.user_id AND i.moderation_status = 'APPROVED') AS image_count,
+            (SELECT COUNT(*) FROM user_prompts up WHERE up.user_id = p.user_id
+                AND (up.answer IS NOT NULL OR up.audio_url IS NOT NULL OR up.poll_options IS NOT NULL
+                     OR up.video_moderation_status = 'APPROVED')) AS prompt_count,
+            EXISTS (
+                SELECT 1 FROM interactions l
+                WHERE l.from_user_id = p.user_id AND l.to_user_id = $2 AND l.action IN ('LIKE', 'SUPERLIKE')
+            ) AS liked_viewer,
+            COALESCE((
+                SELECT AVG(CASE WHEN a.action IN ('LIKE', 'SUPERLIKE') THEN 1.0 ELSE 0.0 END)::FLOAT8
+                FROM interactions a WHERE a.from_user_id = p.user_id
+            ), 0.0) AS like_rate
+        FROM profiles p
+        JOIN users u ON u.id = p.user_id
+        WHERE ($1::TEXT[] IS NULL OR p.gender = ANY($1)) AND p.user_id != $2
+          AND NOT EXISTS (
+              SELECT 1 FROM interactions x WHERE x.from_user_id = $2 AND x.to_user_id = p.user_id
+          )
+          AND NOT EXISTS (
+              SELECT 1 FROM blocks b
+              WHERE (b.blocker_id = $2 AND b.blocked_id = p.user_id)
+                 OR (b.blocker_id = p.user_id AND b.blocked_id = $2)
+          )
+          AND u.account_status = 'ACTIVE'
+          AND u.is_profile_complete
+        ORDER BY u.last_active DESC NULLS LAST, p.user_id
+        LIMIT $3
+    "#,
+    )
+    .bind(&gender_preference)
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(candidates)
+}
+
+/// Get profiles by user id, in the order of `user_ids`
+/// Ids without a profile (e.g. deleted accounts) are skipped
+pub async fn get_profiles_by_ids(
+    pool: &PgPool,
+    user_ids: &[Uuid],
 ) -> Result<Vec<SuggestionProfile>, sqlx::Error> {
-    // If gender_preference is provided, filter by it; otherwise return all profiles
-    let profiles = if let Some(genders) = gender_preference {
-        // Filter profiles where gender is in the preference list
-        sqlx::query_as::<_, SuggestionProfile>(
-            r#"
-            SELECT user_id::TEXT as user_id, name, bio, birthdate::TEXT, pronouns, gender, sexuality, height,
-                NULL as location, job, company, school, ethnicity, politics, religion,
-                relationship_type, dating_intention, drinks, smokes
-            FROM profiles 
-            WHERE gender = ANY($1) AND user_id != $2
-            LIMIT 20
-        "#,
-        )
-        .bind(&genders)
+    let profiles = sqlx::query_as::<_, SuggestionProfile>(
+        r#"
+        SELECT p.user_id::TEXT as user_id, p.name, p.bio, p.birthdate::TEXT, p.pronouns, p.gender, p.sexuality, p.height,
+            NULL as location, p.job, p.company, p.school, p.ethnicity, p.politics, p.religion,
+            p.relationship_type, p.dating_intention, p.drinks, p.smokes, p.hidden_fields
+        FROM UNNEST($1::UUID[]) WITH ORDINALITY AS ids(user_id, position)
+        JOIN profiles p ON p.user_id = ids.user_id
+        ORDER BY ids.position
+    "#,
+    )
+    .bind(user_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(profiles)
+}
+
+/// Get the stored birthdate and whether support has unlocked it for one more change
+/// Returns None if the user has no profile yet
+pub async fn get_birthdate_lock(pool: &PgPool, user_id: &Uuid) -> Result<Option<(Option<NaiveDate>, bool)>, sqlx::Error> {
+    sqlx::query_as("SELECT birthdate, birthdate_unlocked FROM profiles WHERE user_id = $1")
         .bind(user_id)
-        .fetch_all(pool)
-        .await?
-    } else {
-        // No preference - return all profiles except current user
-        sqlx::query_as::<_, SuggestionProfile>(
-            r#"
-            SELECT user_id::TEXT as user_id, name, bio, birthdate::TEXT, pronouns, gender, sexuality, height,
-                NULL as location, job, company, school, ethnicity, politics, religion,
-                relationship_type, dating_intention, drinks, smokes
-            FROM profiles 
-            WHERE user_id != $1
-            LIMIT 20
-        "#,
-        )
+        .fetch_optional(pool)
+        .await
+}
+
+/// Allow the user to change their birthdate once more
+/// Returns false if the user has no profile
+pub async fn unlock_birthdate(pool: &PgPool, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE profiles SET birthdate_unlocked = TRUE WHERE user_id = $1")
         .bind(user_id)
-        .fetch_all(pool)
-        .await?
-    };
+        .execute(pool)
+        .await?;
 
-    Ok(profiles)
+    Ok(result.rows_affected() > 0)
+}
+
+/// Hide and show profile fields for other users, leaving the other flags as they are
+pub async fn set_field_visibility(conn: &mut PgConnection, user_id: &Uuid, hide: &[String], show: &[String]) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"UPDATE profiles
+           SET hidden_fields = ARRAY(
+               SELECT DISTINCT f FROM UNNEST(hidden_fields || $2::TEXT[]) AS f
+               WHERE f <> ALL($3::TEXT[])
+               ORDER BY f
+           )
+           WHERE user_id = $1"#
+    )
+    .bind(user_id)
+    .bind(hide)
+    .bind(show)
+    .execute(conn)
+    .await?;
+
+    Ok(())
 }
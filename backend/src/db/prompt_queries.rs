@@ -1,6 +1,8 @@
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
+use crate::models::outputs::{CatalogRow, PromptRow};
+
 /// Count the number of prompts for a user
 pub async fn count_prompts(pool: &PgPool, user_id: &Uuid) -> Result<i64, sqlx::Error> {
     let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user_prompts WHERE user_id = $1")
@@ -11,46 +13,192 @@ pub async fn count_prompts(pool: &PgPool, user_id: &Uuid) -> Result<i64, sqlx::E
     Ok(row.0)
 }
 
+/// Check that a prompt belongs to the user
+pub async fn prompt_belongs_to(pool: &PgPool, prompt_id: &Uuid, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+    let row: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM user_prompts WHERE id = $1 AND user_id = $2")
+        .bind(prompt_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.is_some())
+}
+
+/// Get every active catalog question with its category, in display order
+pub async fn get_catalog(pool: &PgPool) -> Result<Vec<CatalogRow>, sqlx::Error> {
+    sqlx::query_as(
+        r#"SELECT c.id AS category_id, c.name AS category_name, q.id, q.text, q.kind
+           FROM prompt_questions q
+           JOIN prompt_categories c ON c.id = q.category_id
+           WHERE q.is_active
+           ORDER BY c.display_order, q.display_order"#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// How a catalog question is answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionKind {
+    /// Text, a voice note or a video
+    Text,
+    /// 2-4 options viewers vote on
+    Poll,
+}
+
+impl QuestionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuestionKind::Text => "TEXT",
+            QuestionKind::Poll => "POLL",
+        }
+    }
+}
+
+/// Find an active catalog question of the given kind by id, or else by its text (matched case-insensitively)
+/// Returns the question id
+pub async fn find_active_question(
+    pool: &PgPool,
+    id: Option<&str>,
+    text: Option<&str>,
+    kind: QuestionKind,
+) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> = sqlx::query_as(
+        r#"SELECT id FROM prompt_questions
+           WHERE is_active AND kind = $3 AND (id = $1 OR ($1 IS NULL AND LOWER(text) = LOWER(TRIM($2))))"#
+    )
+    .bind(id)
+    .bind(text)
+    .bind(kind.as_str())
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| r.0))
+}
+
+/// A stored voice note answering a prompt
+pub struct NewAudio {
+    /// Media store key of the recording
+    pub key: String,
+    pub url: String,
+    pub duration_ms: i32,
+}
+
+/// A stored video answering a prompt
+pub struct NewPromptVideo {
+    /// Media store key prefix: the clip is at media::video_key, its poster frame's renditions next to it
+    pub key: String,
+    pub url: String,
+    pub poster_url: String,
+    pub duration_ms: i32,
+}
+
+/// What a prompt is answered with besides text: a voice note or a video, never both
+pub enum PromptRecording {
+    Voice(NewAudio),
+    Video(NewPromptVideo),
+}
+
+impl PromptRecording {
+    fn audio(&self) -> Option<&NewAudio> {
+        match self {
+            PromptRecording::Voice(audio) => Some(audio),
+            PromptRecording::Video(_) => None,
+        }
+    }
+
+    fn video(&self) -> Option<&NewPromptVideo> {
+        match self {
+            PromptRecording::Voice(_) => None,
+            PromptRecording::Video(video) => Some(video),
+        }
+    }
+
+    /// Keys of the stored files, to discard them if the prompt can't be saved
+    pub fn media_keys(&self) -> PromptMediaKeys {
+        PromptMediaKeys {
+            audio_key: self.audio().map(|a| a.key.clone()),
+            video_key: self.video().map(|v| v.key.clone()),
+        }
+    }
+}
+
+/// Media store keys of a prompt's recordings, for deleting the files once the prompt no longer points at them
+#[derive(Debug, Default, sqlx::FromRow)]
+pub struct PromptMediaKeys {
+    pub audio_key: Option<String>,
+    /// Key prefix of a video (see NewPromptVideo)
+    pub video_key: Option<String>,
+}
+
 /// Insert a new prompt for a user (max 3 prompts allowed)
+/// Needs a text answer, a recording or both, or else poll options alone; a video starts PENDING moderation
+/// Counts and inserts with the user's row locked, so parallel uploads can't add a 4th prompt
 pub async fn insert_prompt(
     pool: &PgPool,
     user_id: &Uuid,
-    question: &str,
-    answer: &str,
+    question_id: &str,
+    answer: Option<&str>,
+    recording: Option<&PromptRecording>,
+    poll_options: Option<&[String]>,
 ) -> Result<(), sqlx::Error> {
-    let count = count_prompts(pool, user_id).await?;
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user_prompts WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
 
     if count >= 3 {
         return Err(sqlx::Error::Protocol("Maximum 3 prompts allowed".to_string()));
     }
 
     let display_order = count as i32;
+    let audio = recording.and_then(|r| r.audio());
+    let video = recording.and_then(|r| r.video());
 
     sqlx::query(
-        "INSERT INTO user_prompts (user_id, question, answer, display_order) VALUES ($1, $2, $3, $4)"
+        r#"INSERT INTO user_prompts (user_id, question_id, answer, audio_key, audio_url, audio_duration_ms,
+                                    video_key, video_url, video_poster_url, video_duration_ms, video_moderation_status,
+                                    poll_options, display_order)
+           VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, CASE WHEN $7::TEXT IS NOT NULL THEN 'PENDING' END, $11, $12)"#
     )
     .bind(user_id)
-    .bind(question)
+    .bind(question_id)
     .bind(answer)
+    .bind(audio.map(|a| &a.key))
+    .bind(audio.map(|a| &a.url))
+    .bind(audio.map(|a| a.duration_ms))
+    .bind(video.map(|v| &v.key))
+    .bind(video.map(|v| &v.url))
+    .bind(video.map(|v| &v.poster_url))
+    .bind(video.map(|v| v.duration_ms))
+    .bind(poll_options)
     .bind(display_order)
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
+    tx.commit().await?;
     Ok(())
 }
 
-/// Update an existing prompt
+/// Update an existing prompt; the question is kept when `question_id` is None
 pub async fn update_prompt(
     pool: &PgPool,
     user_id: &Uuid,
     display_order: i32,
-    question: &str,
+    question_id: Option<&str>,
     answer: &str,
 ) -> Result<(), sqlx::Error> {
     sqlx::query(
-        "UPDATE user_prompts SET question = $1, answer = $2 WHERE user_id = $3 AND display_order = $4"
+        "UPDATE user_prompts SET question_id = COALESCE($1, question_id), answer = $2 WHERE user_id = $3 AND display_order = $4"
     )
-    .bind(question)
+    .bind(question_id)
     .bind(answer)
     .bind(user_id)
     .bind(display_order)
@@ -60,21 +208,164 @@ pub async fn update_prompt(
     Ok(())
 }
 
-/// Delete a prompt by order
-pub async fn delete_prompt(pool: &PgPool, user_id: &Uuid, display_order: i32) -> Result<(), sqlx::Error> {
-    sqlx::query("DELETE FROM user_prompts WHERE user_id = $1 AND display_order = $2")
-        .bind(user_id)
-        .bind(display_order)
-        .execute(pool)
+/// Set the recording of an existing prompt, replacing any previous voice note or video; the question is kept when `question_id` is None
+/// A new video goes back to PENDING moderation
+/// Returns Some(previous keys) so the caller can delete the old files, None if there's no prompt at that order
+pub async fn set_prompt_recording(
+    pool: &PgPool,
+    user_id: &Uuid,
+    display_order: i32,
+    question_id: Option<&str>,
+    recording: &PromptRecording,
+) -> Result<Option<PromptMediaKeys>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let old: Option<PromptMediaKeys> = sqlx::query_as(
+        "SELECT audio_key, video_key FROM user_prompts WHERE user_id = $1 AND display_order = $2 FOR UPDATE"
+    )
+    .bind(user_id)
+    .bind(display_order)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if old.is_none() {
+        return Ok(None);
+    }
+
+    let (audio, video) = (recording.audio(), recording.video());
+
+    sqlx::query(
+        r#"UPDATE user_prompts
+           SET question_id = COALESCE($3, question_id), audio_key = $4, audio_url = $5, audio_duration_ms = $6,
+               video_key = $7, video_url = $8, video_poster_url = $9, video_duration_ms = $10,
+               video_moderation_status = CASE WHEN $7::TEXT IS NOT NULL THEN 'PENDING' END, video_rejection_reason = NULL
+           WHERE user_id = $1 AND display_order = $2"#
+    )
+    .bind(user_id)
+    .bind(display_order)
+    .bind(question_id)
+    .bind(audio.map(|a| &a.key))
+    .bind(audio.map(|a| &a.url))
+    .bind(audio.map(|a| a.duration_ms))
+    .bind(video.map(|v| &v.key))
+    .bind(video.map(|v| &v.url))
+    .bind(video.map(|v| &v.poster_url))
+    .bind(video.map(|v| v.duration_ms))
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(old)
+}
+
+/// Whether the user's prompt at that order is a poll (None if there's no prompt there)
+pub async fn is_poll(pool: &PgPool, user_id: &Uuid, display_order: i32) -> Result<Option<bool>, sqlx::Error> {
+    let row: Option<(bool,)> = sqlx::query_as(
+        "SELECT poll_options IS NOT NULL FROM user_prompts WHERE user_id = $1 AND display_order = $2"
+    )
+    .bind(user_id)
+    .bind(display_order)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| r.0))
+}
+
+/// Replace the options of an existing poll; the question is kept when `question_id` is None
+/// Returns false if there's no poll at that order
+pub async fn update_poll(
+    pool: &PgPool,
+    user_id: &Uuid,
+    display_order: i32,
+    question_id: Option<&str>,
+    options: &[String],
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"UPDATE user_prompts SET question_id = COALESCE($3, question_id), poll_options = $4
+           WHERE user_id = $1 AND display_order = $2 AND poll_options IS NOT NULL"#
+    )
+    .bind(user_id)
+    .bind(display_order)
+    .bind(question_id)
+    .bind(options)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get a poll's options (None if the prompt isn't a poll)
+pub async fn get_poll_options(pool: &PgPool, prompt_id: &Uuid) -> Result<Option<Vec<String>>, sqlx::Error> {
+    let row: Option<(Option<Vec<String>>,)> = sqlx::query_as("SELECT poll_options FROM user_prompts WHERE id = $1")
+        .bind(prompt_id)
+        .fetch_optional(pool)
         .await?;
 
+    Ok(row.and_then(|r| r.0))
+}
+
+/// Delete a prompt by order
+/// Returns the media keys of its recording (none if there was no prompt)
+pub async fn delete_prompt(pool: &PgPool, user_id: &Uuid, display_order: i32) -> Result<PromptMediaKeys, sqlx::Error> {
+    let row: Option<PromptMediaKeys> = sqlx::query_as(
+        "DELETE FROM user_prompts WHERE user_id = $1 AND display_order = $2 RETURNING audio_key, video_key"
+    )
+    .bind(user_id)
+    .bind(display_order)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.unwrap_or_default())
+}
+
+/// Renumber the user's prompts 0..n-1, keeping their order
+/// Call in the same transaction as a delete, so the next prompt (numbered by count) doesn't reuse an order
+pub async fn compact_order(conn: &mut PgConnection, user_id: &Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"UPDATE user_prompts up SET display_order = o.new_order
+           FROM (
+               SELECT id, (ROW_NUMBER() OVER (ORDER BY display_order) - 1)::INT AS new_order
+               FROM user_prompts WHERE user_id = $1
+           ) o
+           WHERE up.id = o.id AND up.display_order <> o.new_order"#
+    )
+    .bind(user_id)
+    .execute(conn)
+    .await?;
+
     Ok(())
 }
 
-/// Get all prompts for a user
-pub async fn get_user_prompts(pool: &PgPool, user_id: &Uuid) -> Result<Vec<(Uuid, String, String, i32)>, sqlx::Error> {
-    let rows: Vec<(Uuid, String, String, i32)> = sqlx::query_as(
-        "SELECT id, question, answer, display_order FROM user_prompts WHERE user_id = $1 ORDER BY display_order"
+/// Get the media keys of a prompt's recording (none if the prompt doesn't exist)
+pub async fn get_media_keys(pool: &PgPool, prompt_id: &Uuid) -> Result<PromptMediaKeys, sqlx::Error> {
+    let row: Option<PromptMediaKeys> = sqlx::query_as("SELECT audio_key, video_key FROM user_prompts WHERE id = $1")
+        .bind(prompt_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.unwrap_or_default())
+}
+
+/// Get the media keys of all the user's recordings
+pub async fn get_all_media_keys(pool: &PgPool, user_id: &Uuid) -> Result<Vec<PromptMediaKeys>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT audio_key, video_key FROM user_prompts WHERE user_id = $1 AND (audio_key IS NOT NULL OR video_key IS NOT NULL)"
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Get all prompts for a user with their question text and video moderation (used by the user's own views)
+pub async fn get_user_prompts(pool: &PgPool, user_id: &Uuid) -> Result<Vec<PromptRow>, sqlx::Error> {
+    let rows: Vec<PromptRow> = sqlx::query_as(
+        r#"SELECT up.id, up.question_id, q.text AS question, up.answer, up.audio_url, up.audio_duration_ms,
+                  up.video_url, up.video_poster_url, up.video_duration_ms, up.video_moderation_status,
+                  up.video_rejection_reason, up.poll_options, up.display_order
+           FROM user_prompts up
+           JOIN prompt_questions q ON q.id = up.question_id
+           WHERE up.user_id = $1 ORDER BY up.display_order"#
     )
     .bind(user_id)
     .fetch_all(pool)
@@ -82,3 +373,88 @@ pub async fn get_user_prompts(pool: &PgPool, user_id: &Uuid) -> Result<Vec<(Uuid
 
     Ok(rows)
 }
+
+/// Get the prompts other users get to see: a video is left out until it's APPROVED,
+/// and so is a prompt that has nothing else to show
+pub async fn get_visible_prompts(pool: &PgPool, user_id: &Uuid) -> Result<Vec<PromptRow>, sqlx::Error> {
+    let rows: Vec<PromptRow> = sqlx::query_as(
+        r#"SELECT up.id, up.question_id, q.text AS question, up.answer, up.audio_url, up.audio_duration_ms,
+                  v.video_url, v.video_poster_url, v.video_duration_ms,
+                  NULL AS video_moderation_status, NULL AS video_rejection_reason, up.poll_options, up.display_order
+           FROM user_prompts up
+           JOIN prompt_questions q ON q.id = up.question_id
+           LEFT JOIN user_prompts v ON v.id = up.id AND v.video_moderation_status = 'APPROVED'
+           WHERE up.user_id = $1
+             AND (up.answer IS NOT NULL OR up.audio_url IS NOT NULL OR up.poll_options IS NOT NULL OR v.id IS NOT NULL)
+           ORDER BY up.display_order"#
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Check that a prompt belongs to the user and is shown to other users (see get_visible_prompts)
+pub async fn visible_prompt_belongs_to(pool: &PgPool, prompt_id: &Uuid, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+    let row: Option<(Uuid,)> = sqlx::query_as(
+        r#"SELECT id FROM user_prompts
+           WHERE id = $1 AND user_id = $2
+             AND (answer IS NOT NULL OR audio_url IS NOT NULL OR poll_options IS NOT NULL
+                  OR video_moderation_status = 'APPROVED')"#
+    )
+    .bind(prompt_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.is_some())
+}
+
+/// Get the user's prompt videos that aren't approved yet
+/// Returns (display_order, video_moderation_status, video_rejection_reason), first prompt first
+pub async fn get_unapproved_videos(pool: &PgPool, user_id: &Uuid) -> Result<Vec<(i32, String, Option<String>)>, sqlx::Error> {
+    sqlx::query_as(
+        r#"SELECT display_order, video_moderation_status, video_rejection_reason FROM user_prompts
+           WHERE user_id = $1 AND video_moderation_status IN ('PENDING', 'REJECTED')
+           ORDER BY display_order"#
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Save the moderation result for a prompt video
+/// Only applies while a prompt still holds the video that was classified (`video_key` is unique);
+/// returns false if it was replaced or deleted in the meantime
+pub async fn set_video_moderation(
+    pool: &PgPool,
+    video_key: &str,
+    status: &str,
+    rejection_reason: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE user_prompts SET video_moderation_status = $2, video_rejection_reason = $3 WHERE video_key = $1"
+    )
+    .bind(video_key)
+    .bind(status)
+    .bind(rejection_reason)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get prompt videos left PENDING by every user
+/// Returns (user_id, video_key), oldest first
+pub async fn get_unmoderated_videos(pool: &PgPool, limit: i64) -> Result<Vec<(Uuid, String)>, sqlx::Error> {
+    sqlx::query_as(
+        r#"SELECT user_id, video_key FROM user_prompts
+           WHERE video_moderation_status = 'PENDING'
+           ORDER BY created_at
+           LIMIT $1"#
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,216 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::{images_queries, prompt_queries, user_queries};
+use crate::models::inputs::{AccountStatus, ModerationAction, ReportContextType, ReportReason, ReportStatus};
+use crate::models::outputs::ReportRow;
+
+/// Number of reports returned per admin list request
+const REPORTS_PAGE_SIZE: i64 = 50;
+
+const REPORT_COLUMNS: &str = r#"id, reporter_id, target_user_id, context_type, context_id, context_snapshot,
+    reason, details, status, assigned_to, resolution_action, resolution_note, resolved_by, resolved_at, created_at"#;
+
+/// Copy of the reported content, or None if it isn't the target's
+/// Messages must have been sent by the target to the reporter
+pub async fn get_context_snapshot(
+    pool: &PgPool,
+    context_type: ReportContextType,
+    context_id: &Uuid,
+    target_user_id: &Uuid,
+    reporter_id: &Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> = match context_type {
+        ReportContextType::Image => {
+            sqlx::query_as(
+                r#"SELECT CONCAT_WS(E'\n', url, 'Video: ' || video_url)
+                   FROM user_images WHERE id = $1 AND user_id = $2"#
+            )
+            .bind(context_id)
+            .bind(target_user_id)
+            .fetch_optional(pool)
+            .await?
+        }
+        ReportContextType::Prompt => {
+            sqlx::query_as(
+                r#"SELECT CONCAT_WS(E'\n', q.text, up.answer, 'Voice note: ' || up.audio_url, 'Video: ' || up.video_url,
+                                    'Poll: ' || array_to_string(up.poll_options, ' / '))
+                   FROM user_prompts up
+                   JOIN prompt_questions q ON q.id = up.question_id
+                   WHERE up.id = $1 AND up.user_id = $2"#
+            )
+            .bind(context_id)
+            .bind(target_user_id)
+            .fetch_optional(pool)
+            .await?
+        }
+        ReportContextType::Message => {
+            sqlx::query_as(
+                r#"SELECT m.text
+                   FROM messages m
+                   JOIN matches ma ON ma.id = m.match_id
+                   WHERE m.id = $1
+                     AND m.sender_id = $2
+                     AND (ma.user1_id = $3 OR ma.user2_id = $3)"#
+            )
+            .bind(context_id)
+            .bind(target_user_id)
+            .bind(reporter_id)
+            .fetch_optional(pool)
+            .await?
+        }
+    };
+
+    Ok(row.map(|r| r.0))
+}
+
+/// Add a report to the moderation queue, returning its id
+pub async fn create_report(
+    pool: &PgPool,
+    reporter_id: &Uuid,
+    target_user_id: &Uuid,
+    context: Option<(ReportContextType, Uuid, String)>,
+    reason: ReportReason,
+    details: Option<&str>,
+) -> Result<Uuid, sqlx::Error> {
+    let (context_type, context_id, context_snapshot) = match context {
+        Some((t, id, snapshot)) => (Some(t.as_str()), Some(id), Some(snapshot)),
+        None => (None, None, None),
+    };
+
+    let row: (Uuid,) = sqlx::query_as(
+        r#"INSERT INTO reports (reporter_id, target_user_id, context_type, context_id, context_snapshot, reason, details)
+           VALUES ($1, $2, $3, $4, $5, $6, $7)
+           RETURNING id"#
+    )
+    .bind(reporter_id)
+    .bind(target_user_id)
+    .bind(context_type)
+    .bind(context_id)
+    .bind(context_snapshot)
+    .bind(reason.as_str())
+    .bind(details)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.0)
+}
+
+/// List reports oldest first, either with the given status or all open (OPEN/ASSIGNED) ones
+pub async fn list_reports(pool: &PgPool, status: Option<ReportStatus>) -> Result<Vec<ReportRow>, sqlx::Error> {
+    let statuses: Vec<&str> = match status {
+        Some(s) => vec![s.as_str()],
+        None => vec![ReportStatus::Open.as_str(), ReportStatus::Assigned.as_str()],
+    };
+
+    sqlx::query_as(&format!(
+        "SELECT {} FROM reports WHERE status = ANY($1) ORDER BY created_at, id LIMIT $2",
+        REPORT_COLUMNS
+    ))
+    .bind(statuses)
+    .bind(REPORTS_PAGE_SIZE)
+    .fetch_all(pool)
+    .await
+}
+
+/// Get a single report
+pub async fn get_report(pool: &PgPool, report_id: &Uuid) -> Result<Option<ReportRow>, sqlx::Error> {
+    sqlx::query_as(&format!("SELECT {} FROM reports WHERE id = $1", REPORT_COLUMNS))
+        .bind(report_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Assign an open report to a moderator
+pub async fn assign_report(pool: &PgPool, report_id: &Uuid, assignee_id: &Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"UPDATE reports SET status = 'ASSIGNED', assigned_to = $2, assigned_at = NOW()
+           WHERE id = $1 AND status IN ('OPEN', 'ASSIGNED')"#
+    )
+    .bind(report_id)
+    .bind(assignee_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Close a report and apply its moderation action in one transaction
+/// NONE dismisses the report; the other actions resolve it
+/// The report is locked first, so of two moderators resolving it at once only one acts;
+/// returns false (nothing applied) if it was already closed
+pub async fn resolve_report(
+    pool: &PgPool,
+    report: &ReportRow,
+    resolved_by: &Uuid,
+    action: ModerationAction,
+    note: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let open: Option<(Uuid,)> = sqlx::query_as(
+        "SELECT id FROM reports WHERE id = $1 AND status IN ('OPEN', 'ASSIGNED') FOR UPDATE"
+    )
+    .bind(report.id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if open.is_none() {
+        return Ok(false);
+    }
+
+    match action {
+        ModerationAction::None => {}
+        ModerationAction::RemoveImage => {
+            sqlx::query("DELETE FROM user_images WHERE id = $1 AND user_id = $2")
+                .bind(report.context_id)
+                .bind(report.target_user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            images_queries::compact_order(&mut tx, &report.target_user_id).await?;
+        }
+        ModerationAction::RemovePrompt => {
+            sqlx::query("DELETE FROM user_prompts WHERE id = $1 AND user_id = $2")
+                .bind(report.context_id)
+                .bind(report.target_user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            prompt_queries::compact_order(&mut tx, &report.target_user_id).await?;
+        }
+        ModerationAction::BanUser | ModerationAction::ShadowBanUser => {
+            let status = if action == ModerationAction::BanUser {
+                AccountStatus::Banned
+            } else {
+                AccountStatus::ShadowBanned
+            };
+
+            user_queries::set_account_status(&mut tx, &report.target_user_id, status, Some(resolved_by), note, Some(&report.id))
+                .await?;
+        }
+    }
+
+    let status = if action == ModerationAction::None {
+        ReportStatus::Dismissed
+    } else {
+        ReportStatus::Resolved
+    };
+
+    sqlx::query(
+        r#"UPDATE reports
+           SET status = $2, resolution_action = $3, resolution_note = $4, resolved_by = $5, resolved_at = NOW()
+           WHERE id = $1"#
+    )
+    .bind(report.id)
+    .bind(status.as_str())
+    .bind(action.as_str())
+    .bind(note)
+    .bind(resolved_by)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
@@ -1,7 +1,10 @@
+use chrono::{DateTime, Duration, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 use serde_json::json;
 use crate::models::inputs::UpdateProfileRequest;
+use crate::models::outputs::SuggestionProfile;
+use crate::routes::feed::ranker::{Candidate, Viewer};
 use crate::db::{user_queries, profile_queries};
 
 /// Sample profile data for seeding
@@ -75,7 +78,7 @@ fn get_seed_profiles() -> Vec<SeedProfile> {
             religion: "Agnostic".to_string(),
             relationship_type: "Monogamy".to_string(),
             dating_intention: "Long-term relationship".to_string(),
-            drinks: "Socially".to_string(),
+            drinks: "Sometimes".to_string(),
             smokes: "No".to_string(),
             preferences: SeedPreferences {
                 age_min: 28,
@@ -145,7 +148,7 @@ fn get_seed_profiles() -> Vec<SeedProfile> {
             ethnicity: "White".to_string(),
             politics: "Liberal".to_string(),
             religion: "Agnostic".to_string(),
-            relationship_type: "Figuring out my dating goals".to_string(),
+            relationship_type: "Figuring out my relationship type".to_string(),
             dating_intention: "Figuring out my dating goals".to_string(),
             drinks: "Sometimes".to_string(),
             smokes: "No".to_string(),
@@ -196,7 +199,7 @@ fn get_seed_profiles() -> Vec<SeedProfile> {
             religion: "Christian".to_string(),
             relationship_type: "Monogamy".to_string(),
             dating_intention: "Long-term relationship".to_string(),
-            drinks: "Socially".to_string(),
+            drinks: "Sometimes".to_string(),
             smokes: "No".to_string(),
             preferences: SeedPreferences {
                 age_min: 25,
@@ -316,7 +319,7 @@ fn get_seed_profiles() -> Vec<SeedProfile> {
             religion: "Catholic".to_string(),
             relationship_type: "Monogamy".to_string(),
             dating_intention: "Long-term relationship".to_string(),
-            drinks: "Socially".to_string(),
+            drinks: "Sometimes".to_string(),
             smokes: "No".to_string(),
             preferences: SeedPreferences {
                 age_min: 21,
@@ -351,9 +354,77 @@ fn to_profile_request(seed: &SeedProfile) -> UpdateProfileRequest {
         dating_intention: Some(seed.dating_intention.clone()),
         drinks: Some(seed.drinks.clone()),
         smokes: Some(seed.smokes.clone()),
+        visibility: None,
+        time_zone: None,
     }
 }
 
+/// A seed user as seen by the feed ranker
+pub struct SeedRankingUser {
+    pub viewer: Viewer,
+    pub gender_preference: Vec<String>,
+    pub candidate: Candidate,
+}
+
+/// Build ranking inputs from the seed profiles without touching the database
+/// Activity, location, photos and like history are derived from each profile's
+/// position in the seed list so every run produces the same data
+pub fn seed_ranking_users(now: DateTime<Utc>) -> Vec<SeedRankingUser> {
+    get_seed_profiles()
+        .into_iter()
+        .enumerate()
+        .map(|(i, seed)| {
+            let latitude = 34.05 + (i % 5) as f64 * 0.08;
+            let longitude = -118.25 + (i % 3) as f64 * 0.11;
+
+            let viewer = Viewer {
+                relationship_type: Some(seed.relationship_type.clone()),
+                dating_intention: Some(seed.dating_intention.clone()),
+                latitude: Some(latitude),
+                longitude: Some(longitude),
+            };
+
+            let candidate = Candidate {
+                profile: SuggestionProfile {
+                    user_id: seed.name.clone(),
+                    name: Some(seed.name.clone()),
+                    bio: None,
+                    birthdate: None,
+                    hidden_fields: Vec::new(),
+                    pronouns: Some(seed.pronouns.clone()),
+                    gender: Some(seed.gender.clone()),
+                    sexuality: Some(seed.sexuality.clone()),
+                    height: Some(seed.height),
+                    location: None,
+                    job: Some(seed.job.clone()),
+                    company: None,
+                    school: None,
+                    ethnicity: Some(seed.ethnicity.clone()),
+                    politics: Some(seed.politics.clone()),
+                    religion: Some(seed.religion.clone()),
+                    relationship_type: Some(seed.relationship_type.clone()),
+                    dating_intention: Some(seed.dating_intention.clone()),
+                    drinks: Some(seed.drinks.clone()),
+                    smokes: Some(seed.smokes.clone()),
+                },
+                last_active: Some(now - Duration::hours(i as i64 * 9)),
+                latitude: Some(latitude),
+                longitude: Some(longitude),
+                image_count: (i % 7) as i64,
+                prompt_count: (i % 4) as i64,
+                liked_viewer: false,
+                like_rate: (i % 4) as f64 / 4.0,
+            };
+
+            SeedRankingUser {
+                viewer,
+                gender_preference: seed.preferences.gender_preference,
+                candidate,
+            }
+        })
+        .collect()
+}
+
 /// Convert SeedPreferences to JSON
 fn to_preferences_json(prefs: &SeedPreferences) -> serde_json::Value {
     json!({
@@ -407,6 +478,9 @@ pub async fn seed_database(pool: &PgPool) -> Result<(), sqlx::Error> {
         }
         */
         
+        // Seed profiles are fixtures without photos or prompts; mark them complete so they show up in feeds
+        user_queries::set_profile_complete(pool, &user_uuid, true).await?;
+
         // Always update preferences (works for both new and existing users)
         let preferences_json = to_preferences_json(&seed.preferences);
         update_user_preferences(pool, &user_uuid, preferences_json).await?;
@@ -1,6 +1,9 @@
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
+use crate::models::inputs::AccountStatus;
+use crate::models::outputs::UserSummary;
+
 /// Check if the user exists in the database by phone
 /// Returns Some(id) if user exists, None if not found
 pub async fn check_user_exists(pool: &PgPool, phone: &str) -> Result<Option<String>, sqlx::Error> {
@@ -14,6 +17,16 @@ pub async fn check_user_exists(pool: &PgPool, phone: &str) -> Result<Option<Stri
     Ok(row.map(|r| r.0.to_string()))
 }
 
+/// Check if a user exists by id
+pub async fn user_exists(pool: &PgPool, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+    let row: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.is_some())
+}
+
 /// Create a new user in the database
 /// Returns the new user's id
 pub async fn create_user(pool: &PgPool, phone: &str) -> Result<String, sqlx::Error> {
@@ -84,4 +97,155 @@ pub async fn update_user_preferences(pool: &PgPool, user_id: &Uuid, preferences:
     .await?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Record that the user was active just now
+pub async fn touch_last_active(pool: &PgPool, user_id: &Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET last_active = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Get the public summary of a user
+pub async fn get_user_summary(pool: &PgPool, user_id: &Uuid) -> Result<Option<UserSummary>, sqlx::Error> {
+    let row: Option<(Uuid, Option<bool>, Option<bool>)> = sqlx::query_as(
+        "SELECT id, is_profile_complete, is_new_user FROM users WHERE id = $1"
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|(id, is_profile_complete, is_new_user)| UserSummary {
+        id: id.to_string(),
+        is_profile_complete: is_profile_complete.unwrap_or(false),
+        is_new_user: is_new_user.unwrap_or(false),
+    }))
+}
+
+/// Check that a time zone name is one Postgres knows (IANA names like "Europe/London")
+pub async fn is_valid_time_zone(pool: &PgPool, time_zone: &str) -> Result<bool, sqlx::Error> {
+    let row: Option<(String,)> = sqlx::query_as("SELECT name FROM pg_timezone_names WHERE name = $1")
+        .bind(time_zone)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.is_some())
+}
+
+/// Update the user's time zone (used for the daily LIKE and undo resets)
+pub async fn update_time_zone(conn: &mut PgConnection, user_id: &Uuid, time_zone: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET time_zone = $2 WHERE id = $1")
+        .bind(user_id)
+        .bind(time_zone)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Check whether the user can use the admin (moderation) routes
+pub async fn is_admin(pool: &PgPool, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+    let row: Option<(bool,)> = sqlx::query_as("SELECT is_admin FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.is_some_and(|r| r.0))
+}
+
+/// Get the user's moderation state, None if the user doesn't exist
+pub async fn get_account_status(pool: &PgPool, user_id: &Uuid) -> Result<Option<AccountStatus>, sqlx::Error> {
+    let row: Option<(String,)> = sqlx::query_as("SELECT account_status FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.and_then(|r| AccountStatus::parse(&r.0)))
+}
+
+/// Change the user's moderation state and write it to the audit log
+/// Runs on the caller's connection so it can join a transaction; returns false if nothing changed
+pub async fn set_account_status(
+    conn: &mut PgConnection,
+    user_id: &Uuid,
+    status: AccountStatus,
+    changed_by: Option<&Uuid>,
+    reason: Option<&str>,
+    report_id: Option<&Uuid>,
+) -> Result<bool, sqlx::Error> {
+    let old: Option<(String,)> = sqlx::query_as("SELECT account_status FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let Some((old_status,)) = old else {
+        return Ok(false);
+    };
+
+    if old_status == status.as_str() {
+        return Ok(false);
+    }
+
+    sqlx::query("UPDATE users SET account_status = $2 WHERE id = $1")
+        .bind(user_id)
+        .bind(status.as_str())
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"INSERT INTO account_status_audit (user_id, old_status, new_status, changed_by, reason, report_id)
+           VALUES ($1, $2, $3, $4, $5, $6)"#
+    )
+    .bind(user_id)
+    .bind(old_status)
+    .bind(status.as_str())
+    .bind(changed_by)
+    .bind(reason)
+    .bind(report_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(true)
+}
+
+/// `set_account_status` in its own transaction
+pub async fn update_account_status(
+    pool: &PgPool,
+    user_id: &Uuid,
+    status: AccountStatus,
+    changed_by: Option<&Uuid>,
+    reason: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let changed = set_account_status(&mut tx, user_id, status, changed_by, reason, None).await?;
+    tx.commit().await?;
+
+    Ok(changed)
+}
+
+/// Check whether the user's profile is marked complete
+pub async fn is_profile_complete(pool: &PgPool, user_id: &Uuid) -> Result<bool, sqlx::Error> {
+    let row: Option<(Option<bool>,)> = sqlx::query_as("SELECT is_profile_complete FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.and_then(|r| r.0).unwrap_or(false))
+}
+
+/// Mark the user's profile complete or incomplete
+/// Returns true if the flag changed
+pub async fn set_profile_complete(pool: &PgPool, user_id: &Uuid, complete: bool) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE users SET is_profile_complete = $2 WHERE id = $1 AND is_profile_complete IS DISTINCT FROM $2"
+    )
+    .bind(user_id)
+    .bind(complete)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -1,9 +1,14 @@
 use serde::{Serialize, Deserialize};
 use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey};
 use actix_web::dev::ServiceRequest;
-use actix_web::{Error as ActixError, HttpMessage};
-use actix_web::error::ErrorUnauthorized;
+use actix_web::{Error as ActixError, HttpMessage, web};
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::user_queries;
+use crate::models::inputs::AccountStatus;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
@@ -30,12 +35,31 @@ impl Claims {
         let token = credentials.token();
 
         // verify the token
-        match Self::verify_token(token) {
-            Ok(claims) => {
-                req.extensions_mut().insert(claims);
-                Ok(req)
+        let claims = match Self::verify_token(token) {
+            Ok(claims) => claims,
+            Err(_) => return Err((ErrorUnauthorized("Invalid token"), req)),
+        };
+
+        // Tokens issued before a ban stop working straight away
+        let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+            return Err((ErrorUnauthorized("Invalid token"), req));
+        };
+
+        let Some(pool) = req.app_data::<web::Data<PgPool>>().cloned() else {
+            return Err((ErrorInternalServerError("Database unavailable"), req));
+        };
+
+        match user_queries::get_account_status(&pool, &user_id).await {
+            Ok(Some(AccountStatus::Banned)) => return Err((ErrorForbidden("Account banned"), req)),
+            Ok(Some(_)) => {}
+            Ok(None) => return Err((ErrorUnauthorized("Invalid token"), req)),
+            Err(e) => {
+                println!("Failed to get account status: {:?}", e);
+                return Err((ErrorInternalServerError("Database error"), req));
             }
-            Err(_) => Err((ErrorUnauthorized("Invalid token"), req)),
         }
+
+        req.extensions_mut().insert(claims);
+        Ok(req)
     }
 }
\ No newline at end of file
@@ -15,7 +15,8 @@ mod jwtauth;
 mod models;
 mod routes;
 
-use routes::{auth, feed, interactions, matches, profile, prompts, user};
+use routes::feed::ranker::{RankingWeights, WeightedRanker};
+use routes::{admin, auth, feed, interactions, likes, matches, media, meta, profile, prompts, reports, user};
 
 async fn health_check() -> impl Responder {
     HttpResponse::Ok().body("I'm ok")
@@ -41,8 +42,19 @@ async fn main() -> std::io::Result<()> {
     // Create AppState BEFORE the closure so it's shared across all workers
     let app_state = web::Data::new(models::state::AppState {
         pending_verifications: Mutex::new(HashMap::new()),
+        ranker: Box::new(WeightedRanker::new(RankingWeights::from_env())),
+        rewind: models::state::RewindConfig::from_env(),
+        superlikes: models::state::SuperlikeConfig::from_env(),
+        like_daily_limit: interactions::quota::base_limit_from_env(),
+        entitlements: Box::new(interactions::quota::StoredEntitlements),
+        media: media::store_from_env(),
+        classifier: media::moderation::classifier_from_env(),
+        transcoder: media::transcode::transcoder_from_env(),
     });
 
+    // Background job that precomputes each active user's feed candidates
+    feed::candidate_pool::spawn_pool_job(pool.clone(), app_state.clone());
+
     println!("Starting server on 0.0.0.0:8080 (accessible from network)");
     HttpServer::new(move || {
         // Create the auth middleware
@@ -61,13 +73,41 @@ async fn main() -> std::io::Result<()> {
                     .wrap(auth)
                     .route("/profile/me", web::get().to(profile::get_profile))
                     .route("/profile", web::post().to(profile::update_profile))
+                    .route("/meta/options", web::get().to(meta::get_options))
+                    .route("/media/{key:.*}", web::get().to(media::serve))
                     .route("/user/preferences", web::post().to(user::update_user_preference))
-                    .route("/user/images", web::post().to(profile::upload_user_images))
+                    .route("/users/{id}/block", web::post().to(user::block_user))
+                    .route("/users/{id}/block", web::delete().to(user::unblock_user))
+                    .service(
+                        web::resource("/user/images")
+                            .app_data(media::upload_config(media::sniff::MAX_IMAGE_BYTES))
+                            .route(web::post().to(profile::upload_user_images)),
+                    )
+                    .service(
+                        web::resource("/user/videos")
+                            .app_data(media::upload_config(media::video::MAX_VIDEO_BYTES))
+                            .route(web::post().to(profile::upload_user_video)),
+                    )
                     .route("/profile/images", web::post().to(profile::upload_profile_images))
+                    .route("/profile/images/order", web::put().to(profile::reorder_images))
+                    .service(
+                        web::resource("/profile/images/{id}")
+                            .app_data(media::upload_config(media::sniff::MAX_IMAGE_BYTES))
+                            .route(web::put().to(profile::replace_image))
+                            .route(web::delete().to(profile::delete_image)),
+                    )
+                    .service(
+                        web::resource("/profile/images/{id}/video")
+                            .app_data(media::upload_config(media::video::MAX_VIDEO_BYTES))
+                            .route(web::put().to(profile::replace_with_video)),
+                    )
+                    .route("/profile/images/{id}/caption", web::put().to(profile::update_image_caption))
                     .route("/profile/finalize", web::post().to(profile::finalize_profile))
                     .route("/profile", web::delete().to(profile::delete_account))
                     .route("/feed", web::get().to(feed::get_feed))
                     .route("/interact", web::post().to(interactions::interact))
+                    .route("/interact/undo", web::post().to(interactions::undo))
+                    .route("/likes/received", web::get().to(likes::get_likes_received))
                     .route("/matches", web::get().to(matches::get_matches))
                     .route(
                         "/matches/{id}/messages",
@@ -77,8 +117,38 @@ async fn main() -> std::io::Result<()> {
                         "/matches/{id}/messages",
                         web::post().to(matches::send_message),
                     )
+                    .route("/reports", web::post().to(reports::create_report))
+                    // Admin (moderation) routes
+                    .route("/admin/reports", web::get().to(admin::list_reports))
+                    .route("/admin/reports/{id}/assign", web::post().to(admin::assign_report))
+                    .route("/admin/reports/{id}/resolve", web::post().to(admin::resolve_report))
+                    .route("/admin/users/{id}/status", web::post().to(admin::update_account_status))
+                    .route("/admin/users/{id}/unlock-birthdate", web::post().to(admin::unlock_birthdate))
                     // Prompts routes
                     .route("/prompts", web::get().to(prompts::get_prompts))
+                    .route("/prompts/catalog", web::get().to(prompts::get_catalog))
+                    .service(
+                        web::resource("/prompts/voice")
+                            .app_data(media::upload_config(media::audio::MAX_AUDIO_BYTES))
+                            .route(web::post().to(prompts::create_voice_prompt)),
+                    )
+                    .service(
+                        web::resource("/prompts/{order}/voice")
+                            .app_data(media::upload_config(media::audio::MAX_AUDIO_BYTES))
+                            .route(web::put().to(prompts::update_voice_prompt)),
+                    )
+                    .service(
+                        web::resource("/prompts/video")
+                            .app_data(media::upload_config(media::video::MAX_VIDEO_BYTES))
+                            .route(web::post().to(prompts::create_video_prompt)),
+                    )
+                    .service(
+                        web::resource("/prompts/{order}/video")
+                            .app_data(media::upload_config(media::video::MAX_VIDEO_BYTES))
+                            .route(web::put().to(prompts::update_video_prompt)),
+                    )
+                    .route("/prompts/poll", web::post().to(prompts::create_poll_prompt))
+                    .route("/prompts/{order}/poll", web::put().to(prompts::update_poll_prompt))
                     .route("/prompts", web::post().to(prompts::create_prompt))
                     .route("/prompts/{order}", web::put().to(prompts::update_prompt))
                     .route("/prompts/{order}", web::delete().to(prompts::delete_prompt)),
@@ -107,20 +177,47 @@ GET /profile/me
 POST /profile
 - Updates profile fields (name, bio, etc.).
 
+GET /media/{key}
+- Serves files kept by the local media store: the owner's own, or approved media of active, unblocked users.
+
+POST /user/images
+- Uploads a profile image file to the media store and adds it to the profile.
+
 POST /profile/images
-- Uploads a user profile image.
+- Adds an image already in the media store (URLs from anywhere else are rejected).
+
+PUT /profile/images/order
+- Reorders the profile images.
+
+PUT /profile/images/{id}
+- Replaces one image's file, keeping its slot.
+
+DELETE /profile/images/{id}
+- Deletes an image and its stored files.
+
+PUT /profile/images/{id}/caption
+- Sets or clears an image's caption.
 
 POST /profile/finalize
 - Finalizes profile (sets "is_profile_complete") after ensuring 6 images are present.
 
 DELETE /profile
-- Deletes the user account permanently.
+- Deletes the user account permanently, along with all stored media.
 
 GET /feed
-- Gets recommended profiles for the user to swipe on.
+- Gets recommended profiles for the user to swipe on, ranked by the feed Ranker.
+- Reads the precomputed candidate pool (rebuilt by the feed pool job) and falls back to live ranking.
 
 POST /interact
 - Handles Like (Heart) or Pass (Cross) interactions.
+- A Like back to someone who already liked the user creates the match.
+- A Like on a poll prompt can vote for one of its options; a new match's chat opens with the votes.
+
+POST /interact/undo
+- Reverts the user's most recent interaction (within the rewind window and daily quota).
+
+GET /likes/received
+- Gets the people who liked the user, with the liked image/prompt, comment and poll vote.
 
 GET /matches
 - Gets a list of all matches (conversations).
@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 
 #[derive(Deserialize)]
 pub struct PhoneLoginRequest {
@@ -22,6 +23,16 @@ pub struct UploadProfileImageRequest {
     pub image_url: String,
 }
 
+#[derive(Deserialize)]
+pub struct ReorderImagesRequest {
+    pub image_ids: Vec<String>, // every image id, first image first
+}
+
+#[derive(Deserialize)]
+pub struct UpdateImageCaptionRequest {
+    pub caption: Option<String>, // null or blank clears it
+}
+
 #[derive(Deserialize)]
 pub struct UpdateProfileRequest {
     // For profiles table
@@ -41,14 +52,18 @@ pub struct UpdateProfileRequest {
     pub religion: Option<String>,
     pub relationship_type: Option<String>,  // "Monogamy"
     pub dating_intention: Option<String>,   // "Long-term relationship"
-    pub drinks: Option<String>,             // "Socially", "No"
+    pub drinks: Option<String>,             // "Yes", "Sometimes", "No"
     pub smokes: Option<String>,             // "No", "Yes"
+    // Field name -> shown to other users ({"religion": false} hides religion)
+    pub visibility: Option<HashMap<String, bool>>,
+    // For users table
+    pub time_zone: Option<String>,          // IANA name, "America/New_York"
 }
 
 #[derive(Deserialize)]
 pub struct InteractRequest {
     pub target_user_id: String,
-    pub action: String, // "LIKE" or "PASS" (Cross Click)
+    pub action: String, // "LIKE", "SUPERLIKE" or "PASS" (Cross Click)
     pub context: Option<InteractContext>,
     pub comment: Option<String>,
 }
@@ -57,6 +72,279 @@ pub struct InteractRequest {
 pub struct InteractContext {
     pub r#type: String, // "IMAGE" or "PROMPT"
     pub id: String,
+    pub poll_option: Option<i32>, // index of the option voted for, when liking a poll prompt
+}
+
+/// Interaction kinds accepted by /interact (stored as the uppercase name)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InteractAction {
+    Like,
+    SuperLike,
+    Pass,
+}
+
+impl InteractAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "LIKE" => Some(Self::Like),
+            "SUPERLIKE" => Some(Self::SuperLike),
+            "PASS" => Some(Self::Pass),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Like => "LIKE",
+            Self::SuperLike => "SUPERLIKE",
+            Self::Pass => "PASS",
+        }
+    }
+
+    /// Likes and super-likes both count towards a match
+    pub fn is_like(&self) -> bool {
+        matches!(self, Self::Like | Self::SuperLike)
+    }
+}
+
+/// What part of the target's profile a like refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextType {
+    Image,
+    Prompt,
+}
+
+impl ContextType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "IMAGE" => Some(Self::Image),
+            "PROMPT" => Some(Self::Prompt),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Image => "IMAGE",
+            Self::Prompt => "PROMPT",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateReportRequest {
+    pub target_user_id: String,
+    pub context: Option<ReportContext>,
+    pub reason: String,
+    pub details: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReportContext {
+    pub r#type: String, // "IMAGE", "PROMPT" or "MESSAGE"
+    pub id: String,
+}
+
+/// What a report points at on the target's profile or in the chat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportContextType {
+    Image,
+    Prompt,
+    Message,
+}
+
+impl ReportContextType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "IMAGE" => Some(Self::Image),
+            "PROMPT" => Some(Self::Prompt),
+            "MESSAGE" => Some(Self::Message),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Image => "IMAGE",
+            Self::Prompt => "PROMPT",
+            Self::Message => "MESSAGE",
+        }
+    }
+}
+
+/// Reason categories accepted by POST /reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportReason {
+    InappropriateContent,
+    FakeProfile,
+    Harassment,
+    Spam,
+    Underage,
+    Scam,
+    Other,
+}
+
+impl ReportReason {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "INAPPROPRIATE_CONTENT" => Some(Self::InappropriateContent),
+            "FAKE_PROFILE" => Some(Self::FakeProfile),
+            "HARASSMENT" => Some(Self::Harassment),
+            "SPAM" => Some(Self::Spam),
+            "UNDERAGE" => Some(Self::Underage),
+            "SCAM" => Some(Self::Scam),
+            "OTHER" => Some(Self::Other),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InappropriateContent => "INAPPROPRIATE_CONTENT",
+            Self::FakeProfile => "FAKE_PROFILE",
+            Self::Harassment => "HARASSMENT",
+            Self::Spam => "SPAM",
+            Self::Underage => "UNDERAGE",
+            Self::Scam => "SCAM",
+            Self::Other => "OTHER",
+        }
+    }
+}
+
+/// Moderation queue states
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReportStatus {
+    Open,
+    Assigned,
+    Resolved,
+    Dismissed,
+}
+
+impl ReportStatus {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "OPEN" => Some(Self::Open),
+            "ASSIGNED" => Some(Self::Assigned),
+            "RESOLVED" => Some(Self::Resolved),
+            "DISMISSED" => Some(Self::Dismissed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "OPEN",
+            Self::Assigned => "ASSIGNED",
+            Self::Resolved => "RESOLVED",
+            Self::Dismissed => "DISMISSED",
+        }
+    }
+
+    /// Still waiting for a moderator's decision
+    pub fn is_open(&self) -> bool {
+        matches!(self, Self::Open | Self::Assigned)
+    }
+}
+
+/// What resolving a report does to the reported user or content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationAction {
+    None,
+    RemoveImage,
+    RemovePrompt,
+    BanUser,
+    ShadowBanUser,
+}
+
+impl ModerationAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "NONE" => Some(Self::None),
+            "REMOVE_IMAGE" => Some(Self::RemoveImage),
+            "REMOVE_PROMPT" => Some(Self::RemovePrompt),
+            "BAN_USER" => Some(Self::BanUser),
+            "SHADOW_BAN_USER" => Some(Self::ShadowBanUser),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "NONE",
+            Self::RemoveImage => "REMOVE_IMAGE",
+            Self::RemovePrompt => "REMOVE_PROMPT",
+            Self::BanUser => "BAN_USER",
+            Self::ShadowBanUser => "SHADOW_BAN_USER",
+        }
+    }
+}
+
+/// Account states set by moderation (`users.account_status`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountStatus {
+    Active,
+    Banned,
+    ShadowBanned,
+}
+
+impl AccountStatus {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ACTIVE" => Some(Self::Active),
+            "BANNED" => Some(Self::Banned),
+            "SHADOW_BANNED" => Some(Self::ShadowBanned),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "ACTIVE",
+            Self::Banned => "BANNED",
+            Self::ShadowBanned => "SHADOW_BANNED",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateAccountStatusRequest {
+    pub status: String, // "ACTIVE", "BANNED" or "SHADOW_BANNED"
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ReportsQuery {
+    pub status: Option<ReportStatus>, // all open reports when missing
+}
+
+#[derive(Deserialize)]
+pub struct AssignReportRequest {
+    pub assignee_id: Option<String>, // the calling admin when missing
+}
+
+#[derive(Deserialize)]
+pub struct ResolveReportRequest {
+    pub action: String, // "NONE" (dismiss), "REMOVE_IMAGE", "REMOVE_PROMPT", "BAN_USER" or "SHADOW_BAN_USER"
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct FeedQuery {
+    pub cursor: Option<String>, // opaque, from FeedResponse.next_cursor
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LikesOrder {
+    #[default]
+    Recent,
+    Oldest,
+}
+
+#[derive(Deserialize)]
+pub struct LikesReceivedQuery {
+    pub order: Option<LikesOrder>, // "recent" (default) or "oldest"
 }
 
 #[derive(Deserialize)]
@@ -64,18 +352,39 @@ pub struct SendMessageRequest {
     pub text: String,
 }
 
+/// The question is picked from GET /prompts/catalog by `question_id`; older clients may send the
+/// catalog question's exact text as `question` instead
 #[derive(Deserialize)]
 pub struct CreatePromptRequest {
-    pub question: String,
+    pub question_id: Option<String>,
+    pub question: Option<String>,
     pub answer: String,
 }
 
+/// Same as CreatePromptRequest; without `question_id` or `question` the prompt keeps its question
 #[derive(Deserialize)]
 pub struct UpdatePromptRequest {
-    pub question: String,
+    pub question_id: Option<String>,
+    pub question: Option<String>,
     pub answer: String,
 }
 
+/// A poll prompt: a POLL question from the catalog and 2-4 options for viewers to vote on
+#[derive(Deserialize)]
+pub struct CreatePollRequest {
+    pub question_id: Option<String>,
+    pub question: Option<String>,
+    pub options: Vec<String>,
+}
+
+/// Same as CreatePollRequest; without `question_id` or `question` the poll keeps its question
+#[derive(Deserialize)]
+pub struct UpdatePollRequest {
+    pub question_id: Option<String>,
+    pub question: Option<String>,
+    pub options: Vec<String>,
+}
+
 // USERS PREFERENCES
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -1,4 +1,8 @@
+use chrono::{DateTime, Utc};
 use serde::Serialize;
+use uuid::Uuid;
+
+use crate::routes::profile::birthdate;
 
 // Generic Responses
 #[derive(Serialize)]
@@ -9,6 +13,70 @@ pub struct StatusResponse {
     // pub pending_actions: Option<Vec<String>>,
 }
 
+/// Machine-readable error codes for 4xx responses
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    InvalidTarget,
+    SelfInteraction,
+    TargetNotFound,
+    InvalidAction,
+    InvalidContext,
+    ContextNotFound,
+    NothingToUndo,
+    UndoWindowExpired,
+    UndoLimitReached,
+    AlreadyMatched,
+    SuperlikeAllowanceExhausted,
+    LikeLimitReached,
+    InvalidTimeZone,
+    InvalidReason,
+    ReportNotFound,
+    ReportClosed,
+    InvalidResolution,
+    AccountBanned,
+    InvalidStatus,
+    InvalidBirthdate,
+    Underage,
+    BirthdateLocked,
+    InvalidOption,
+    InvalidVisibility,
+    UnsupportedMediaType,
+    TooManyImages,
+    ImagesChanged,
+    InvalidImageUrl,
+    FileTooLarge,
+    InvalidImageDimensions,
+    ImageNotFound,
+    InvalidImageOrder,
+    InvalidCaption,
+    InvalidPromptQuestion,
+    InvalidAudioDuration,
+    InvalidVideoDuration,
+    PromptNotFound,
+    InvalidPollOptions,
+    WrongPromptType,
+    InvalidPollVote,
+}
+
+/// Error response carrying a typed code the client can branch on
+#[derive(Serialize)]
+pub struct ErrorResponse {
+    pub status: String,
+    pub code: ErrorCode,
+    pub message: Option<String>,
+}
+
+impl ErrorResponse {
+    pub fn new(code: ErrorCode, message: &str) -> Self {
+        Self {
+            status: "error".to_string(),
+            code,
+            message: Some(message.to_string()),
+        }
+    }
+}
+
 // Auth
 #[derive(Serialize)]
 pub struct LoginResponse {
@@ -36,28 +104,219 @@ pub struct UserProfile {
     pub images: Option<Vec<UserImage>>,
     pub prompts: Option<Vec<UserPrompt>>,
     pub details: Option<ProfileDetails>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub superliked_you: bool, // feed cards: this person super-liked the viewer
 }
 
 #[derive(Serialize)]
 pub struct UserImage {
     pub id: String,
-    pub url: String,
+    pub url: String, // full-size rendition (the poster frame of a video)
     pub order: i32,
+    pub media_type: String, // "IMAGE" or "VIDEO"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_duration_ms: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renditions: Option<ImageRenditions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blurhash: Option<String>, // placeholder to show while loading
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    // Only in the user's own profile: "PENDING", "APPROVED" or "REJECTED"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moderation_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejection_reason: Option<String>,
+}
+
+/// Sizes built by the image pipeline (None for images uploaded before it existed)
+#[derive(Serialize)]
+pub struct ImageRenditions {
+    pub thumbnail: String, // 200x200 square
+    pub card: String,      // fits 720x960
+    pub full: String,      // fits 1600x1600
+}
+
+impl UserImage {
+    pub fn new(
+        id: String,
+        url: String,
+        order: i32,
+        thumbnail_url: Option<String>,
+        card_url: Option<String>,
+        blurhash: Option<String>,
+        caption: Option<String>,
+    ) -> Self {
+        let renditions = match (thumbnail_url, card_url) {
+            (Some(thumbnail), Some(card)) => Some(ImageRenditions { thumbnail, card, full: url.clone() }),
+            _ => None,
+        };
+
+        Self {
+            id,
+            url,
+            order,
+            media_type: "IMAGE".to_string(),
+            video_url: None,
+            video_duration_ms: None,
+            renditions,
+            blurhash,
+            caption,
+            moderation_status: None,
+            rejection_reason: None,
+        }
+    }
+}
+
+/// A row from user_images
+#[derive(sqlx::FromRow)]
+pub struct ImageRow {
+    pub id: Uuid,
+    pub url: String,
+    pub display_order: i32,
+    pub thumbnail_url: Option<String>,
+    pub card_url: Option<String>,
+    pub blurhash: Option<String>,
+    pub caption: Option<String>,
+    pub moderation_status: String,
+    pub rejection_reason: Option<String>,
+    pub media_type: String,
+    pub video_url: Option<String>,
+    pub video_duration_ms: Option<i32>,
+}
+
+/// A row from user_images with its owner
+#[derive(sqlx::FromRow)]
+pub struct OwnedImageRow {
+    pub user_id: Uuid,
+    #[sqlx(flatten)]
+    pub image: ImageRow,
+}
+
+/// Moderation is only shown to the image's owner; other users only ever get APPROVED images
+impl From<ImageRow> for UserImage {
+    fn from(row: ImageRow) -> Self {
+        UserImage {
+            media_type: row.media_type,
+            video_url: row.video_url,
+            video_duration_ms: row.video_duration_ms,
+            ..UserImage::new(
+                row.id.to_string(),
+                row.url,
+                row.display_order,
+                row.thumbnail_url,
+                row.card_url,
+                row.blurhash,
+                row.caption,
+            )
+        }
+    }
 }
 
 #[derive(Serialize)]
 pub struct UserPrompt {
     pub id: String,
+    pub question_id: String, // catalog id (GET /prompts/catalog)
     pub question: String,
-    pub answer: String,
+    pub answer: Option<String>, // null for voice- or video-only answers and polls
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_url: Option<String>, // voice note
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_duration_ms: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_url: Option<String>, // video answer (never together with a voice note)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_poster_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_duration_ms: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_moderation_status: Option<String>, // owner only: PENDING, APPROVED or REJECTED
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_rejection_reason: Option<String>, // owner only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poll_options: Option<Vec<String>>, // 2-4 options, in order, for a poll question
     pub order: i32,
 }
 
+/// A row from user_prompts joined with its question
+#[derive(sqlx::FromRow)]
+pub struct PromptRow {
+    pub id: Uuid,
+    pub question_id: String,
+    pub question: String,
+    pub answer: Option<String>,
+    pub audio_url: Option<String>,
+    pub audio_duration_ms: Option<i32>,
+    pub video_url: Option<String>,
+    pub video_poster_url: Option<String>,
+    pub video_duration_ms: Option<i32>,
+    pub video_moderation_status: Option<String>,
+    pub video_rejection_reason: Option<String>,
+    pub poll_options: Option<Vec<String>>,
+    pub display_order: i32,
+}
+
+impl From<PromptRow> for UserPrompt {
+    fn from(row: PromptRow) -> Self {
+        UserPrompt {
+            id: row.id.to_string(),
+            question_id: row.question_id,
+            question: row.question,
+            answer: row.answer,
+            audio_url: row.audio_url,
+            audio_duration_ms: row.audio_duration_ms,
+            video_url: row.video_url,
+            video_poster_url: row.video_poster_url,
+            video_duration_ms: row.video_duration_ms,
+            video_moderation_status: row.video_moderation_status,
+            video_rejection_reason: row.video_rejection_reason,
+            poll_options: row.poll_options,
+            order: row.display_order,
+        }
+    }
+}
+
+/// An active catalog question with its category
+#[derive(sqlx::FromRow)]
+pub struct CatalogRow {
+    pub category_id: String,
+    pub category_name: String,
+    pub id: String,
+    pub text: String,
+    pub kind: String,
+}
+
+/// GET /prompts/catalog
+#[derive(Serialize)]
+pub struct PromptCatalogResponse {
+    pub categories: Vec<PromptCategory>,
+}
+
+#[derive(Serialize)]
+pub struct PromptCategory {
+    pub id: String,
+    pub name: String,
+    pub questions: Vec<PromptQuestion>,
+}
+
+#[derive(Serialize)]
+pub struct PromptQuestion {
+    pub id: String,
+    pub text: String,
+    pub kind: String, // "TEXT" (text, voice or video answers) or "POLL"
+}
+
 #[derive(Serialize, Debug, sqlx::FromRow)]
 pub struct ProfileDetails {
     pub name: Option<String>,
     pub bio: Option<String>,
+    // Only in the user's own profile; everyone else sees `age`
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub birthdate: Option<String>, // DATE - "1995-03-15"
+    #[sqlx(default)]
+    pub age: Option<i32>,
     pub pronouns: Option<String>,  // "she/her", "he/him"
     pub gender: Option<String>,    // "Woman", "Man", "Non-binary"
     pub sexuality: Option<String>, // "Straight", "Gay", "Bisexual"
@@ -71,12 +330,16 @@ pub struct ProfileDetails {
     pub religion: Option<String>,
     pub relationship_type: Option<String>, // "Monogamy"
     pub dating_intention: Option<String>,  // "Long-term relationship"
-    pub drinks: Option<String>,            // "Socially", "No"
+    pub drinks: Option<String>,            // "Yes", "Sometimes", "No"
     pub smokes: Option<String>,
+    // Only in the user's own profile: fields hidden from everyone else
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(default)]
+    pub hidden_fields: Option<Vec<String>>,
 }
 
 /// Profile with user_id for feed suggestions
-#[derive(Serialize, Debug, sqlx::FromRow)]
+#[derive(Serialize, Debug, Clone, Default, sqlx::FromRow)]
 pub struct SuggestionProfile {
     pub user_id: String,
     pub name: Option<String>,
@@ -97,6 +360,85 @@ pub struct SuggestionProfile {
     pub dating_intention: Option<String>,
     pub drinks: Option<String>,
     pub smokes: Option<String>,
+    /// Fields the user hid from other users (still used for filtering and ranking)
+    pub hidden_fields: Vec<String>,
+}
+
+impl SuggestionProfile {
+    /// Card for the feed / likes list (details only, images and prompts are filled in by the caller)
+    /// Other users see the age, never the birthdate, and none of the hidden fields
+    pub fn into_user_profile(self) -> UserProfile {
+        fn visible<T>(hidden: &[String], field: &str, value: Option<T>) -> Option<T> {
+            value.filter(|_| !hidden.iter().any(|h| h == field))
+        }
+        let hidden = self.hidden_fields;
+
+        UserProfile {
+            id: self.user_id,
+            images: None,
+            prompts: None,
+            superliked_you: false,
+            details: Some(ProfileDetails {
+                name: self.name,
+                bio: self.bio,
+                age: self
+                    .birthdate
+                    .as_deref()
+                    .and_then(|b| birthdate::age_from_text(b, Utc::now().date_naive())),
+                birthdate: None,
+                pronouns: visible(&hidden, "pronouns", self.pronouns),
+                gender: visible(&hidden, "gender", self.gender),
+                sexuality: visible(&hidden, "sexuality", self.sexuality),
+                height: visible(&hidden, "height", self.height),
+                location: self.location,
+                job: visible(&hidden, "job", self.job),
+                company: visible(&hidden, "company", self.company),
+                school: visible(&hidden, "school", self.school),
+                ethnicity: visible(&hidden, "ethnicity", self.ethnicity),
+                politics: visible(&hidden, "politics", self.politics),
+                religion: visible(&hidden, "religion", self.religion),
+                relationship_type: visible(&hidden, "relationship_type", self.relationship_type),
+                dating_intention: visible(&hidden, "dating_intention", self.dating_intention),
+                drinks: visible(&hidden, "drinks", self.drinks),
+                smokes: visible(&hidden, "smokes", self.smokes),
+                hidden_fields: None,
+            }),
+        }
+    }
+}
+
+/// A like received by the current user, with the liker's profile and what they liked
+#[derive(Debug, sqlx::FromRow)]
+pub struct LikeReceivedRow {
+    #[sqlx(flatten)]
+    pub profile: SuggestionProfile,
+    pub is_superlike: bool,
+    pub context_type: Option<String>,
+    pub context_id: Option<String>,
+    pub comment: Option<String>,
+    pub poll_answer: Option<String>,
+    pub created_at: DateTime<Utc>,
+    // The liked image (if context_type = IMAGE)
+    pub image_url: Option<String>,
+    pub image_order: Option<i32>,
+    pub image_thumbnail_url: Option<String>,
+    pub image_card_url: Option<String>,
+    pub image_blurhash: Option<String>,
+    pub image_caption: Option<String>,
+    pub image_media_type: Option<String>,
+    pub image_video_url: Option<String>,
+    pub image_video_duration_ms: Option<i32>,
+    // The liked prompt (if context_type = PROMPT)
+    pub prompt_question_id: Option<String>,
+    pub prompt_question: Option<String>,
+    pub prompt_answer: Option<String>,
+    pub prompt_audio_url: Option<String>,
+    pub prompt_audio_duration_ms: Option<i32>,
+    pub prompt_video_url: Option<String>,
+    pub prompt_video_poster_url: Option<String>,
+    pub prompt_video_duration_ms: Option<i32>,
+    pub prompt_poll_options: Option<Vec<String>>,
+    pub prompt_order: Option<i32>,
 }
 
 #[derive(Serialize)]
@@ -104,6 +446,18 @@ pub struct ImageUploadResponse {
     pub id: String,
     pub url: String,
     pub order: i32,
+    pub media_type: String, // "IMAGE" or "VIDEO"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_duration_ms: Option<i32>,
+    pub renditions: ImageRenditions,
+    pub blurhash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    pub moderation_status: String, // "PENDING", "APPROVED" or "REJECTED"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejection_reason: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -118,6 +472,36 @@ pub struct FinalizeProfileResponse {
 #[derive(Serialize)]
 pub struct FeedResponse {
     pub profiles: Vec<UserProfile>,
+    pub next_cursor: Option<String>, // None when the snapshot is exhausted
+    pub like_quota: Option<LikeQuota>,
+}
+
+/// Where the user stands against their daily LIKE limit
+#[derive(Serialize, Debug, Clone)]
+pub struct LikeQuota {
+    pub limit: i64,
+    pub remaining: i64,
+    pub resets_at: String, // ISO String, end of the current quota window (a local midnight)
+}
+
+// Likes
+#[derive(Serialize)]
+pub struct LikeReceived {
+    pub user: UserProfile,
+    pub is_superlike: bool,
+    pub context_type: Option<String>, // "IMAGE" or "PROMPT"
+    pub context_id: Option<String>,
+    pub liked_image: Option<UserImage>,
+    pub liked_prompt: Option<UserPrompt>,
+    pub comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poll_answer: Option<String>, // the option they voted for, when they liked a poll
+    pub created_at: String, // ISO String
+}
+
+#[derive(Serialize)]
+pub struct LikesReceivedResponse {
+    pub likes: Vec<LikeReceived>,
 }
 
 // Interactions
@@ -126,6 +510,18 @@ pub struct MatchResponse {
     pub status: String, // "MATCH" or "SENT"
     pub match_id: Option<String>,
     pub match_data: Option<MatchData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub superlikes_remaining: Option<i32>, // only after a SUPERLIKE
+    pub likes_remaining: Option<i64>, // today's LIKE quota
+}
+
+#[derive(Serialize)]
+pub struct UndoResponse {
+    pub status: String,
+    pub target_user_id: String,
+    pub action: String,           // the interaction that was undone
+    pub profile: Option<UserProfile>, // to put back at the top of the feed
+    pub undos_remaining: i64,     // for today
 }
 
 #[derive(Serialize)]
@@ -160,3 +556,83 @@ pub struct Message {
     pub text: String,
     pub created_at: String,
 }
+
+// Reports
+#[derive(Serialize)]
+pub struct CreateReportResponse {
+    pub status: String,
+    pub report_id: String,
+}
+
+/// A row from the reports table
+#[derive(sqlx::FromRow)]
+pub struct ReportRow {
+    pub id: Uuid,
+    pub reporter_id: Option<Uuid>,
+    pub target_user_id: Uuid,
+    pub context_type: Option<String>,
+    pub context_id: Option<Uuid>,
+    pub context_snapshot: Option<String>,
+    pub reason: String,
+    pub details: Option<String>,
+    pub status: String,
+    pub assigned_to: Option<Uuid>,
+    pub resolution_action: Option<String>,
+    pub resolution_note: Option<String>,
+    pub resolved_by: Option<Uuid>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A report as shown in the admin moderation queue
+#[derive(Serialize)]
+pub struct Report {
+    pub id: String,
+    pub reporter_id: Option<String>,
+    pub target_user_id: String,
+    pub context_type: Option<String>,
+    pub context_id: Option<String>,
+    pub context_snapshot: Option<String>,
+    pub reason: String,
+    pub details: Option<String>,
+    pub status: String,
+    pub assigned_to: Option<String>,
+    pub resolution_action: Option<String>,
+    pub resolution_note: Option<String>,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<String>,
+    pub created_at: String,
+}
+
+impl From<ReportRow> for Report {
+    fn from(row: ReportRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            reporter_id: row.reporter_id.map(|id| id.to_string()),
+            target_user_id: row.target_user_id.to_string(),
+            context_type: row.context_type,
+            context_id: row.context_id.map(|id| id.to_string()),
+            context_snapshot: row.context_snapshot,
+            reason: row.reason,
+            details: row.details,
+            status: row.status,
+            assigned_to: row.assigned_to.map(|id| id.to_string()),
+            resolution_action: row.resolution_action,
+            resolution_note: row.resolution_note,
+            resolved_by: row.resolved_by.map(|id| id.to_string()),
+            resolved_at: row.resolved_at.map(|at| at.to_rfc3339()),
+            created_at: row.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ReportsResponse {
+    pub reports: Vec<Report>,
+}
+
+/// Allowed values per controlled profile field ("drinks" -> ["Yes", "Sometimes", "No"])
+#[derive(Serialize)]
+pub struct OptionsResponse {
+    pub options: std::collections::BTreeMap<String, Vec<String>>,
+}
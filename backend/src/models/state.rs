@@ -1,6 +1,76 @@
 use std::sync::Mutex;
 use std::collections::HashMap;
 
+use crate::routes::feed::ranker::Ranker;
+use crate::routes::interactions::quota::Entitlements;
+use crate::routes::media::MediaStore;
+use crate::routes::media::moderation::ImageClassifier;
+use crate::routes::media::transcode::Transcoder;
+
 pub struct AppState {
     pub pending_verifications: Mutex<HashMap<String, String>>,
-}  
\ No newline at end of file
+    pub ranker: Box<dyn Ranker>,
+    pub rewind: RewindConfig,
+    pub superlikes: SuperlikeConfig,
+    /// Default daily LIKE limit (LIKE_DAILY_LIMIT)
+    pub like_daily_limit: i64,
+    pub entitlements: Box<dyn Entitlements>,
+    /// Where uploaded images are stored (MEDIA_STORE)
+    pub media: Box<dyn MediaStore>,
+    /// Approves or rejects uploaded images (IMAGE_CLASSIFIER)
+    pub classifier: Box<dyn ImageClassifier>,
+    /// Pulls poster frames out of uploaded videos (VIDEO_TRANSCODER)
+    pub transcoder: Box<dyn Transcoder>,
+}
+
+/// Limits for POST /interact/undo
+#[derive(Debug, Clone)]
+pub struct RewindConfig {
+    /// How long after an interaction it can still be undone
+    pub window_secs: i64,
+    /// Undos allowed per user per day
+    pub daily_limit: i64,
+}
+
+impl RewindConfig {
+    /// Read REWIND_WINDOW_SECS (default 300) and REWIND_DAILY_LIMIT (default 3)
+    pub fn from_env() -> Self {
+        let read = |key: &str, default: i64| -> i64 {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            window_secs: read("REWIND_WINDOW_SECS", 300),
+            daily_limit: read("REWIND_DAILY_LIMIT", 3),
+        }
+    }
+}
+
+/// Super-like allowance
+#[derive(Debug, Clone)]
+pub struct SuperlikeConfig {
+    /// Super-likes available per replenish period
+    pub allowance: i32,
+    /// Hours until a used allowance is topped back up
+    pub replenish_hours: i32,
+}
+
+impl SuperlikeConfig {
+    /// Read SUPERLIKE_ALLOWANCE (default 1) and SUPERLIKE_REPLENISH_HOURS (default 24)
+    pub fn from_env() -> Self {
+        let read = |key: &str, default: i32| -> i32 {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            allowance: read("SUPERLIKE_ALLOWANCE", 1),
+            replenish_hours: read("SUPERLIKE_REPLENISH_HOURS", 24),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,374 @@
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, web};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::{feed_queries, images_queries, profile_queries, prompt_queries, report_queries, user_queries};
+use crate::jwtauth::Claims;
+use crate::models::inputs::{
+    AccountStatus, AssignReportRequest, ModerationAction, ReportContextType, ReportStatus, ReportsQuery,
+    ResolveReportRequest, UpdateAccountStatusRequest,
+};
+use crate::models::state::AppState;
+use crate::routes::{media, prompts};
+use crate::routes::profile::completeness;
+use crate::models::outputs::{ErrorCode, ErrorResponse, ReportRow, ReportsResponse, StatusResponse};
+
+/// The calling user's id, if they are an admin
+async fn require_admin(pool: &PgPool, req: &HttpRequest) -> Result<Uuid, HttpResponse> {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return Err(HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        }));
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return Err(HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        }));
+    };
+
+    match user_queries::is_admin(pool, &user_id).await {
+        Ok(true) => Ok(user_id),
+        Ok(false) => Err(HttpResponse::Forbidden().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Admin access required".to_string()),
+        })),
+        Err(e) => {
+            println!("Failed to check admin: {:?}", e);
+            Err(HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to check admin access".to_string()),
+            }))
+        }
+    }
+}
+
+/// Load a report that is still waiting for a decision
+async fn open_report(pool: &PgPool, path: &str) -> Result<ReportRow, HttpResponse> {
+    let not_found = || HttpResponse::NotFound().json(ErrorResponse::new(ErrorCode::ReportNotFound, "Report not found"));
+
+    let Ok(report_id) = Uuid::parse_str(path) else {
+        return Err(not_found());
+    };
+
+    let report = match report_queries::get_report(pool, &report_id).await {
+        Ok(Some(report)) => report,
+        Ok(None) => return Err(not_found()),
+        Err(e) => {
+            println!("Failed to get report: {:?}", e);
+            return Err(HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to get report".to_string()),
+            }));
+        }
+    };
+
+    if !ReportStatus::parse(&report.status).is_some_and(|s| s.is_open()) {
+        return Err(HttpResponse::Conflict().json(ErrorResponse::new(
+            ErrorCode::ReportClosed,
+            "Report is already resolved",
+        )));
+    }
+
+    Ok(report)
+}
+
+/// GET /admin/reports - The moderation queue, oldest first
+pub async fn list_reports(pool: web::Data<PgPool>, req: HttpRequest, query: web::Query<ReportsQuery>) -> impl Responder {
+    if let Err(response) = require_admin(&pool, &req).await {
+        return response;
+    }
+
+    match report_queries::list_reports(&pool, query.status).await {
+        Ok(rows) => HttpResponse::Ok().json(ReportsResponse {
+            reports: rows.into_iter().map(Into::into).collect(),
+        }),
+        Err(e) => {
+            println!("Failed to list reports: {:?}", e);
+            HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to list reports".to_string()),
+            })
+        }
+    }
+}
+
+/// POST /admin/reports/{id}/assign - Assign a report to a moderator (the caller by default)
+pub async fn assign_report(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<AssignReportRequest>,
+) -> impl Responder {
+    let admin_id = match require_admin(&pool, &req).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let report = match open_report(&pool, &path).await {
+        Ok(report) => report,
+        Err(response) => return response,
+    };
+
+    let assignee_id = match &body.assignee_id {
+        Some(id) => {
+            let assignee = Uuid::parse_str(id).ok();
+            let is_admin = match assignee {
+                Some(id) => user_queries::is_admin(&pool, &id).await.unwrap_or(false),
+                None => false,
+            };
+
+            match assignee {
+                Some(id) if is_admin => id,
+                _ => {
+                    return HttpResponse::BadRequest().json(ErrorResponse::new(
+                        ErrorCode::InvalidTarget,
+                        "Reports can only be assigned to admins",
+                    ));
+                }
+            }
+        }
+        None => admin_id,
+    };
+
+    match report_queries::assign_report(&pool, &report.id, &assignee_id).await {
+        Ok(()) => HttpResponse::Ok().json(StatusResponse {
+            status: "success".to_string(),
+            message: Some("Report assigned".to_string()),
+        }),
+        Err(e) => {
+            println!("Failed to assign report: {:?}", e);
+            HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to assign report".to_string()),
+            })
+        }
+    }
+}
+
+/// POST /admin/reports/{id}/resolve - Close a report, optionally acting on the reported user
+pub async fn resolve_report(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<ResolveReportRequest>,
+) -> impl Responder {
+    let admin_id = match require_admin(&pool, &req).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let report = match open_report(&pool, &path).await {
+        Ok(report) => report,
+        Err(response) => return response,
+    };
+
+    let Some(action) = ModerationAction::parse(&body.action) else {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidResolution,
+            "Action must be NONE, REMOVE_IMAGE, REMOVE_PROMPT or BAN_USER",
+        ));
+    };
+
+    // Content removal needs a report about that piece of content
+    let context_type = report.context_type.as_deref().and_then(ReportContextType::parse);
+    let fits_report = match action {
+        ModerationAction::RemoveImage => context_type == Some(ReportContextType::Image),
+        ModerationAction::RemovePrompt => context_type == Some(ReportContextType::Prompt),
+        ModerationAction::None | ModerationAction::BanUser | ModerationAction::ShadowBanUser => true,
+    };
+
+    if !fits_report {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidResolution,
+            "This report isn't about an image or prompt that can be removed",
+        ));
+    }
+
+    // Look up a removed image's or prompt recording's files while its row still exists
+    let removed_image_key = match (action, report.context_id) {
+        (ModerationAction::RemoveImage, Some(image_id)) => match images_queries::get_storage_key(&pool, &image_id).await {
+            Ok(key) => key,
+            Err(e) => {
+                println!("Failed to look up image files: {:?}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+    let removed_prompt_keys = match (action, report.context_id) {
+        (ModerationAction::RemovePrompt, Some(prompt_id)) => match prompt_queries::get_media_keys(&pool, &prompt_id).await {
+            Ok(keys) => Some(keys),
+            Err(e) => {
+                println!("Failed to look up prompt recording: {:?}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    match report_queries::resolve_report(&pool, &report, &admin_id, action, body.note.as_deref()).await {
+        Ok(true) => {}
+        // Another moderator closed it since it was read
+        Ok(false) => {
+            return HttpResponse::Conflict().json(ErrorResponse::new(
+                ErrorCode::ReportClosed,
+                "Report is already resolved",
+            ));
+        }
+        Err(e) => {
+            println!("Failed to resolve report: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to resolve report".to_string()),
+            });
+        }
+    }
+
+    // Feeds built with the removed content or banned user need rebuilding
+    if action != ModerationAction::None
+        && let Err(e) = feed_queries::request_pool_refresh(&pool, &report.target_user_id).await
+    {
+        println!("Failed to request feed pool refresh: {:?}", e);
+    }
+
+    if let Some(key) = removed_image_key {
+        media::delete_image_files(state.media.as_ref(), &key).await;
+    }
+    if let Some(keys) = removed_prompt_keys {
+        prompts::delete_recordings(state.media.as_ref(), &keys).await;
+    }
+
+    // A removed image or prompt can take the profile below the bar
+    if matches!(action, ModerationAction::RemoveImage | ModerationAction::RemovePrompt)
+        && let Err(e) = completeness::recheck(&pool, &report.target_user_id).await
+    {
+        println!("Failed to recheck profile completeness: {:?}", e);
+    }
+
+    HttpResponse::Ok().json(StatusResponse {
+        status: "success".to_string(),
+        message: Some("Report resolved".to_string()),
+    })
+}
+
+/// POST /admin/users/{id}/status - Ban, shadow-ban or reinstate a user
+/// Every change is written to the account status audit log
+pub async fn update_account_status(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpdateAccountStatusRequest>,
+) -> impl Responder {
+    let admin_id = match require_admin(&pool, &req).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let Ok(target_user_id) = Uuid::parse_str(&path) else {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidTarget,
+            "Invalid target user ID",
+        ));
+    };
+
+    if target_user_id == admin_id {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::SelfInteraction,
+            "You can't change your own account status",
+        ));
+    }
+
+    let Some(status) = AccountStatus::parse(&body.status) else {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidStatus,
+            "Status must be ACTIVE, BANNED or SHADOW_BANNED",
+        ));
+    };
+
+    match user_queries::user_exists(&pool, &target_user_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::NotFound().json(ErrorResponse::new(
+                ErrorCode::TargetNotFound,
+                "Target user not found",
+            ));
+        }
+        Err(e) => {
+            println!("Failed to check target user: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to update account status".to_string()),
+            });
+        }
+    }
+
+    let changed = match user_queries::update_account_status(
+        &pool,
+        &target_user_id,
+        status,
+        Some(&admin_id),
+        body.reason.as_deref(),
+    )
+    .await
+    {
+        Ok(changed) => changed,
+        Err(e) => {
+            println!("Failed to update account status: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to update account status".to_string()),
+            });
+        }
+    };
+
+    // Hide (or bring back) the user in other people's precomputed feeds
+    if changed && let Err(e) = feed_queries::request_pool_refresh(&pool, &target_user_id).await {
+        println!("Failed to request feed pool refresh: {:?}", e);
+    }
+
+    HttpResponse::Ok().json(StatusResponse {
+        status: "success".to_string(),
+        message: Some(if changed {
+            format!("Account status set to {}", status.as_str())
+        } else {
+            format!("Account status already {}", status.as_str())
+        }),
+    })
+}
+
+/// POST /admin/users/{id}/unlock-birthdate - Let a user correct their birthdate once
+/// The unlock is used up by the next birthdate change
+pub async fn unlock_birthdate(pool: web::Data<PgPool>, req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    if let Err(response) = require_admin(&pool, &req).await {
+        return response;
+    }
+
+    let Ok(target_user_id) = Uuid::parse_str(&path) else {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidTarget,
+            "Invalid target user ID",
+        ));
+    };
+
+    match profile_queries::unlock_birthdate(&pool, &target_user_id).await {
+        Ok(true) => HttpResponse::Ok().json(StatusResponse {
+            status: "success".to_string(),
+            message: Some("Birthdate unlocked for one change".to_string()),
+        }),
+        Ok(false) => HttpResponse::NotFound().json(ErrorResponse::new(
+            ErrorCode::TargetNotFound,
+            "Target user has no profile",
+        )),
+        Err(e) => {
+            println!("Failed to unlock birthdate: {:?}", e);
+            HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to unlock birthdate".to_string()),
+            })
+        }
+    }
+}
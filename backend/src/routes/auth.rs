@@ -1,5 +1,5 @@
-use crate::models::inputs::{PhoneLoginRequest, PhoneVerifyRequest};
-use crate::models::outputs::{AuthResponse, LoginResponse, StatusResponse, UserSummary};
+use crate::models::inputs::{AccountStatus, PhoneLoginRequest, PhoneVerifyRequest};
+use crate::models::outputs::{AuthResponse, ErrorCode, ErrorResponse, LoginResponse, StatusResponse, UserSummary};
 use crate::models::state::AppState;
 use crate::jwtauth::Claims;
 use actix_web::{HttpResponse, Responder, web};
@@ -60,6 +60,46 @@ pub async fn phone_verify(body: web::Json<PhoneVerifyRequest>, pool: web::Data<P
         }
     };
 
+    let Ok(user_uuid) = uuid::Uuid::parse_str(&user_id) else {
+        println!("Invalid user id from get_or_create_user: {}", user_id);
+        return HttpResponse::InternalServerError().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Database error".to_string()),
+        });
+    };
+
+    // Banned accounts can't sign in
+    match db::user_queries::get_account_status(&pool, &user_uuid).await {
+        Ok(Some(AccountStatus::Banned)) => {
+            return HttpResponse::Forbidden().json(ErrorResponse::new(
+                ErrorCode::AccountBanned,
+                "This account has been banned",
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            println!("Failed to get account status: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Database error".to_string()),
+            });
+        }
+    }
+
+    // Keep last_active current for feed ranking and the feed pool job
+    if let Err(e) = db::user_queries::touch_last_active(&pool, &user_uuid).await {
+        println!("Failed to update last_active: {:?}", e);
+    }
+
+    // Set by /profile/finalize, so the app knows whether to resume onboarding
+    let is_profile_complete = match db::user_queries::is_profile_complete(&pool, &user_uuid).await {
+        Ok(complete) => complete,
+        Err(e) => {
+            println!("Failed to get profile completeness: {:?}", e);
+            false
+        }
+    };
+
     // Step 2: Create token with user_id as subject
     let my_claim = Claims {
         sub: user_id.clone(),
@@ -77,7 +117,7 @@ pub async fn phone_verify(body: web::Json<PhoneVerifyRequest>, pool: web::Data<P
                 token,
                 user: UserSummary {
                     id: user_id,
-                    is_profile_complete: false,
+                    is_profile_complete,
                     is_new_user,
                 },
             })
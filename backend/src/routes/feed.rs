@@ -1,99 +1,258 @@
+pub mod candidate_pool;
+pub mod ranker;
+
+use std::collections::HashMap;
+
 use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, web};
+use chrono::{Duration, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::db::{profile_queries, user_queries};
+use crate::db::{feed_queries, images_queries, interact_queries, profile_queries, user_queries};
 use crate::jwtauth::Claims;
-use crate::models::inputs::Preferences;
-use crate::models::outputs::{FeedResponse, ProfileDetails, StatusResponse, UserProfile};
+use crate::models::inputs::{FeedQuery, Preferences};
+use crate::models::outputs::{FeedResponse, StatusResponse, UserImage, UserProfile};
+use crate::models::state::AppState;
+use crate::routes::interactions::quota;
 
-pub async fn get_feed(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
-    let Some(claim) = req.extensions().get::<Claims>().cloned() else {
-        return HttpResponse::Unauthorized().json(StatusResponse {
-            status: "error".to_string(),
-            message: Some("No authentication claims found".to_string()),
-        });
-    };
+/// Number of profiles returned per feed request
+const FEED_SIZE: usize = 20;
 
-    let Ok(user_id) = Uuid::parse_str(&claim.sub) else {
-        return HttpResponse::Unauthorized().json(StatusResponse {
-            status: "error".to_string(),
-            message: Some("Invalid user ID format".to_string()),
-        });
-    };
+/// Cursor format: "<snapshot id>.<offset>". Clients treat it as opaque.
+fn encode_cursor(snapshot_id: &Uuid, offset: usize) -> String {
+    format!("{}.{}", snapshot_id.simple(), offset)
+}
 
+fn decode_cursor(cursor: &str) -> Option<(Uuid, usize)> {
+    let (snapshot_id, offset) = cursor.split_once('.')?;
+    Some((Uuid::parse_str(snapshot_id).ok()?, offset.parse().ok()?))
+}
+
+/// Ranked candidate ids for the user, best first
+/// Uses the precomputed pool when it is fresh, otherwise ranks live
+async fn rank_feed(pool: &PgPool, state: &AppState, user_id: &Uuid) -> Result<Vec<Uuid>, HttpResponse> {
     // Get user's preferences (from users table - JSONB field)
-    let preferences = match user_queries::get_user_preferences(&pool, &user_id).await {
+    let preferences = match user_queries::get_user_preferences(pool, user_id).await {
         Ok(prefs) => prefs,
         Err(e) => {
-            return HttpResponse::InternalServerError().json(StatusResponse {
+            return Err(HttpResponse::InternalServerError().json(StatusResponse {
                 status: "error".to_string(),
                 message: Some(format!("Failed to get preferences: {}", e)),
-            });
+            }));
         }
     };
 
     // Check if preferences exist
     let Some(prefs_json) = preferences else {
-        return HttpResponse::BadRequest().json(StatusResponse {
+        return Err(HttpResponse::BadRequest().json(StatusResponse {
             status: "error".to_string(),
             message: Some("User has no preferences set".to_string()),
-        });
+        }));
     };
 
     // Parse the JSON into Preferences struct
     let preference: Preferences = match serde_json::from_value(prefs_json) {
         Ok(p) => p,
         Err(e) => {
-            return HttpResponse::InternalServerError().json(StatusResponse {
+            return Err(HttpResponse::InternalServerError().json(StatusResponse {
                 status: "error".to_string(),
                 message: Some(format!("Failed to parse preferences: {}", e)),
-            });
+            }));
+        }
+    };
+
+    // Precomputed pool from the background job, if it is fresh
+    let pooled = match feed_queries::get_fresh_pool(pool, user_id).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            println!("Failed to read feed pool, ranking live: {:?}", e);
+            None
         }
     };
 
-    // Get suggestions based on gender preference only (for now)
-    let suggestions =
-        match profile_queries::get_suggestions(&pool, preference.gender_preference, &user_id).await
-        {
-            Ok(profiles) => profiles,
+    // Fall back to filtering and ranking live
+    let ranked = match pooled {
+        Some(ids) => ids,
+        None => match candidate_pool::rank_for_user(pool, state.ranker.as_ref(), user_id, &preference).await {
+            Ok(ids) => ids,
             Err(e) => {
-                return HttpResponse::InternalServerError().json(StatusResponse {
+                return Err(HttpResponse::InternalServerError().json(StatusResponse {
                     status: "error".to_string(),
                     message: Some(format!("Failed to get suggestions: {}", e)),
+                }));
+            }
+        },
+    };
+
+    // Profiles brought back with /interact/undo go first
+    let mut ids = match feed_queries::get_rewound_profiles(pool, user_id).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            println!("Failed to get rewound profiles: {:?}", e);
+            Vec::new()
+        }
+    };
+    ids.extend(ranked.into_iter().filter(|id| !ids.contains(id)).collect::<Vec<_>>());
+
+    Ok(ids)
+}
+
+/// GET /feed - Get a page of ranked profiles
+/// Without a cursor a new ranked snapshot is built; with a cursor the same snapshot is paged,
+/// so profiles aren't repeated or skipped while new users join
+pub async fn get_feed(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<FeedQuery>,
+) -> impl Responder {
+    let Some(claim) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        });
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claim.sub) else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        });
+    };
+
+    // Resolve the snapshot to page through
+    let (snapshot_id, profile_ids, offset) = match &query.cursor {
+        Some(cursor) => {
+            let Some((snapshot_id, offset)) = decode_cursor(cursor) else {
+                return HttpResponse::BadRequest().json(StatusResponse {
+                    status: "error".to_string(),
+                    message: Some("Invalid feed cursor".to_string()),
+                });
+            };
+
+            let snapshot = match feed_queries::get_snapshot(&pool, &snapshot_id, &user_id).await {
+                Ok(s) => s,
+                Err(e) => {
+                    return HttpResponse::InternalServerError().json(StatusResponse {
+                        status: "error".to_string(),
+                        message: Some(format!("Failed to get feed snapshot: {}", e)),
+                    });
+                }
+            };
+
+            let Some((profile_ids, created_at)) = snapshot else {
+                return HttpResponse::BadRequest().json(StatusResponse {
+                    status: "error".to_string(),
+                    message: Some("Invalid feed cursor".to_string()),
+                });
+            };
+
+            if Utc::now() - created_at > Duration::hours(feed_queries::SNAPSHOT_TTL_HOURS) {
+                return HttpResponse::Gone().json(StatusResponse {
+                    status: "error".to_string(),
+                    message: Some("Feed cursor expired, request the feed without a cursor".to_string()),
                 });
             }
-        };
+
+            (snapshot_id, profile_ids, offset)
+        }
+        None => {
+            let profile_ids = match rank_feed(&pool, &state, &user_id).await {
+                Ok(ids) => ids,
+                Err(response) => return response,
+            };
+
+            let snapshot_id = match feed_queries::create_snapshot(&pool, &user_id, &profile_ids).await {
+                Ok(id) => id,
+                Err(e) => {
+                    return HttpResponse::InternalServerError().json(StatusResponse {
+                        status: "error".to_string(),
+                        message: Some(format!("Failed to save feed snapshot: {}", e)),
+                    });
+                }
+            };
+
+            (snapshot_id, profile_ids, 0)
+        }
+    };
+
+    let start = offset.min(profile_ids.len());
+    let end = (start + FEED_SIZE).min(profile_ids.len());
+
+    // Snapshots can predate a block or ban, so drop those profiles from the page
+    let hidden = match feed_queries::get_hidden_among(&pool, &user_id, &profile_ids[start..end]).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some(format!("Failed to get suggestions: {}", e)),
+            });
+        }
+    };
+    let page_ids: Vec<Uuid> = profile_ids[start..end]
+        .iter()
+        .filter(|id| !hidden.contains(id))
+        .copied()
+        .collect();
+
+    let suggestions = match profile_queries::get_profiles_by_ids(&pool, &page_ids).await {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some(format!("Failed to get suggestions: {}", e)),
+            });
+        }
+    };
+
+    let next_cursor = (end < profile_ids.len()).then(|| encode_cursor(&snapshot_id, end));
 
     // Convert SuggestionProfile to UserProfile for the response
+    // Flag cards from people who super-liked the viewer
+    let superlikers = match interact_queries::get_superlikers(&pool, &user_id, &page_ids).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            println!("Failed to get super-likes: {:?}", e);
+            Vec::new()
+        }
+    };
+
+    // Only images that passed moderation are shown on cards
+    let mut images: HashMap<Uuid, Vec<UserImage>> = HashMap::new();
+    match images_queries::get_approved_images(&pool, &page_ids).await {
+        Ok(rows) => {
+            for (owner, row) in rows {
+                images.entry(owner).or_default().push(UserImage::from(row));
+            }
+        }
+        Err(e) => println!("Failed to get feed images: {:?}", e),
+    }
+
     let profiles: Vec<UserProfile> = suggestions
         .into_iter()
-        .map(|p| UserProfile {
-            id: p.user_id.clone(),
-            images: None,
-            prompts: None,
-            details: Some(ProfileDetails {
-                name: p.name,
-                bio: p.bio,
-                birthdate: p.birthdate,
-                pronouns: p.pronouns,
-                gender: p.gender,
-                sexuality: p.sexuality,
-                height: p.height,
-                location: p.location,
-                job: p.job,
-                company: p.company,
-                school: p.school,
-                ethnicity: p.ethnicity,
-                politics: p.politics,
-                religion: p.religion,
-                relationship_type: p.relationship_type,
-                dating_intention: p.dating_intention,
-                drinks: p.drinks,
-                smokes: p.smokes,
-            }),
+        .map(|p| {
+            let id = Uuid::parse_str(&p.user_id).ok();
+            let superliked_you = id.is_some_and(|id| superlikers.contains(&id));
+            UserProfile {
+                superliked_you,
+                images: Some(id.and_then(|id| images.remove(&id)).unwrap_or_default()),
+                ..p.into_user_profile()
+            }
         })
         .collect();
 
-    HttpResponse::Ok().json(FeedResponse { profiles })
+    // Today's LIKE quota so the client can show how many are left
+    let like_quota = match quota::like_quota(&pool, &state, &user_id).await {
+        Ok(q) => Some(q),
+        Err(e) => {
+            println!("Failed to get like quota: {:?}", e);
+            None
+        }
+    };
+
+    HttpResponse::Ok().json(FeedResponse {
+        profiles,
+        next_cursor,
+        like_quota,
+    })
 }
@@ -0,0 +1,117 @@
+//! Precomputed feed candidate pools
+//! A background job ranks each active user's candidates into `feed_pool_candidates`
+//! so `/feed` doesn't have to filter and rank on every request.
+
+use actix_web::rt;
+use actix_web::web;
+use chrono::Utc;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db::{feed_queries, profile_queries};
+use crate::models::inputs::Preferences;
+use crate::models::state::AppState;
+use crate::routes::feed::ranker::Ranker;
+use crate::routes::media::moderation;
+
+/// Number of candidates pulled from the database and ranked for one user
+pub const CANDIDATE_POOL_SIZE: i64 = 500;
+
+/// Number of users whose pool is rebuilt per job tick
+const POOL_BATCH_SIZE: i64 = 100;
+
+/// Default seconds between job ticks (override with FEED_POOL_INTERVAL_SECS)
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+
+/// Filter and rank every candidate for the user, best first
+pub async fn rank_for_user(
+    pool: &PgPool,
+    ranker: &dyn Ranker,
+    user_id: &Uuid,
+    preference: &Preferences,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    // Viewer's own signals (intention, relationship type, location) for ranking
+    let viewer = profile_queries::get_viewer(pool, user_id).await?;
+
+    // Get candidates based on gender preference only (for now)
+    let candidates = profile_queries::get_feed_candidates(
+        pool,
+        preference.gender_preference.clone(),
+        user_id,
+        CANDIDATE_POOL_SIZE,
+    )
+    .await?;
+
+    // Ids come from the profiles table so they always parse
+    Ok(ranker
+        .rank(&viewer, candidates, Utc::now())
+        .into_iter()
+        .filter_map(|c| Uuid::parse_str(&c.profile.user_id).ok())
+        .collect())
+}
+
+/// Rebuild one batch of missing or stale pools
+/// A user whose pool can't be rebuilt is logged and skipped; nothing is written for them,
+/// so their pool stays stale and is picked up again by the next batch
+/// Returns the number of pools rebuilt
+pub async fn refresh_pools(pool: &PgPool, ranker: &dyn Ranker) -> Result<usize, sqlx::Error> {
+    let users = feed_queries::get_users_needing_pool(pool, POOL_BATCH_SIZE).await?;
+    let mut rebuilt = 0;
+
+    for (user_id, prefs_json) in users {
+        let preference: Preferences = match serde_json::from_value(prefs_json) {
+            Ok(p) => p,
+            Err(e) => {
+                println!("Feed pool: skipping {} (bad preferences: {})", user_id, e);
+                continue;
+            }
+        };
+
+        let started_at = Utc::now();
+        let candidate_ids = match rank_for_user(pool, ranker, &user_id, &preference).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                println!("Feed pool: skipping {} (ranking failed: {:?})", user_id, e);
+                continue;
+            }
+        };
+
+        if let Err(e) = feed_queries::save_pool(pool, &user_id, &candidate_ids, started_at).await {
+            println!("Feed pool: skipping {} (saving failed: {:?})", user_id, e);
+            continue;
+        }
+        rebuilt += 1;
+    }
+
+    Ok(rebuilt)
+}
+
+/// Start the feed pool job on the current runtime
+/// Runs one batch every FEED_POOL_INTERVAL_SECS seconds (default 300), then retries images still waiting for moderation
+pub fn spawn_pool_job(pool: PgPool, state: web::Data<AppState>) {
+    let interval_secs = std::env::var("FEED_POOL_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(Duration::from_secs(interval_secs));
+
+        loop {
+            interval.tick().await;
+
+            match refresh_pools(&pool, state.ranker.as_ref()).await {
+                Ok(0) => {}
+                Ok(count) => println!("Feed pool: rebuilt {} pools", count),
+                Err(e) => println!("Feed pool: job failed: {:?}", e),
+            }
+
+            match moderation::review_pending(&pool, state.media.as_ref(), state.classifier.as_ref()).await {
+                Ok(0) => {}
+                Ok(count) => println!("Moderation: reviewed {} pending images and prompt videos", count),
+                Err(e) => println!("Moderation: retry failed: {:?}", e),
+            }
+        }
+    });
+}
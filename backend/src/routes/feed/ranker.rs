@@ -0,0 +1,383 @@
+//! Feed ranking
+//! Scores candidate profiles for a viewer so the feed isn't returned in table order.
+
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+
+use crate::models::outputs::SuggestionProfile;
+
+/// Number of profile attributes counted towards completeness
+const PROFILE_FIELD_COUNT: f64 = 18.0;
+
+/// The user asking for a feed
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+pub struct Viewer {
+    pub relationship_type: Option<String>,
+    pub dating_intention: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// A profile that could be shown to the viewer, with the signals used for ranking
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Candidate {
+    #[sqlx(flatten)]
+    pub profile: SuggestionProfile,
+    pub last_active: Option<DateTime<Utc>>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub image_count: i64,
+    pub prompt_count: i64,
+    /// The candidate has already liked the viewer
+    pub liked_viewer: bool,
+    /// Share of the candidate's interactions that were likes (0.0 - 1.0)
+    pub like_rate: f64,
+}
+
+impl Candidate {
+    /// Fraction of details, images (6) and prompts (3) filled in (0.0 - 1.0)
+    /// Location counts as filled when the candidate's coordinates are known (the profile's display `location` is never loaded)
+    pub fn completeness(&self) -> f64 {
+        let p = &self.profile;
+        let filled = [
+            p.name.is_some(),
+            p.bio.is_some(),
+            p.birthdate.is_some(),
+            p.pronouns.is_some(),
+            p.gender.is_some(),
+            p.sexuality.is_some(),
+            p.height.is_some(),
+            self.latitude.is_some() && self.longitude.is_some(),
+            p.job.is_some(),
+            p.company.is_some(),
+            p.school.is_some(),
+            p.ethnicity.is_some(),
+            p.politics.is_some(),
+            p.religion.is_some(),
+            p.relationship_type.is_some(),
+            p.dating_intention.is_some(),
+            p.drinks.is_some(),
+            p.smokes.is_some(),
+        ]
+        .iter()
+        .filter(|f| **f)
+        .count() as f64;
+
+        let details = filled / PROFILE_FIELD_COUNT;
+        let images = (self.image_count.min(6) as f64) / 6.0;
+        let prompts = (self.prompt_count.min(3) as f64) / 3.0;
+
+        (details + images + prompts) / 3.0
+    }
+
+    /// Distance to the viewer in km, if both locations are known
+    pub fn distance_km(&self, viewer: &Viewer) -> Option<f64> {
+        match (viewer.latitude, viewer.longitude, self.latitude, self.longitude) {
+            (Some(lat1), Some(lon1), Some(lat2), Some(lon2)) => {
+                Some(haversine_km(lat1, lon1, lat2, lon2))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Great-circle distance between two points in km
+fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Scores candidates for a viewer. Higher scores are shown first.
+pub trait Ranker: Send + Sync {
+    /// Short name used when comparing rankers
+    fn name(&self) -> &str;
+
+    fn score(&self, viewer: &Viewer, candidate: &Candidate, now: DateTime<Utc>) -> f64;
+
+    /// Sort candidates by score (descending); ties are broken by user id so the order is deterministic
+    fn rank(&self, viewer: &Viewer, candidates: Vec<Candidate>, now: DateTime<Utc>) -> Vec<Candidate> {
+        let mut scored: Vec<(f64, Candidate)> = candidates
+            .into_iter()
+            .map(|c| (self.score(viewer, &c, now), c))
+            .collect();
+
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .partial_cmp(a_score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.profile.user_id.cmp(&b.profile.user_id))
+        });
+
+        scored.into_iter().map(|(_, c)| c).collect()
+    }
+}
+
+/// Weights for each ranking signal
+#[derive(Debug, Clone)]
+pub struct RankingWeights {
+    pub dating_intention: f64,
+    pub relationship_type: f64,
+    pub recency: f64,
+    pub completeness: f64,
+    pub distance: f64,
+    pub mutual_like: f64,
+    /// Hours after which the recency signal has halved
+    pub recency_half_life_hours: f64,
+    /// Distance (km) at which the distance signal has halved
+    pub distance_half_km: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            dating_intention: 3.0,
+            relationship_type: 2.0,
+            recency: 1.5,
+            completeness: 1.0,
+            distance: 1.0,
+            mutual_like: 2.5,
+            recency_half_life_hours: 72.0,
+            distance_half_km: 25.0,
+        }
+    }
+}
+
+impl RankingWeights {
+    /// Read weights from RANK_WEIGHT_* env vars, falling back to the defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |key: &str, default: f64| -> f64 {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            dating_intention: read("RANK_WEIGHT_DATING_INTENTION", defaults.dating_intention),
+            relationship_type: read("RANK_WEIGHT_RELATIONSHIP_TYPE", defaults.relationship_type),
+            recency: read("RANK_WEIGHT_RECENCY", defaults.recency),
+            completeness: read("RANK_WEIGHT_COMPLETENESS", defaults.completeness),
+            distance: read("RANK_WEIGHT_DISTANCE", defaults.distance),
+            mutual_like: read("RANK_WEIGHT_MUTUAL_LIKE", defaults.mutual_like),
+            recency_half_life_hours: read("RANK_RECENCY_HALF_LIFE_HOURS", defaults.recency_half_life_hours),
+            distance_half_km: read("RANK_DISTANCE_HALF_KM", defaults.distance_half_km),
+        }
+    }
+}
+
+/// Default ranker: weighted sum of the individual signals (each 0.0 - 1.0)
+#[derive(Debug, Clone, Default)]
+pub struct WeightedRanker {
+    pub weights: RankingWeights,
+}
+
+impl WeightedRanker {
+    pub fn new(weights: RankingWeights) -> Self {
+        Self { weights }
+    }
+}
+
+/// 1.0 if both sides have the same (case-insensitive) value, 0.0 otherwise
+fn same_value(a: &Option<String>, b: &Option<String>) -> f64 {
+    match (a, b) {
+        (Some(a), Some(b)) if a.trim().eq_ignore_ascii_case(b.trim()) => 1.0,
+        _ => 0.0,
+    }
+}
+
+impl Ranker for WeightedRanker {
+    fn name(&self) -> &str {
+        "weighted"
+    }
+
+    fn score(&self, viewer: &Viewer, candidate: &Candidate, now: DateTime<Utc>) -> f64 {
+        let w = &self.weights;
+
+        let intention = same_value(&viewer.dating_intention, &candidate.profile.dating_intention);
+        let relationship = same_value(&viewer.relationship_type, &candidate.profile.relationship_type);
+
+        let recency = match candidate.last_active {
+            Some(at) => {
+                let hours = (now - at).num_minutes().max(0) as f64 / 60.0;
+                0.5_f64.powf(hours / w.recency_half_life_hours)
+            }
+            None => 0.0,
+        };
+
+        let distance = match candidate.distance_km(viewer) {
+            Some(km) => w.distance_half_km / (w.distance_half_km + km),
+            None => 0.0,
+        };
+
+        let mutual_like = if candidate.liked_viewer {
+            1.0
+        } else {
+            candidate.like_rate.clamp(0.0, 1.0)
+        };
+
+        w.dating_intention * intention
+            + w.relationship_type * relationship
+            + w.recency * recency
+            + w.completeness * candidate.completeness()
+            + w.distance * distance
+            + w.mutual_like * mutual_like
+    }
+}
+
+/// Keeps candidates in the order the database returned them (the old behaviour)
+#[derive(Debug, Clone, Default)]
+pub struct TableOrderRanker;
+
+impl Ranker for TableOrderRanker {
+    fn name(&self) -> &str {
+        "table-order"
+    }
+
+    fn score(&self, _viewer: &Viewer, _candidate: &Candidate, _now: DateTime<Utc>) -> f64 {
+        0.0
+    }
+
+    fn rank(&self, _viewer: &Viewer, candidates: Vec<Candidate>, _now: DateTime<Utc>) -> Vec<Candidate> {
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap()
+    }
+
+    fn viewer() -> Viewer {
+        Viewer {
+            relationship_type: Some("Monogamy".to_string()),
+            dating_intention: Some("Long-term relationship".to_string()),
+            latitude: Some(34.05),
+            longitude: Some(-118.25),
+        }
+    }
+
+    /// A candidate with no signals at all, so each test only sets the one it's about
+    fn candidate(id: &str) -> Candidate {
+        Candidate {
+            profile: SuggestionProfile { user_id: id.to_string(), ..Default::default() },
+            last_active: None,
+            latitude: None,
+            longitude: None,
+            image_count: 0,
+            prompt_count: 0,
+            liked_viewer: false,
+            like_rate: 0.0,
+        }
+    }
+
+    fn ranked_ids(ranker: &dyn Ranker, candidates: Vec<Candidate>) -> Vec<String> {
+        ranker
+            .rank(&viewer(), candidates, now())
+            .into_iter()
+            .map(|c| c.profile.user_id)
+            .collect()
+    }
+
+    #[test]
+    fn ties_are_broken_by_user_id() {
+        let ids = ranked_ids(&WeightedRanker::default(), vec![candidate("c"), candidate("a"), candidate("b")]);
+        assert_eq!(ids, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn matching_dating_intention_ranks_first() {
+        let mut matching = candidate("b");
+        matching.profile.dating_intention = Some(" long-term RELATIONSHIP ".to_string());
+        let mut other = candidate("a");
+        other.profile.dating_intention = Some("Short-term fun".to_string());
+
+        assert_eq!(ranked_ids(&WeightedRanker::default(), vec![other, matching]), ["b", "a"]);
+    }
+
+    #[test]
+    fn recently_active_ranks_above_stale() {
+        let mut recent = candidate("b");
+        recent.last_active = Some(now() - Duration::hours(1));
+        let mut stale = candidate("a");
+        stale.last_active = Some(now() - Duration::days(30));
+
+        assert_eq!(ranked_ids(&WeightedRanker::default(), vec![stale, recent]), ["b", "a"]);
+    }
+
+    #[test]
+    fn recency_halves_after_half_life() {
+        let ranker = WeightedRanker::new(RankingWeights {
+            dating_intention: 0.0,
+            relationship_type: 0.0,
+            recency: 1.0,
+            completeness: 0.0,
+            distance: 0.0,
+            mutual_like: 0.0,
+            ..Default::default()
+        });
+        let mut c = candidate("a");
+        c.last_active = Some(now() - Duration::hours(72));
+
+        assert!((ranker.score(&viewer(), &c, now()) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn nearer_ranks_above_farther() {
+        let mut near = candidate("b");
+        (near.latitude, near.longitude) = (Some(34.06), Some(-118.25));
+        let mut far = candidate("a");
+        (far.latitude, far.longitude) = (Some(40.71), Some(-74.0));
+
+        assert_eq!(ranked_ids(&WeightedRanker::default(), vec![far, near]), ["b", "a"]);
+    }
+
+    #[test]
+    fn having_liked_the_viewer_outweighs_recency() {
+        let mut liker = candidate("b");
+        liker.liked_viewer = true;
+        let mut active = candidate("a");
+        active.last_active = Some(now());
+
+        assert_eq!(ranked_ids(&WeightedRanker::default(), vec![active, liker]), ["b", "a"]);
+    }
+
+    #[test]
+    fn completeness_counts_coordinates_as_location() {
+        let without = candidate("a");
+        let mut with = candidate("a");
+        (with.latitude, with.longitude) = (Some(34.05), Some(-118.25));
+
+        let one_field = 1.0 / PROFILE_FIELD_COUNT / 3.0;
+        assert_eq!(without.completeness(), 0.0);
+        assert!((with.completeness() - one_field).abs() < 1e-9);
+    }
+
+    #[test]
+    fn completeness_caps_images_and_prompts() {
+        let mut c = candidate("a");
+        c.image_count = 9;
+        c.prompt_count = 5;
+
+        assert!((c.completeness() - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn table_order_keeps_database_order() {
+        let mut liker = candidate("a");
+        liker.liked_viewer = true;
+
+        let ids = ranked_ids(&TableOrderRanker, vec![candidate("c"), liker, candidate("b")]);
+        assert_eq!(ids, ["c", "a", "b"]);
+    }
+}
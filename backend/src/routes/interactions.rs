@@ -1,12 +1,21 @@
-use crate::db::interact_queries;
+pub mod quota;
+
+use crate::db::interact_queries::{InteractLimits, InteractOutcome, LikeContext, MatchOutcome, UndoOutcome};
+use crate::db::{images_queries, interact_queries, profile_queries, prompt_queries, user_queries};
 use crate::jwtauth::Claims;
-use crate::models::inputs::InteractRequest;
-use crate::models::outputs::StatusResponse;
+use crate::models::inputs::{ContextType, InteractAction, InteractRequest};
+use crate::models::outputs::{ErrorCode, ErrorResponse, MatchData, MatchResponse, StatusResponse, UndoResponse};
+use crate::models::state::AppState;
 use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, web};
 use sqlx::PgPool;
 use uuid::Uuid;
 
-pub async fn interact(body: web::Json<InteractRequest>, pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+pub async fn interact(
+    body: web::Json<InteractRequest>,
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+) -> impl Responder {
     let claims = match req.extensions().get::<Claims>().cloned() {
         Some(c) => c,
         None => return HttpResponse::Unauthorized().json(StatusResponse {
@@ -24,20 +33,308 @@ pub async fn interact(body: web::Json<InteractRequest>, pool: web::Data<PgPool>,
     };
 
     let Ok(target_user_id) = Uuid::parse_str(&body.target_user_id) else {
-        return HttpResponse::BadRequest().json(StatusResponse {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidTarget,
+            "Invalid target user ID",
+        ));
+    };
+
+    if target_user_id == user_id {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::SelfInteraction,
+            "You can't interact with yourself",
+        ));
+    }
+
+    let Some(action) = InteractAction::parse(&body.action) else {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidAction,
+            "Action must be LIKE, SUPERLIKE or PASS",
+        ));
+    };
+
+    match user_queries::user_exists(&pool, &target_user_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::NotFound().json(ErrorResponse::new(
+                ErrorCode::TargetNotFound,
+                "Target user not found",
+            ));
+        }
+        Err(e) => {
+            println!("Failed to check target user: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to record interaction".to_string()),
+            });
+        }
+    }
+
+    // Context and comment only make sense on a like
+    if !action.is_like() && (body.context.is_some() || body.comment.is_some()) {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidContext,
+            "Context and comment are only allowed on likes",
+        ));
+    }
+
+    // The liked image or prompt must belong to the target (and images must have passed moderation)
+    let context = match &body.context {
+        Some(ctx) => {
+            let (Some(context_type), Ok(context_id)) = (ContextType::parse(&ctx.r#type), Uuid::parse_str(&ctx.id)) else {
+                return HttpResponse::BadRequest().json(ErrorResponse::new(
+                    ErrorCode::InvalidContext,
+                    "Context must have type IMAGE or PROMPT and a valid id",
+                ));
+            };
+
+            let belongs = match context_type {
+                ContextType::Image => images_queries::approved_image_belongs_to(&pool, &context_id, &target_user_id).await,
+                ContextType::Prompt => prompt_queries::visible_prompt_belongs_to(&pool, &context_id, &target_user_id).await,
+            };
+
+            match belongs {
+                Ok(true) => {}
+                Ok(false) => {
+                    return HttpResponse::NotFound().json(ErrorResponse::new(
+                        ErrorCode::ContextNotFound,
+                        "Liked image or prompt not found on the target's profile",
+                    ));
+                }
+                Err(e) => {
+                    println!("Failed to check interaction context: {:?}", e);
+                    return HttpResponse::InternalServerError().json(StatusResponse {
+                        status: "error".to_string(),
+                        message: Some("Failed to record interaction".to_string()),
+                    });
+                }
+            }
+
+            // A vote is the text of the picked option, which must be one of the poll's
+            let poll_answer = match (ctx.poll_option, context_type) {
+                (None, _) => None,
+                (Some(_), ContextType::Image) => {
+                    return HttpResponse::BadRequest().json(ErrorResponse::new(
+                        ErrorCode::InvalidPollVote,
+                        "Only poll prompts can be voted on",
+                    ));
+                }
+                (Some(index), ContextType::Prompt) => match prompt_queries::get_poll_options(&pool, &context_id).await {
+                    Ok(options) => {
+                        let picked = options.and_then(|o| usize::try_from(index).ok().and_then(|i| o.into_iter().nth(i)));
+                        let Some(picked) = picked else {
+                            return HttpResponse::BadRequest().json(ErrorResponse::new(
+                                ErrorCode::InvalidPollVote,
+                                "poll_option must be the index of one of the poll's options",
+                            ));
+                        };
+                        Some(picked)
+                    }
+                    Err(e) => {
+                        println!("Failed to get poll options: {:?}", e);
+                        return HttpResponse::InternalServerError().json(StatusResponse {
+                            status: "error".to_string(),
+                            message: Some("Failed to record interaction".to_string()),
+                        });
+                    }
+                },
+            };
+
+            Some(LikeContext { context_type, context_id, poll_answer })
+        }
+        None => None,
+    };
+
+    // Daily LIKE quota (also reported back to the client); a LIKE is checked again when it's recorded
+    let like_quota = match quota::like_quota(&pool, &state, &user_id).await {
+        Ok(q) => q,
+        Err(e) => {
+            println!("Failed to check like quota: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to record interaction".to_string()),
+            });
+        }
+    };
+
+    if action == InteractAction::Like && like_quota.remaining <= 0 {
+        return HttpResponse::TooManyRequests().json(ErrorResponse::new(
+            ErrorCode::LikeLimitReached,
+            &format!("You're out of likes for today, more at {}", like_quota.resets_at),
+        ));
+    }
+
+    let outcome = match interact_queries::interact(
+        &pool,
+        &user_id,
+        &target_user_id,
+        action,
+        context,
+        body.comment.as_deref(),
+        &InteractLimits { superlikes: &state.superlikes, daily_likes: like_quota.limit },
+    )
+    .await
+    {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            println!("Failed to record interaction: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to record interaction".to_string()),
+            });
+        }
+    };
+
+    let (superlikes_remaining, likes_remaining) = match outcome {
+        InteractOutcome::Recorded { superlikes_remaining, likes_remaining } => {
+            (superlikes_remaining, Some(likes_remaining.unwrap_or(like_quota.remaining)))
+        }
+        InteractOutcome::SuperlikeAllowanceExhausted => {
+            return HttpResponse::TooManyRequests().json(ErrorResponse::new(
+                ErrorCode::SuperlikeAllowanceExhausted,
+                "No super-likes left, they replenish soon",
+            ));
+        }
+        InteractOutcome::LikeLimitReached { resets_at } => {
+            return HttpResponse::TooManyRequests().json(ErrorResponse::new(
+                ErrorCode::LikeLimitReached,
+                &format!("You're out of likes for today, more at {}", resets_at.to_rfc3339()),
+            ));
+        }
+    };
+
+    if !action.is_like() {
+        return HttpResponse::Ok().json(MatchResponse {
+            status: "SENT".to_string(),
+            match_id: None,
+            match_data: None,
+            superlikes_remaining,
+            likes_remaining,
+        });
+    }
+
+    // A like back (e.g. from the "likes you" list) creates the match
+    let outcome = match interact_queries::create_match_if_mutual(&pool, &user_id, &target_user_id).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            println!("Failed to create match: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to create match".to_string()),
+            });
+        }
+    };
+
+    let match_id = match outcome {
+        MatchOutcome::Matched(match_id) => Some(match_id),
+        // Looks like a match to the sender, who mustn't find out they're shadow-banned
+        MatchOutcome::Withheld => None,
+        MatchOutcome::NotMutual => {
+            return HttpResponse::Ok().json(MatchResponse {
+                status: "SENT".to_string(),
+                match_id: None,
+                match_data: None,
+                superlikes_remaining,
+                likes_remaining,
+            });
+        }
+    };
+
+    let match_data = match user_queries::get_user_summary(&pool, &target_user_id).await {
+        Ok(summary) => summary.map(|user| MatchData { user }),
+        Err(e) => {
+            println!("Failed to get matched user: {:?}", e);
+            None
+        }
+    };
+
+    HttpResponse::Ok().json(MatchResponse {
+        status: "MATCH".to_string(),
+        match_id: match_id.map(|id| id.to_string()),
+        match_data,
+        superlikes_remaining,
+        likes_remaining,
+    })
+}
+
+/// POST /interact/undo - Revert the user's most recent interaction
+/// Only allowed within the rewind window and daily quota; the profile goes back to the top of the feed
+pub async fn undo(pool: web::Data<PgPool>, state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
             status: "error".to_string(),
-            message: Some("Invalid target user ID".to_string()),
-        })
+            message: Some("Unauthorized".to_string()),
+        });
     };
 
-    match interact_queries::interact(&pool, &user_id, &target_user_id, &body.into_inner()).await {
-        Ok(_) => HttpResponse::Ok().json(StatusResponse {
-            status: "success".to_string(),
-            message: Some("Interaction recorded".to_string()),
-        }),
-        Err(_) => HttpResponse::InternalServerError().json(StatusResponse {
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
             status: "error".to_string(),
-            message: Some("Failed to record interaction".to_string()),
-        })
-    }
+            message: Some("Invalid user ID".to_string()),
+        });
+    };
+
+    let outcome = match interact_queries::undo_last_interaction(
+        &pool,
+        &user_id,
+        state.rewind.window_secs,
+        state.rewind.daily_limit,
+        &state.superlikes,
+    )
+    .await
+    {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            println!("Failed to undo interaction: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to undo interaction".to_string()),
+            });
+        }
+    };
+
+    let (target_user_id, action, undos_remaining) = match outcome {
+        UndoOutcome::Undone { to_user_id, action, undos_remaining } => (to_user_id, action, undos_remaining),
+        UndoOutcome::LimitReached => {
+            return HttpResponse::TooManyRequests().json(ErrorResponse::new(
+                ErrorCode::UndoLimitReached,
+                "No undos left today",
+            ));
+        }
+        UndoOutcome::NothingToUndo => {
+            return HttpResponse::NotFound().json(ErrorResponse::new(
+                ErrorCode::NothingToUndo,
+                "Nothing to undo",
+            ));
+        }
+        UndoOutcome::WindowExpired => {
+            return HttpResponse::Conflict().json(ErrorResponse::new(
+                ErrorCode::UndoWindowExpired,
+                "Your last interaction is too old to undo",
+            ));
+        }
+        UndoOutcome::AlreadyMatched => {
+            return HttpResponse::Conflict().json(ErrorResponse::new(
+                ErrorCode::AlreadyMatched,
+                "You already matched with this person",
+            ));
+        }
+    };
+
+    // The profile so the client can show it again straight away
+    let profile = match profile_queries::get_profiles_by_ids(&pool, &[target_user_id]).await {
+        Ok(profiles) => profiles.into_iter().next().map(|p| p.into_user_profile()),
+        Err(e) => {
+            println!("Failed to get undone profile: {:?}", e);
+            None
+        }
+    };
+
+    HttpResponse::Ok().json(UndoResponse {
+        status: "success".to_string(),
+        target_user_id: target_user_id.to_string(),
+        action,
+        profile,
+        undos_remaining,
+    })
 }
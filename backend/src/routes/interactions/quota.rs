@@ -0,0 +1,52 @@
+//! Daily LIKE quota
+//! Each user may send a limited number of LIKEs per day, reset at midnight in the time zone
+//! that was in effect when the day's window opened.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::interact_queries;
+use crate::models::outputs::LikeQuota;
+use crate::models::state::AppState;
+
+/// Decides a user's daily LIKE limit
+/// Plug in a different implementation to let subscriptions or promotions raise the limit
+pub trait Entitlements: Send + Sync {
+    /// `base` is the configured default; `granted` is the user's stored limit (users.daily_like_limit)
+    fn daily_like_limit(&self, user_id: &Uuid, base: i64, granted: Option<i64>) -> i64;
+}
+
+/// Default entitlements: a stored grant can only raise the limit, never lower it
+#[derive(Debug, Clone, Default)]
+pub struct StoredEntitlements;
+
+impl Entitlements for StoredEntitlements {
+    fn daily_like_limit(&self, _user_id: &Uuid, base: i64, granted: Option<i64>) -> i64 {
+        granted.map_or(base, |g| g.max(base))
+    }
+}
+
+/// Read LIKE_DAILY_LIMIT (default 50)
+pub fn base_limit_from_env() -> i64 {
+    std::env::var("LIKE_DAILY_LIMIT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(50)
+}
+
+/// Work out the user's LIKE quota for today
+pub async fn like_quota(pool: &PgPool, state: &AppState, user_id: &Uuid) -> Result<LikeQuota, sqlx::Error> {
+    let (used, granted, resets_at): (i64, Option<i32>, DateTime<Utc>) =
+        interact_queries::get_like_usage(pool, user_id).await?;
+
+    let limit = state
+        .entitlements
+        .daily_like_limit(user_id, state.like_daily_limit, granted.map(i64::from));
+
+    Ok(LikeQuota {
+        limit,
+        remaining: (limit - used).max(0),
+        resets_at: resets_at.to_rfc3339(),
+    })
+}
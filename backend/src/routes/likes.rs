@@ -0,0 +1,121 @@
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, web};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::{images_queries, interact_queries, prompt_queries};
+use crate::jwtauth::Claims;
+use crate::models::inputs::LikesReceivedQuery;
+use crate::models::outputs::{LikeReceived, LikesReceivedResponse, StatusResponse, UserImage, UserPrompt};
+
+/// GET /likes/received - People who liked the current user and haven't been answered yet
+/// Liking one of them back through /interact creates the match
+pub async fn get_likes_received(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    query: web::Query<LikesReceivedQuery>,
+) -> impl Responder {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        });
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        });
+    };
+
+    let order = query.order.unwrap_or_default();
+
+    let rows = match interact_queries::get_likes_received(&pool, &user_id, order).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Failed to get likes: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to retrieve likes".to_string()),
+            });
+        }
+    };
+
+    let mut likes: Vec<LikeReceived> = Vec::with_capacity(rows.len());
+
+    for row in rows {
+        let p = row.profile;
+
+        // Liker's approved photos and prompts for the card
+        let liker_id = Uuid::parse_str(&p.user_id).ok();
+        let images = match liker_id {
+            Some(id) => images_queries::get_approved_images(&pool, &[id])
+                .await
+                .ok()
+                .map(|rows| rows.into_iter().map(|(_, row)| UserImage::from(row)).collect()),
+            None => None,
+        };
+        let prompts = match liker_id {
+            Some(id) => prompt_queries::get_visible_prompts(&pool, &id)
+                .await
+                .ok()
+                .map(|rows| rows.into_iter().map(UserPrompt::from).collect()),
+            None => None,
+        };
+
+        let liked_image = match (&row.context_id, row.image_url, row.image_order) {
+            (Some(id), Some(url), Some(order)) => Some(UserImage {
+                media_type: row.image_media_type.unwrap_or_else(|| "IMAGE".to_string()),
+                video_url: row.image_video_url,
+                video_duration_ms: row.image_video_duration_ms,
+                ..UserImage::new(
+                    id.clone(),
+                    url,
+                    order,
+                    row.image_thumbnail_url,
+                    row.image_card_url,
+                    row.image_blurhash,
+                    row.image_caption,
+                )
+            }),
+            _ => None,
+        };
+        // Voice, video and poll prompts may have no text answer
+        let liked_prompt = match (&row.context_id, row.prompt_question_id, row.prompt_question, row.prompt_order) {
+            (Some(id), Some(question_id), Some(question), Some(order)) => Some(UserPrompt {
+                id: id.clone(),
+                question_id,
+                question,
+                answer: row.prompt_answer,
+                audio_url: row.prompt_audio_url,
+                audio_duration_ms: row.prompt_audio_duration_ms,
+                video_url: row.prompt_video_url,
+                video_poster_url: row.prompt_video_poster_url,
+                video_duration_ms: row.prompt_video_duration_ms,
+                video_moderation_status: None,
+                video_rejection_reason: None,
+                poll_options: row.prompt_poll_options,
+                order,
+            }),
+            _ => None,
+        };
+
+        let mut user = p.into_user_profile();
+        user.images = images;
+        user.prompts = prompts;
+
+        likes.push(LikeReceived {
+            user,
+            is_superlike: row.is_superlike,
+            context_type: row.context_type,
+            context_id: row.context_id,
+            liked_image,
+            liked_prompt,
+            comment: row.comment,
+            poll_answer: row.poll_answer,
+            created_at: row.created_at.to_rfc3339(),
+        });
+    }
+
+    HttpResponse::Ok().json(LikesReceivedResponse { likes })
+}
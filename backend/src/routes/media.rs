@@ -0,0 +1,261 @@
+//! Media storage
+//! Uploaded files are written through a MediaStore. The server picks the key and builds the public URL,
+//! so rows in user_images only ever point at files we stored ourselves.
+
+pub mod audio;
+pub mod local;
+pub mod moderation;
+pub mod mp4;
+pub mod pipeline;
+pub mod process;
+pub mod s3;
+pub mod sniff;
+pub mod transcode;
+pub mod video;
+
+use std::fmt;
+
+use actix_multipart::MultipartError;
+use actix_multipart::form::MultipartFormConfig;
+use actix_web::error::{InternalError, PayloadError};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, web};
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::images_queries;
+use crate::jwtauth::Claims;
+use crate::models::outputs::{ErrorCode, ErrorResponse, StatusResponse};
+use crate::models::state::AppState;
+use local::LocalMediaStore;
+use s3::{S3Config, S3MediaStore};
+
+#[derive(Debug)]
+pub enum MediaError {
+    /// Keys are relative paths of `[A-Za-z0-9._-]` segments, never `.` or `..`
+    InvalidKey,
+    Io(std::io::Error),
+    /// The remote store rejected the request or could not be reached
+    Remote(String),
+}
+
+impl fmt::Display for MediaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaError::InvalidKey => write!(f, "invalid media key"),
+            MediaError::Io(e) => write!(f, "media io error: {}", e),
+            MediaError::Remote(e) => write!(f, "media store error: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for MediaError {
+    fn from(e: std::io::Error) -> Self {
+        MediaError::Io(e)
+    }
+}
+
+/// Where uploaded media lives
+/// Plug in a different implementation to move files to another backend without touching the handlers
+#[async_trait]
+pub trait MediaStore: Send + Sync {
+    /// Short name used in logs (MEDIA_STORE)
+    fn name(&self) -> &str;
+
+    /// Base URL objects are served from, without a trailing slash
+    fn public_base(&self) -> &str;
+
+    /// Store `bytes` under `key`, replacing anything already there
+    async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> Result<(), MediaError>;
+
+    /// Read an object back, None if it doesn't exist
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, MediaError>;
+
+    /// Remove an object; removing a missing object is not an error
+    async fn delete(&self, key: &str) -> Result<(), MediaError>;
+
+    /// Public URL clients load the object from
+    fn url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_base(), key)
+    }
+
+    /// Key of an object served by this store, None if the URL points anywhere else
+    fn key_from_url(&self, url: &str) -> Option<String> {
+        let key = url.strip_prefix(self.public_base())?.strip_prefix('/')?;
+        is_valid_key(key).then(|| key.to_string())
+    }
+}
+
+/// Check a key is a plain relative path that can't escape the store's root
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        })
+}
+
+/// Prefix every key of the user's media starts with
+pub fn user_prefix(user_id: &Uuid) -> String {
+    format!("users/{}/", user_id)
+}
+
+/// New random key prefix for one of the user's profile images; its renditions are stored under it
+pub fn image_key(user_id: &Uuid) -> String {
+    format!("{}images/{}", user_prefix(user_id), Uuid::new_v4())
+}
+
+/// Key of one rendition of an image (see pipeline::RENDITIONS)
+pub fn rendition_key(image_key: &str, rendition: &str) -> String {
+    format!("{}/{}.jpg", image_key, rendition)
+}
+
+/// Key of the clip behind a video slot or video prompt; its poster frame's renditions sit next to it
+pub fn video_key(base_key: &str, extension: &str) -> String {
+    format!("{}/video.{}", base_key, extension)
+}
+
+/// New random key prefix for a video answering one of the user's prompts (laid out like an image: see video_key)
+pub fn prompt_video_key(user_id: &Uuid) -> String {
+    format!("{}prompts/{}", user_prefix(user_id), Uuid::new_v4())
+}
+
+/// New random key for a voice note answering one of the user's prompts
+pub fn prompt_audio_key(user_id: &Uuid, extension: &str) -> String {
+    format!("{}prompts/{}.{}", user_prefix(user_id), Uuid::new_v4(), extension)
+}
+
+/// Delete a stored file, only logging failures (used once nothing points at it any more)
+pub async fn delete_file(store: &dyn MediaStore, key: &str) {
+    if let Err(e) = store.delete(key).await {
+        println!("Failed to delete media {}: {}", key, e);
+    }
+}
+
+/// Delete every file stored for an image: its renditions, the clip if it's a video (slot or prompt),
+/// and the key itself for images uploaded before the pipeline (stored as a single file)
+/// Failures are only logged; a leftover file is unreachable once its row is gone
+pub async fn delete_image_files(store: &dyn MediaStore, storage_key: &str) {
+    let keys = std::iter::once(storage_key.to_string())
+        .chain(pipeline::RENDITIONS.iter().map(|(name, _)| rendition_key(storage_key, name)))
+        .chain(video::VideoFormat::ALL.iter().map(|format| video_key(storage_key, format.extension())));
+
+    for key in keys {
+        delete_file(store, &key).await;
+    }
+}
+
+/// Content type to serve a key with, based on its extension
+pub fn content_type_for(key: &str) -> &'static str {
+    match key.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("jpg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        Some("heic") => "image/heic",
+        Some("m4a") => "audio/mp4",
+        Some("ogg") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("mp4") => "video/mp4",
+        Some("mov") => "video/quicktime",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Multipart settings for an upload route: caps the whole body at `max_bytes`
+/// and answers oversized uploads with FILE_TOO_LARGE instead of a bare 400
+pub fn upload_config(max_bytes: usize) -> MultipartFormConfig {
+    MultipartFormConfig::default()
+        .total_limit(max_bytes)
+        .error_handler(move |err, _req| {
+            let response = match err {
+                MultipartError::Payload(PayloadError::Overflow) => HttpResponse::PayloadTooLarge().json(ErrorResponse::new(
+                    ErrorCode::FileTooLarge,
+                    &format!("Files can be at most {} MB", max_bytes / (1024 * 1024)),
+                )),
+                _ => HttpResponse::BadRequest().json(StatusResponse {
+                    status: "error".to_string(),
+                    message: Some(err.to_string()),
+                }),
+            };
+            InternalError::from_response(err, response).into()
+        })
+}
+
+/// Build the store from MEDIA_STORE (`local`, the default, or `s3`)
+/// Local: MEDIA_DIR (default ./uploads) and MEDIA_PUBLIC_URL (default http://localhost:8080/media)
+/// S3: S3_ENDPOINT, S3_BUCKET, S3_REGION (default us-east-1), S3_ACCESS_KEY, S3_SECRET_KEY and optionally S3_PUBLIC_URL
+pub fn store_from_env() -> Box<dyn MediaStore> {
+    let var = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+
+    match var("MEDIA_STORE").as_deref() {
+        Some("s3") => {
+            let endpoint = var("S3_ENDPOINT").expect("S3_ENDPOINT MUST BE SET");
+            let bucket = var("S3_BUCKET").expect("S3_BUCKET MUST BE SET");
+            let public_base = var("S3_PUBLIC_URL")
+                .unwrap_or_else(|| format!("{}/{}", endpoint.trim_end_matches('/'), bucket));
+
+            Box::new(S3MediaStore::new(S3Config {
+                endpoint,
+                bucket,
+                region: var("S3_REGION").unwrap_or_else(|| "us-east-1".to_string()),
+                access_key: var("S3_ACCESS_KEY").expect("S3_ACCESS_KEY MUST BE SET"),
+                secret_key: var("S3_SECRET_KEY").expect("S3_SECRET_KEY MUST BE SET"),
+                public_base,
+            }))
+        }
+        Some("local") | None => Box::new(LocalMediaStore::new(
+            var("MEDIA_DIR").unwrap_or_else(|| "./uploads".to_string()),
+            var("MEDIA_PUBLIC_URL").unwrap_or_else(|| "http://localhost:8080/media".to_string()),
+        )),
+        Some(other) => panic!("Unknown MEDIA_STORE: {}", other),
+    }
+}
+
+/// GET /media/{key} - Serve a stored object (the local store's public URL; point S3_PUBLIC_URL here to get the same checks)
+/// Owners can load all their files; anyone else only what media_visible_to allows, so pending or rejected
+/// renditions and the media of banned or blocked users stay private
+pub async fn serve(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().finish();
+    };
+    let Ok(viewer_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::Unauthorized().finish();
+    };
+
+    let key = path.into_inner();
+    if !is_valid_key(&key) {
+        return HttpResponse::NotFound().finish();
+    }
+
+    if !key.starts_with(&user_prefix(&viewer_id)) {
+        let parent = key.rsplit_once('/').map_or("", |(parent, _)| parent);
+        match images_queries::media_visible_to(&pool, &viewer_id, &key, parent).await {
+            Ok(true) => {}
+            Ok(false) => return HttpResponse::NotFound().finish(),
+            Err(e) => {
+                println!("Failed to check access to media {}: {:?}", key, e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
+    }
+
+    match state.media.get(&key).await {
+        // Files never change under a key, but who may load them does
+        Ok(Some(bytes)) => HttpResponse::Ok()
+            .content_type(content_type_for(&key))
+            .insert_header(("Cache-Control", "private, max-age=3600"))
+            .body(bytes),
+        Ok(None) | Err(MediaError::InvalidKey) => HttpResponse::NotFound().finish(),
+        Err(e) => {
+            println!("Failed to read media {}: {}", key, e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
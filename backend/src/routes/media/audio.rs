@@ -0,0 +1,391 @@
+//! Voice note checks
+//! Works out what an uploaded recording is and how long it lasts from its own bytes, like sniff does for images.
+//! Accepted: M4A (AAC, what iOS and Android record), Ogg (Opus or Vorbis, what browsers record) and WAV.
+
+use super::mp4;
+use super::sniff::{read_u16_le, read_u32_le, read_u64_le};
+
+/// Largest voice note accepted
+pub const MAX_AUDIO_BYTES: usize = 10 * 1024 * 1024;
+
+/// Shortest voice note accepted, in milliseconds
+pub const MIN_AUDIO_MS: u32 = 1_000;
+
+/// Longest voice note accepted, in milliseconds
+pub const MAX_AUDIO_MS: u32 = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    M4a,
+    Ogg,
+    Wav,
+}
+
+impl AudioFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            AudioFormat::M4a => "audio/mp4",
+            AudioFormat::Ogg => "audio/ogg",
+            AudioFormat::Wav => "audio/wav",
+        }
+    }
+
+    /// Extension of the stored file (media::content_type_for maps it back)
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Wav => "wav",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioError {
+    /// Not an M4A, Ogg or WAV recording
+    Unsupported,
+    /// The duration can't be read
+    Unreadable,
+    TooShort,
+    TooLong,
+}
+
+/// Sniff an uploaded recording and check its length against the limits
+/// Returns the format and the duration in milliseconds
+pub fn check_audio(bytes: &[u8]) -> Result<(AudioFormat, u32), AudioError> {
+    let format = sniff_audio(bytes).ok_or(AudioError::Unsupported)?;
+    let duration_ms = audio_duration_ms(format, bytes).ok_or(AudioError::Unreadable)?;
+
+    if duration_ms < MIN_AUDIO_MS {
+        return Err(AudioError::TooShort);
+    }
+    if duration_ms > MAX_AUDIO_MS {
+        return Err(AudioError::TooLong);
+    }
+
+    Ok((format, duration_ms))
+}
+
+/// Identify the audio format from the file's structure
+pub fn sniff_audio(bytes: &[u8]) -> Option<AudioFormat> {
+    if bytes.starts_with(b"OggS") {
+        return ogg_codec(bytes).map(|_| AudioFormat::Ogg);
+    }
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WAVE" {
+        return Some(AudioFormat::Wav);
+    }
+
+    // An MP4 with an AAC track and no video
+    let movie = mp4::parse(bytes)?;
+    let is_aac = movie.tracks_of(b"soun").any(|t| t.codec == Some(*b"mp4a"));
+    (is_aac && movie.tracks_of(b"vide").next().is_none()).then_some(AudioFormat::M4a)
+}
+
+/// Remove metadata the recorder may have written (location, device)
+/// M4A `udta`/`meta` boxes and WAV `LIST` chunks are blanked in place; Ogg comment headers are left alone,
+/// since rewriting them means recomputing page checksums and recorders only put the encoder name there
+pub fn strip_metadata(format: AudioFormat, bytes: &mut [u8]) {
+    match format {
+        AudioFormat::M4a => mp4::strip_metadata(bytes),
+        AudioFormat::Wav => {
+            for (offset, start, end) in wav_chunks(bytes) {
+                if &bytes[offset..offset + 4] == b"LIST" {
+                    bytes[offset..offset + 4].copy_from_slice(b"JUNK");
+                    bytes[start..end].fill(0);
+                }
+            }
+        }
+        AudioFormat::Ogg => {}
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OggCodec {
+    /// Pre-skip in 48 kHz samples
+    Opus { pre_skip: u16 },
+    Vorbis { sample_rate: u32 },
+}
+
+/// Codec of the first logical stream, from its identification header
+fn ogg_codec(bytes: &[u8]) -> Option<OggCodec> {
+    // The first page has a 27 byte header and a segment table, then the first packet
+    let segments = *bytes.get(26)? as usize;
+    let packet = 27 + segments;
+
+    if bytes.get(packet..packet + 8)? == b"OpusHead" {
+        return Some(OggCodec::Opus { pre_skip: read_u16_le(bytes, packet + 10)? });
+    }
+    if bytes.get(packet..packet + 7)? == b"\x01vorbis" {
+        return Some(OggCodec::Vorbis { sample_rate: read_u32_le(bytes, packet + 12)? });
+    }
+
+    None
+}
+
+/// Duration in milliseconds, read from the container without decoding
+pub fn audio_duration_ms(format: AudioFormat, bytes: &[u8]) -> Option<u32> {
+    let ms = match format {
+        AudioFormat::M4a => return mp4::parse(bytes)?.duration_ms,
+        AudioFormat::Ogg => {
+            // The last page's granule position is the sample count at the end of the stream
+            let granule = ogg_last_granule(bytes)?;
+
+            match ogg_codec(bytes)? {
+                // Opus granules always count 48 kHz samples
+                OggCodec::Opus { pre_skip } => granule.checked_sub(pre_skip as u64)?.checked_mul(1000)? / 48_000,
+                OggCodec::Vorbis { sample_rate } if sample_rate > 0 => granule.checked_mul(1000)? / sample_rate as u64,
+                OggCodec::Vorbis { .. } => return None,
+            }
+        }
+        AudioFormat::Wav => {
+            let chunks = wav_chunks(bytes);
+            let chunk = |id: &[u8]| chunks.iter().find(|(offset, _, _)| &bytes[*offset..*offset + 4] == id);
+
+            // Byte rate sits after the format tag, channel count and sample rate
+            let (_, fmt, _) = chunk(b"fmt ")?;
+            let byte_rate = read_u32_le(bytes, fmt + 8)?;
+            let (_, start, end) = chunk(b"data")?;
+
+            if byte_rate == 0 {
+                return None;
+            }
+            (end - start) as u64 * 1000 / byte_rate as u64
+        }
+    };
+
+    u32::try_from(ms).ok()
+}
+
+/// Granule position of the first logical stream's last complete page
+/// Walks the page headers from the start (a page body can contain "OggS" too) and stops at the stream's end,
+/// or at the first page that is malformed or cut off. Pages that finish no packet have a granule of -1 and are skipped.
+fn ogg_last_granule(bytes: &[u8]) -> Option<u64> {
+    // End-of-stream flag in the header type
+    const END_OF_STREAM: u8 = 0x04;
+
+    let serial = read_u32_le(bytes, 14)?;
+    let mut granule = None;
+    let mut at = 0;
+
+    // Capture pattern, version 0, header type, granule, serial, sequence number, checksum, segment count
+    while bytes.get(at..at + 4) == Some(b"OggS") && bytes.get(at + 4) == Some(&0) {
+        let Some(&segments) = bytes.get(at + 26) else {
+            break;
+        };
+        let Some(table) = bytes.get(at + 27..at + 27 + segments as usize) else {
+            break;
+        };
+        let next = at + 27 + table.len() + table.iter().map(|&len| len as usize).sum::<usize>();
+        if next > bytes.len() {
+            break;
+        }
+
+        if read_u32_le(bytes, at + 14) == Some(serial) {
+            match read_u64_le(bytes, at + 6) {
+                Some(u64::MAX) | None => {}
+                Some(position) => granule = Some(position),
+            }
+            if bytes[at + 5] & END_OF_STREAM != 0 {
+                break;
+            }
+        }
+
+        at = next;
+    }
+
+    granule
+}
+
+/// The RIFF chunks of a WAV file as (header offset, body start, body end)
+/// A data chunk with a placeholder size (streamed recordings) is cut off at the end of the file
+fn wav_chunks(bytes: &[u8]) -> Vec<(usize, usize, usize)> {
+    let mut chunks = Vec::new();
+    let mut at = 12;
+
+    while let Some(size) = read_u32_le(bytes, at + 4) {
+        let start = at + 8;
+        let end = start.saturating_add(size as usize).min(bytes.len());
+        chunks.push((at, start, end));
+
+        // Chunks are padded to an even length
+        at = end + (end - start) % 2;
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mp4::fixtures::movie;
+    use super::*;
+
+    const SERIAL: u32 = 0x1234;
+
+    /// One Ogg page holding `body` as a single packet (or the end of one)
+    fn ogg_page(header_type: u8, granule: u64, serial: u32, body: &[u8]) -> Vec<u8> {
+        let mut lacing = vec![255; body.len() / 255];
+        lacing.push((body.len() % 255) as u8);
+
+        let mut page = b"OggS\0".to_vec();
+        page.push(header_type);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&[0; 8]);
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(&lacing);
+        page.extend_from_slice(body);
+        page
+    }
+
+    fn opus_head(pre_skip: u16) -> Vec<u8> {
+        let mut head = b"OpusHead\x01\x01".to_vec();
+        head.extend_from_slice(&pre_skip.to_le_bytes());
+        head.extend_from_slice(&48_000u32.to_le_bytes());
+        head.extend_from_slice(&[0, 0, 0]);
+        head
+    }
+
+    /// An Opus recording whose audio pages end at the given granules
+    fn opus(granules: &[u64]) -> Vec<u8> {
+        let mut data = ogg_page(0x02, 0, SERIAL, &opus_head(312));
+        data.extend(ogg_page(0, 0, SERIAL, b"OpusTags"));
+        for (i, granule) in granules.iter().enumerate() {
+            let header_type = if i + 1 == granules.len() { 0x04 } else { 0 };
+            data.extend(ogg_page(header_type, *granule, SERIAL, &[0xAB; 300]));
+        }
+        data
+    }
+
+    fn wav(byte_rate: u32, data_len: Option<u32>) -> Vec<u8> {
+        let mut data = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0".to_vec();
+        data.extend_from_slice(&(byte_rate / 2).to_le_bytes());
+        data.extend_from_slice(&byte_rate.to_le_bytes());
+        data.extend_from_slice(&[2, 0, 16, 0]);
+        if let Some(len) = data_len {
+            data.extend_from_slice(b"data");
+            data.extend_from_slice(&len.to_le_bytes());
+            data.extend(std::iter::repeat_n(0, len as usize));
+        }
+        data
+    }
+
+    #[test]
+    fn reads_opus_duration() {
+        assert_eq!(check_audio(&opus(&[48_312, 96_312])), Ok((AudioFormat::Ogg, 2_000)));
+    }
+
+    #[test]
+    fn reads_vorbis_duration() {
+        let mut head = b"\x01vorbis\0\0\0\0\x01".to_vec();
+        head.extend_from_slice(&44_100u32.to_le_bytes());
+        head.extend_from_slice(&[0; 14]);
+        let mut data = ogg_page(0x02, 0, SERIAL, &head);
+        data.extend(ogg_page(0x04, 3 * 44_100, SERIAL, &[0; 100]));
+
+        assert_eq!(check_audio(&data), Ok((AudioFormat::Ogg, 3_000)));
+    }
+
+    #[test]
+    fn skips_pages_with_a_granule_of_minus_one() {
+        // A page that finishes no packet, e.g. the middle of a long one
+        assert_eq!(check_audio(&opus(&[96_312, u64::MAX])), Ok((AudioFormat::Ogg, 2_000)));
+        // Nothing but the headers has a position
+        assert_eq!(check_audio(&opus(&[u64::MAX])), Err(AudioError::Unreadable));
+    }
+
+    #[test]
+    fn ignores_capture_patterns_inside_packets() {
+        let mut fake = b"OggS\0\x04".to_vec();
+        fake.extend_from_slice(&u64::MAX.to_le_bytes());
+        fake.extend_from_slice(&[0; 100]);
+        let mut data = ogg_page(0x02, 0, SERIAL, &opus_head(312));
+        data.extend(ogg_page(0x04, 144_312, SERIAL, &fake));
+
+        assert_eq!(check_audio(&data), Ok((AudioFormat::Ogg, 3_000)));
+    }
+
+    #[test]
+    fn ignores_other_streams_and_pages_after_the_end() {
+        let mut data = opus(&[96_312]);
+        data.extend(ogg_page(0, 1_440_000, SERIAL + 1, &[0; 10]));
+        data.extend(ogg_page(0, 1_440_000, SERIAL, &[0; 10]));
+
+        assert_eq!(check_audio(&data), Ok((AudioFormat::Ogg, 2_000)));
+    }
+
+    #[test]
+    fn ignores_a_cut_off_last_page() {
+        let mut data = opus(&[96_312, 1_440_312]);
+        data.truncate(data.len() - 1);
+
+        assert_eq!(check_audio(&data), Ok((AudioFormat::Ogg, 2_000)));
+    }
+
+    #[test]
+    fn rejects_granules_that_overflow() {
+        assert_eq!(check_audio(&opus(&[u64::MAX - 1])), Err(AudioError::Unreadable));
+        assert_eq!(check_audio(&opus(&[100])), Err(AudioError::Unreadable));
+    }
+
+    #[test]
+    fn rejects_ogg_without_a_known_codec() {
+        let data = ogg_page(0x02, 0, SERIAL, b"\x80theora");
+
+        assert_eq!(check_audio(&data), Err(AudioError::Unsupported));
+        assert_eq!(check_audio(b"OggS"), Err(AudioError::Unsupported));
+    }
+
+    #[test]
+    fn reads_wav_duration() {
+        assert_eq!(check_audio(&wav(16_000, Some(40_000))), Ok((AudioFormat::Wav, 2_500)));
+    }
+
+    #[test]
+    fn cuts_a_placeholder_data_size_at_the_end_of_the_file() {
+        let mut data = wav(16_000, Some(0));
+        let len = data.len();
+        data[len - 4..].copy_from_slice(&u32::MAX.to_le_bytes());
+        data.extend(std::iter::repeat_n(0, 32_000));
+
+        assert_eq!(check_audio(&data), Ok((AudioFormat::Wav, 2_000)));
+    }
+
+    #[test]
+    fn rejects_wav_without_a_data_chunk() {
+        assert_eq!(check_audio(&wav(16_000, None)), Err(AudioError::Unreadable));
+    }
+
+    #[test]
+    fn rejects_wav_with_a_zero_byte_rate() {
+        assert_eq!(check_audio(&wav(0, Some(1_000))), Err(AudioError::Unreadable));
+    }
+
+    #[test]
+    fn checks_the_length() {
+        assert_eq!(check_audio(&wav(16_000, Some(15_984))), Err(AudioError::TooShort));
+        assert_eq!(check_audio(&opus(&[48_000 * 31 + 312])), Err(AudioError::TooLong));
+    }
+
+    #[test]
+    fn reads_m4a_but_not_video() {
+        let m4a = movie(b"M4A ", 4_000, &[(b"soun", b"mp4a")]);
+        assert_eq!(check_audio(&m4a), Ok((AudioFormat::M4a, 4_000)));
+
+        let mp4 = movie(b"isom", 4_000, &[(b"vide", b"avc1"), (b"soun", b"mp4a")]);
+        assert_eq!(check_audio(&mp4), Err(AudioError::Unsupported));
+        assert_eq!(check_audio(b"ID3\x04"), Err(AudioError::Unsupported));
+    }
+
+    #[test]
+    fn blanks_wav_info_chunks() {
+        let mut data = wav(16_000, Some(32_000));
+        data.extend_from_slice(b"LIST\x08\0\0\0INFOISFT");
+        let len = data.len();
+
+        strip_metadata(AudioFormat::Wav, &mut data);
+
+        assert_eq!(data.len(), len);
+        assert_eq!(&data[len - 16..len - 8], b"JUNK\x08\0\0\0");
+        assert!(data[len - 8..].iter().all(|&b| b == 0));
+    }
+}
@@ -0,0 +1,143 @@
+//! Media store backed by a directory on the server's disk
+
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use actix_web::web;
+use async_trait::async_trait;
+
+use super::{MediaError, MediaStore, is_valid_key};
+
+/// Keeps objects as files under `root`, served back through GET /media/{key}
+pub struct LocalMediaStore {
+    root: PathBuf,
+    public_base: String,
+}
+
+impl LocalMediaStore {
+    pub fn new(root: impl Into<PathBuf>, public_base: impl Into<String>) -> Self {
+        Self {
+            root: root.into(),
+            public_base: public_base.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf, MediaError> {
+        if !is_valid_key(key) {
+            return Err(MediaError::InvalidKey);
+        }
+
+        Ok(self.root.join(key))
+    }
+}
+
+/// Run blocking file io off the async workers
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> std::io::Result<T> + Send + 'static,
+) -> Result<T, MediaError> {
+    web::block(f)
+        .await
+        .map_err(|e| MediaError::Io(std::io::Error::other(e.to_string())))?
+        .map_err(MediaError::Io)
+}
+
+#[async_trait]
+impl MediaStore for LocalMediaStore {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn public_base(&self) -> &str {
+        &self.public_base
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>, _content_type: &str) -> Result<(), MediaError> {
+        let path = self.path_for(key)?;
+
+        blocking(move || {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, bytes)
+        })
+        .await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, MediaError> {
+        let path = self.path_for(key)?;
+
+        blocking(move || match std::fs::read(&path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == ErrorKind::NotFound || path.is_dir() => Ok(None),
+            Err(e) => Err(e),
+        })
+        .await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), MediaError> {
+        let path = self.path_for(key)?;
+        let root = self.root.clone();
+
+        blocking(move || {
+            // A key naming a directory (an image's rendition prefix) holds no object of its own
+            if path.is_dir() {
+                return Ok(());
+            }
+
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+
+            // Prune directories left empty (remove_dir fails on the first one that isn't)
+            let mut dir = path.parent();
+            while let Some(d) = dir.filter(|d| *d != root) {
+                if std::fs::remove_dir(d).is_err() {
+                    break;
+                }
+                dir = d.parent();
+            }
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> (LocalMediaStore, PathBuf) {
+        let root = std::env::temp_dir().join(format!("aligned-media-{}", uuid::Uuid::new_v4()));
+        (LocalMediaStore::new(&root, "http://localhost/media"), root)
+    }
+
+    #[actix_web::test]
+    async fn treats_a_rendition_prefix_as_absent() {
+        let (store, root) = store();
+        store.put("users/u/images/i/card.jpg", vec![1], "image/jpeg").await.unwrap();
+
+        assert!(store.delete("users/u/images/i").await.is_ok());
+        assert_eq!(store.get("users/u/images/i").await.unwrap(), None);
+        assert_eq!(store.get("users/u/images/i/card.jpg").await.unwrap(), Some(vec![1]));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[actix_web::test]
+    async fn prunes_directories_left_empty() {
+        let (store, root) = store();
+        store.put("users/u/images/i/card.jpg", vec![1], "image/jpeg").await.unwrap();
+        store.put("users/u/images/i/thumbnail.jpg", vec![2], "image/jpeg").await.unwrap();
+
+        store.delete("users/u/images/i/card.jpg").await.unwrap();
+        assert!(root.join("users/u/images/i").is_dir());
+
+        store.delete("users/u/images/i/thumbnail.jpg").await.unwrap();
+        store.delete("users/u/images/i/thumbnail.jpg").await.unwrap();
+        assert!(!root.join("users").exists());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,254 @@
+//! Image moderation
+//! Every stored profile image starts PENDING. An ImageClassifier looks at it right after upload and
+//! approves or rejects it; only APPROVED images are shown to other users (feed, likes, IMAGE likes).
+//! Rejections are listed in the owner's pending actions from /profile/finalize.
+//! Images the classifier couldn't judge stay PENDING and are retried by the feed pool job (review_pending).
+//! Video prompts go through the same classifier by their poster frame; other users only get the clip once it's APPROVED.
+
+use async_trait::async_trait;
+use image::imageops::FilterType;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{MediaStore, rendition_key};
+use crate::db::{images_queries, prompt_queries};
+use crate::routes::profile::completeness;
+
+/// Longest side over shortest side allowed by LocalRulesClassifier (banners, long screenshots)
+const MAX_ASPECT_RATIO: f64 = 3.0;
+
+/// Most PENDING images (and as many prompt videos) review_pending retries per run
+const RETRY_BATCH: i64 = 100;
+
+/// Brightness spread (standard deviation, 0-255) below which LocalRulesClassifier treats an image as blank
+const MIN_BRIGHTNESS_SPREAD: f64 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl ModerationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "PENDING",
+            Self::Approved => "APPROVED",
+            Self::Rejected => "REJECTED",
+        }
+    }
+}
+
+/// A classifier's decision
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Approve,
+    /// The reason is shown to the owner
+    Reject(String),
+}
+
+/// Decides whether an uploaded image may be shown to other users
+/// Plug in a different implementation to call a hosted moderation service
+#[async_trait]
+pub trait ImageClassifier: Send + Sync {
+    /// Short name used in logs (IMAGE_CLASSIFIER)
+    fn name(&self) -> &str;
+
+    /// Classify the card rendition of an image (JPEG, fits 720x960)
+    /// An error leaves the image PENDING
+    async fn classify(&self, jpeg: Vec<u8>) -> Result<Verdict, String>;
+}
+
+/// Approves everything
+#[derive(Debug, Clone, Default)]
+pub struct NoopClassifier;
+
+#[async_trait]
+impl ImageClassifier for NoopClassifier {
+    fn name(&self) -> &str {
+        "none"
+    }
+
+    async fn classify(&self, _jpeg: Vec<u8>) -> Result<Verdict, String> {
+        Ok(Verdict::Approve)
+    }
+}
+
+/// Cheap checks that need no outside service: rejects blank images and extreme aspect ratios
+#[derive(Debug, Clone, Default)]
+pub struct LocalRulesClassifier;
+
+impl LocalRulesClassifier {
+    fn check(jpeg: &[u8]) -> Result<Verdict, String> {
+        let image = image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg).map_err(|e| e.to_string())?;
+
+        let (long, short) = (image.width().max(image.height()), image.width().min(image.height()));
+        if long as f64 > short as f64 * MAX_ASPECT_RATIO {
+            return Ok(Verdict::Reject("The photo is too narrow; crop it closer to a portrait or square".to_string()));
+        }
+
+        let luma = image.resize_exact(64, 64, FilterType::Triangle).to_luma8();
+        let count = luma.len() as f64;
+        let mean = luma.iter().map(|&v| v as f64).sum::<f64>() / count;
+        let variance = luma.iter().map(|&v| (v as f64 - mean).powi(2)).sum::<f64>() / count;
+
+        if variance.sqrt() < MIN_BRIGHTNESS_SPREAD {
+            return Ok(Verdict::Reject("The photo looks blank".to_string()));
+        }
+
+        Ok(Verdict::Approve)
+    }
+}
+
+#[async_trait]
+impl ImageClassifier for LocalRulesClassifier {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    async fn classify(&self, jpeg: Vec<u8>) -> Result<Verdict, String> {
+        actix_web::web::block(move || Self::check(&jpeg))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+}
+
+/// Build the classifier from IMAGE_CLASSIFIER (`local`, the default, or `none`)
+pub fn classifier_from_env() -> Box<dyn ImageClassifier> {
+    match std::env::var("IMAGE_CLASSIFIER").ok().filter(|v| !v.is_empty()).as_deref() {
+        Some("local") | None => Box::new(LocalRulesClassifier),
+        Some("none") => Box::new(NoopClassifier),
+        Some(other) => panic!("Unknown IMAGE_CLASSIFIER: {}", other),
+    }
+}
+
+/// Run the card rendition stored under `storage_key` through the classifier
+/// None if it can't be read or the classifier fails; `what` names the upload in logs
+async fn classify_card(
+    store: &dyn MediaStore,
+    classifier: &dyn ImageClassifier,
+    storage_key: &str,
+    what: &str,
+) -> Option<(ModerationStatus, Option<String>)> {
+    let jpeg = match store.get(&rendition_key(storage_key, "card")).await {
+        Ok(Some(bytes)) => bytes,
+        Ok(None) => {
+            println!("{} has no card rendition to classify", what);
+            return None;
+        }
+        Err(e) => {
+            println!("Failed to read {} for moderation: {}", what, e);
+            return None;
+        }
+    };
+
+    match classifier.classify(jpeg).await {
+        Ok(Verdict::Approve) => Some((ModerationStatus::Approved, None)),
+        Ok(Verdict::Reject(reason)) => Some((ModerationStatus::Rejected, Some(reason))),
+        Err(e) => {
+            println!("Image classifier {} failed on {}: {}", classifier.name(), what, e);
+            None
+        }
+    }
+}
+
+/// Classify a freshly stored image and save the verdict
+/// Returns the image's status and rejection reason; it stays PENDING if the classifier fails
+/// or the image was replaced in the meantime
+pub async fn review(
+    pool: &PgPool,
+    store: &dyn MediaStore,
+    classifier: &dyn ImageClassifier,
+    user_id: &Uuid,
+    image_id: &Uuid,
+    storage_key: &str,
+) -> (ModerationStatus, Option<String>) {
+    let pending = (ModerationStatus::Pending, None);
+
+    let what = format!("Image {}", image_id);
+    let Some((status, reason)) = classify_card(store, classifier, storage_key, &what).await else {
+        return pending;
+    };
+
+    match images_queries::set_moderation(pool, image_id, storage_key, status.as_str(), reason.as_deref()).await {
+        Ok(true) => {}
+        Ok(false) => return pending,
+        Err(e) => {
+            println!("Failed to save moderation for {}: {:?}", image_id, e);
+            return pending;
+        }
+    }
+
+    // Rejected images don't count towards a complete profile
+    if status == ModerationStatus::Rejected {
+        recheck_completeness(pool, user_id).await;
+    }
+
+    (status, reason)
+}
+
+/// Classify a freshly stored prompt video by its poster frame and save the verdict
+/// Same outcomes as review; `video_key` is the prompt's video key (see NewPromptVideo)
+pub async fn review_prompt_video(
+    pool: &PgPool,
+    store: &dyn MediaStore,
+    classifier: &dyn ImageClassifier,
+    user_id: &Uuid,
+    video_key: &str,
+) -> (ModerationStatus, Option<String>) {
+    let pending = (ModerationStatus::Pending, None);
+
+    let what = format!("Prompt video {}", video_key);
+    let Some((status, reason)) = classify_card(store, classifier, video_key, &what).await else {
+        return pending;
+    };
+
+    match prompt_queries::set_video_moderation(pool, video_key, status.as_str(), reason.as_deref()).await {
+        Ok(true) => {}
+        Ok(false) => return pending,
+        Err(e) => {
+            println!("Failed to save moderation for {}: {:?}", what, e);
+            return pending;
+        }
+    }
+
+    // A rejected video is listed in the owner's pending actions
+    if status == ModerationStatus::Rejected {
+        recheck_completeness(pool, user_id).await;
+    }
+
+    (status, reason)
+}
+
+async fn recheck_completeness(pool: &PgPool, user_id: &Uuid) {
+    if let Err(e) = completeness::recheck(pool, user_id).await {
+        println!("Failed to recheck profile completeness: {:?}", e);
+    }
+}
+
+/// Classify images and prompt videos left PENDING, e.g. because the classifier or the media store was down at upload time
+/// Returns how many got a verdict
+pub async fn review_pending(
+    pool: &PgPool,
+    store: &dyn MediaStore,
+    classifier: &dyn ImageClassifier,
+) -> Result<usize, sqlx::Error> {
+    let mut reviewed = 0;
+
+    for (user_id, image_id, storage_key) in images_queries::get_unmoderated_images(pool, RETRY_BATCH).await? {
+        let (status, _) = review(pool, store, classifier, &user_id, &image_id, &storage_key).await;
+        if status != ModerationStatus::Pending {
+            reviewed += 1;
+        }
+    }
+
+    for (user_id, video_key) in prompt_queries::get_unmoderated_videos(pool, RETRY_BATCH).await? {
+        let (status, _) = review_prompt_video(pool, store, classifier, &user_id, &video_key).await;
+        if status != ModerationStatus::Pending {
+            reviewed += 1;
+        }
+    }
+
+    Ok(reviewed)
+}
@@ -0,0 +1,302 @@
+//! ISO base media files (MP4, M4A, MOV)
+//! Just enough box parsing to list tracks, read the duration and blank out metadata, without decoding anything.
+
+use super::sniff::{read_u32_be, read_u64_be};
+
+/// A box within the file
+#[derive(Debug, Clone, Copy)]
+struct Mp4Box {
+    kind: [u8; 4],
+    /// Offset of the box header
+    offset: usize,
+    /// Byte range of the body
+    start: usize,
+    end: usize,
+}
+
+/// The boxes directly inside `data[start..end]`
+/// Stops at the first box whose size doesn't fit, so truncated files yield what could be read
+fn children(data: &[u8], start: usize, end: usize) -> Vec<Mp4Box> {
+    let mut boxes = Vec::new();
+    let mut at = start;
+
+    while at + 8 <= end {
+        let Some(size) = read_u32_be(data, at) else {
+            break;
+        };
+        let kind: [u8; 4] = data[at + 4..at + 8].try_into().unwrap_or_default();
+
+        // 1: 64-bit size follows the type, 0: the box runs to the end of its parent
+        let (header, size) = match size {
+            1 => match read_u64_be(data, at + 8) {
+                Some(large) => (16, usize::try_from(large).unwrap_or(usize::MAX)),
+                None => break,
+            },
+            0 => (8, end - at),
+            n => (8, n as usize),
+        };
+
+        if size < header || size > end - at {
+            break;
+        }
+
+        boxes.push(Mp4Box { kind, offset: at, start: at + header, end: at + size });
+        at += size;
+    }
+
+    boxes
+}
+
+fn child(data: &[u8], parent: &Mp4Box, kind: &[u8; 4]) -> Option<Mp4Box> {
+    children(data, parent.start, parent.end).into_iter().find(|b| &b.kind == kind)
+}
+
+/// Follow a path of box types down from `parent`
+fn descend(data: &[u8], parent: &Mp4Box, path: &[&[u8; 4]]) -> Option<Mp4Box> {
+    path.iter().try_fold(*parent, |current, kind| child(data, &current, kind))
+}
+
+/// One track of a movie
+#[derive(Debug, Clone)]
+pub struct Track {
+    /// `soun` for audio, `vide` for video
+    pub handler: [u8; 4],
+    /// Sample entry format of the first description, e.g. `mp4a`, `avc1`, `hvc1`
+    pub codec: Option<[u8; 4]>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Movie {
+    /// Major and compatible brands from `ftyp`
+    pub brands: Vec<[u8; 4]>,
+    pub duration_ms: Option<u32>,
+    pub tracks: Vec<Track>,
+}
+
+impl Movie {
+    pub fn tracks_of(&self, handler: &[u8; 4]) -> impl Iterator<Item = &Track> {
+        self.tracks.iter().filter(move |t| &t.handler == handler)
+    }
+}
+
+/// Parse the file's `ftyp` and `moov` boxes, None if it isn't an ISO media file with a movie header
+pub fn parse(data: &[u8]) -> Option<Movie> {
+    let top = children(data, 0, data.len());
+
+    let ftyp = top.first().filter(|b| &b.kind == b"ftyp")?;
+    // Major brand, minor version, then compatible brands
+    let brands = std::iter::once(ftyp.start)
+        .chain((ftyp.start + 8..ftyp.end).step_by(4))
+        .filter_map(|at| data.get(at..at + 4)?.try_into().ok())
+        .collect();
+
+    let moov = top.iter().find(|b| &b.kind == b"moov")?;
+
+    let tracks = children(data, moov.start, moov.end)
+        .into_iter()
+        .filter(|b| &b.kind == b"trak")
+        .filter_map(|trak| {
+            let mdia = child(data, &trak, b"mdia")?;
+            // Version/flags and pre_defined come before the handler type
+            let hdlr = child(data, &mdia, b"hdlr")?;
+            let handler = data.get(hdlr.start + 8..hdlr.start + 12)?.try_into().ok()?;
+
+            // Version/flags and entry count, then the first entry's size and format
+            let codec = descend(data, &mdia, &[b"minf", b"stbl", b"stsd"])
+                .and_then(|stsd| data.get(stsd.start + 12..stsd.start + 16)?.try_into().ok());
+
+            Some(Track { handler, codec })
+        })
+        .collect();
+
+    Some(Movie { brands, duration_ms: movie_duration_ms(data, moov), tracks })
+}
+
+/// Duration from the movie header (`mvhd`)
+fn movie_duration_ms(data: &[u8], moov: &Mp4Box) -> Option<u32> {
+    let mvhd = child(data, moov, b"mvhd")?;
+
+    // Version 1 uses 64-bit times and duration
+    let (timescale, duration) = match *data.get(mvhd.start)? {
+        0 => (read_u32_be(data, mvhd.start + 12)?, read_u32_be(data, mvhd.start + 16)? as u64),
+        1 => (read_u32_be(data, mvhd.start + 20)?, read_u64_be(data, mvhd.start + 24)?),
+        _ => return None,
+    };
+
+    if timescale == 0 {
+        return None;
+    }
+
+    Some(u32::try_from(duration.saturating_mul(1000) / timescale as u64).unwrap_or(u32::MAX))
+}
+
+/// Blank out user data and metadata (`udta`, `meta`) in the movie and its tracks.
+/// Phones write the recording location and device there. The boxes become `free` boxes of
+/// the same size, so sample offsets elsewhere in the file stay valid.
+pub fn strip_metadata(data: &mut [u8]) {
+    let Some(moov) = children(data, 0, data.len()).into_iter().find(|b| &b.kind == b"moov") else {
+        return;
+    };
+
+    let mut containers = vec![moov];
+    containers.extend(children(data, moov.start, moov.end).into_iter().filter(|b| &b.kind == b"trak"));
+
+    let blanked: Vec<Mp4Box> = containers
+        .iter()
+        .flat_map(|parent| children(data, parent.start, parent.end))
+        .filter(|b| &b.kind == b"udta" || &b.kind == b"meta")
+        .collect();
+
+    for b in blanked {
+        data[b.offset + 4..b.offset + 8].copy_from_slice(b"free");
+        data[b.start..b.end].fill(0);
+    }
+}
+
+/// Builders for small ISO media files, shared by the video and audio tests
+#[cfg(test)]
+pub(super) mod fixtures {
+    /// A box with a 32-bit size
+    pub fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out
+    }
+
+    pub fn ftyp(major_brand: &[u8; 4]) -> Vec<u8> {
+        mp4_box(b"ftyp", &[major_brand.as_slice(), &[0; 4], b"isom"].concat())
+    }
+
+    /// Version 0 movie header
+    pub fn mvhd(timescale: u32, duration: u32) -> Vec<u8> {
+        let mut body = vec![0; 12];
+        body.extend_from_slice(&timescale.to_be_bytes());
+        body.extend_from_slice(&duration.to_be_bytes());
+        mp4_box(b"mvhd", &body)
+    }
+
+    /// A track with one sample description
+    pub fn trak(handler: &[u8; 4], codec: &[u8; 4]) -> Vec<u8> {
+        let hdlr = mp4_box(b"hdlr", &[[0; 8].as_slice(), handler].concat());
+        let stsd = mp4_box(b"stsd", &[[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 16].as_slice(), codec, &[0; 4]].concat());
+        let minf = mp4_box(b"minf", &mp4_box(b"stbl", &stsd));
+        mp4_box(b"trak", &mp4_box(b"mdia", &[hdlr, minf].concat()))
+    }
+
+    /// A complete file: `ftyp`, then `moov` with a millisecond movie header and the given tracks
+    pub fn movie(major_brand: &[u8; 4], duration_ms: u32, tracks: &[(&[u8; 4], &[u8; 4])]) -> Vec<u8> {
+        let mut moov = mvhd(1000, duration_ms);
+        for (handler, codec) in tracks {
+            moov.extend(trak(handler, codec));
+        }
+        [ftyp(major_brand), mp4_box(b"moov", &moov)].concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::*;
+    use super::*;
+
+    fn kinds(data: &[u8]) -> Vec<[u8; 4]> {
+        children(data, 0, data.len()).iter().map(|b| b.kind).collect()
+    }
+
+    #[test]
+    fn lists_top_level_boxes() {
+        let data = [mp4_box(b"ftyp", b"isom"), mp4_box(b"free", &[]), mp4_box(b"mdat", &[1, 2, 3])].concat();
+
+        let boxes = children(&data, 0, data.len());
+
+        assert_eq!(kinds(&data), vec![*b"ftyp", *b"free", *b"mdat"]);
+        assert_eq!((boxes[2].offset, boxes[2].start, boxes[2].end), (20, 28, 31));
+    }
+
+    #[test]
+    fn reads_64_bit_sizes() {
+        let mut data = 1u32.to_be_bytes().to_vec();
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&20u64.to_be_bytes());
+        data.extend_from_slice(&[0; 4]);
+
+        let boxes = children(&data, 0, data.len());
+
+        assert_eq!(boxes.len(), 1);
+        assert_eq!((boxes[0].start, boxes[0].end), (16, 20));
+    }
+
+    #[test]
+    fn size_zero_runs_to_the_end_of_the_parent() {
+        let data = [mp4_box(b"ftyp", b"isom"), vec![0, 0, 0, 0], b"mdat".to_vec(), vec![0; 10]].concat();
+
+        let boxes = children(&data, 0, data.len());
+
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(boxes[1].end, data.len());
+    }
+
+    #[test]
+    fn stops_at_size_one_without_a_64_bit_size() {
+        let mut data = mp4_box(b"free", &[]);
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(b"ftyp");
+
+        assert_eq!(kinds(&data), vec![*b"free"]);
+    }
+
+    #[test]
+    fn stops_at_boxes_that_dont_fit() {
+        // Declared larger than the file
+        let mut truncated = mp4_box(b"moov", &[0; 32]);
+        truncated.truncate(20);
+        assert!(kinds(&truncated).is_empty());
+
+        // Smaller than its own header
+        let mut tiny = mp4_box(b"free", &[]);
+        tiny.extend_from_slice(&[0, 0, 0, 4]);
+        tiny.extend_from_slice(b"free");
+        assert_eq!(kinds(&tiny), vec![*b"free"]);
+
+        // A 64-bit size past the end of the file or the address space
+        let mut huge = 1u32.to_be_bytes().to_vec();
+        huge.extend_from_slice(b"mdat");
+        huge.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert!(kinds(&huge).is_empty());
+    }
+
+    #[test]
+    fn stays_inside_the_parent() {
+        let data = mp4_box(b"moov", &[mp4_box(b"mvhd", &[0; 4]), mp4_box(b"trak", &[])].concat());
+        let moov = children(&data, 0, data.len())[0];
+
+        let inner: Vec<[u8; 4]> = children(&data, moov.start, moov.end).iter().map(|b| b.kind).collect();
+
+        assert_eq!(inner, vec![*b"mvhd", *b"trak"]);
+    }
+
+    #[test]
+    fn parses_a_movie() {
+        let data = movie(b"isom", 4_500, &[(b"vide", b"avc1"), (b"soun", b"mp4a")]);
+
+        let parsed = parse(&data).unwrap();
+
+        assert_eq!(parsed.brands, vec![*b"isom", *b"isom"]);
+        assert_eq!(parsed.duration_ms, Some(4_500));
+        assert_eq!(parsed.tracks_of(b"vide").next().unwrap().codec, Some(*b"avc1"));
+        assert_eq!(parsed.tracks_of(b"soun").next().unwrap().codec, Some(*b"mp4a"));
+    }
+
+    #[test]
+    fn blanks_metadata_in_place() {
+        let moov = [mvhd(1000, 1000), mp4_box(b"udta", b"GPS+51.5-0.1")].concat();
+        let mut data = [ftyp(b"isom"), mp4_box(b"moov", &moov)].concat();
+        let len = data.len();
+
+        strip_metadata(&mut data);
+
+        assert_eq!(data.len(), len);
+        assert!(!data.windows(4).any(|w| w == b"udta" || w == b"GPS+"));
+        assert_eq!(parse(&data).unwrap().duration_ms, Some(1000));
+    }
+}
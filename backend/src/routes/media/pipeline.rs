@@ -0,0 +1,178 @@
+//! Profile image pipeline
+//! Every upload is decoded, turned upright using its EXIF orientation and re-encoded into fixed renditions.
+//! Re-encoding from pixels drops all metadata (GPS position, camera, timestamps); the original file is never stored.
+
+use std::io::Cursor;
+use std::process::Command;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageReader, Limits, RgbImage};
+use uuid::Uuid;
+
+use super::process;
+use super::sniff::{ImageFormat, MAX_IMAGE_PIXELS, MAX_IMAGE_SIDE};
+
+/// JPEG quality for every rendition
+const JPEG_QUALITY: u8 = 85;
+
+/// BlurHash detail (components across and down)
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+
+/// How a rendition is sized; images are never scaled up
+#[derive(Debug, Clone, Copy)]
+pub enum RenditionSize {
+    /// Center-cropped square with this side
+    Square(u32),
+    /// Scaled to fit within (width, height), keeping the aspect ratio
+    Fit(u32, u32),
+}
+
+/// Renditions generated for each profile image, stored as `{base key}/{name}.jpg`
+pub const RENDITIONS: [(&str, RenditionSize); 3] = [
+    ("thumbnail", RenditionSize::Square(200)),
+    ("card", RenditionSize::Fit(720, 960)),
+    ("full", RenditionSize::Fit(1600, 1600)),
+];
+
+pub struct Rendition {
+    pub name: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+pub struct ProcessedImage {
+    pub renditions: Vec<Rendition>,
+    pub blurhash: String,
+    /// Size of the upright original
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug)]
+pub enum PipelineError {
+    /// The file could not be decoded (corrupt, or HEIC without a converter)
+    Decode(String),
+    Encode(String),
+}
+
+impl std::fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineError::Decode(e) => write!(f, "could not decode image: {}", e),
+            PipelineError::Encode(e) => write!(f, "could not encode image: {}", e),
+        }
+    }
+}
+
+/// Decode an upload and build all renditions
+/// CPU heavy: call it from a blocking thread (web::block)
+pub fn process(format: ImageFormat, bytes: &[u8]) -> Result<ProcessedImage, PipelineError> {
+    let image = flatten(decode(format, bytes)?);
+    let (width, height) = image.dimensions();
+    let image = DynamicImage::ImageRgb8(image);
+
+    let mut renditions = Vec::with_capacity(RENDITIONS.len());
+    for (name, size) in RENDITIONS {
+        let resized = resize(&image, size).to_rgb8();
+
+        let mut out = Vec::new();
+        JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+            .encode_image(&resized)
+            .map_err(|e| PipelineError::Encode(e.to_string()))?;
+
+        renditions.push(Rendition { name, bytes: out });
+    }
+
+    // A tiny copy is plenty for the placeholder and keeps the encode cheap
+    let tiny = image.resize(32, 32, FilterType::Triangle).to_rgba8();
+    let blurhash = blurhash::encode(
+        BLURHASH_COMPONENTS.0,
+        BLURHASH_COMPONENTS.1,
+        tiny.width(),
+        tiny.height(),
+        tiny.as_raw(),
+    )
+    .map_err(|e| PipelineError::Encode(e.to_string()))?;
+
+    Ok(ProcessedImage { renditions, blurhash, width, height })
+}
+
+/// Decode to pixels, applying the EXIF orientation
+fn decode(format: ImageFormat, bytes: &[u8]) -> Result<DynamicImage, PipelineError> {
+    let format = match format {
+        ImageFormat::Jpeg => image::ImageFormat::Jpeg,
+        ImageFormat::Png => image::ImageFormat::Png,
+        ImageFormat::Webp => image::ImageFormat::WebP,
+        ImageFormat::Heic => return decode(ImageFormat::Png, &convert_heic(bytes)?),
+    };
+
+    // Same bounds the upload was checked against, so a lying header can't make us allocate more
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_SIDE);
+    limits.max_image_height = Some(MAX_IMAGE_SIDE);
+    limits.max_alloc = Some(MAX_IMAGE_PIXELS * 4 * 2);
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder().map_err(|e| PipelineError::Decode(e.to_string()))?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| PipelineError::Decode(e.to_string()))?;
+    image.apply_orientation(orientation);
+
+    Ok(image)
+}
+
+/// The image crate can't read HEIC, so convert it to PNG with libheif's `heif-convert`
+/// (HEIF_CONVERT overrides the command). The converter applies the HEIC orientation itself and is killed
+/// after process::TOOL_TIMEOUT.
+fn convert_heic(bytes: &[u8]) -> Result<Vec<u8>, PipelineError> {
+    let command = std::env::var("HEIF_CONVERT").unwrap_or_else(|_| "heif-convert".to_string());
+
+    let dir = std::env::temp_dir();
+    let id = Uuid::new_v4();
+    let input = dir.join(format!("{}.heic", id));
+    let output = dir.join(format!("{}.png", id));
+
+    let result = std::fs::write(&input, bytes)
+        .map_err(|e| e.to_string())
+        .and_then(|_| process::run(Command::new(&command).arg(&input).arg(&output), process::TOOL_TIMEOUT))
+        .and_then(|_| std::fs::read(&output).map_err(|e| e.to_string()))
+        .map_err(PipelineError::Decode);
+
+    let _ = std::fs::remove_file(&input);
+    let _ = std::fs::remove_file(&output);
+
+    result
+}
+
+/// Drop transparency by painting the image over white (JPEG has no alpha)
+fn flatten(image: DynamicImage) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.to_rgb8();
+    }
+
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let over_white = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        image::Rgb([over_white(r), over_white(g), over_white(b)])
+    })
+}
+
+fn resize(image: &DynamicImage, size: RenditionSize) -> DynamicImage {
+    let (width, height) = (image.width(), image.height());
+
+    match size {
+        RenditionSize::Square(side) => {
+            let side = side.min(width).min(height);
+            image.resize_to_fill(side, side, FilterType::Lanczos3)
+        }
+        RenditionSize::Fit(max_width, max_height) if width > max_width || height > max_height => {
+            image.resize(max_width, max_height, FilterType::Lanczos3)
+        }
+        RenditionSize::Fit(..) => image.clone(),
+    }
+}
@@ -0,0 +1,95 @@
+//! External tools (ffmpeg, heif-convert)
+//! Every run has a deadline, so a stuck tool or a file crafted to make it spin can't hold a blocking worker forever.
+
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long a tool may take on one upload before it's killed
+pub const TOOL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a running tool is checked on
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Run `command` to completion, killing it if it's still going after `timeout`
+/// Fails with the tool's error output if it exits unsuccessfully
+pub fn run(command: &mut Command, timeout: Duration) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{}: {}", program, e))?;
+
+    // Drained on the side, so a chatty tool can't fill the pipe and stall until the deadline
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let errors = std::thread::spawn(move || {
+        let mut out = Vec::new();
+        let _ = stderr.read_to_end(&mut out);
+        out
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(POLL_INTERVAL),
+            Ok(None) => break Err(format!("{} took longer than {}s", program, timeout.as_secs())),
+            Err(e) => break Err(format!("{}: {}", program, e)),
+        }
+    };
+
+    if status.is_err() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    let errors = errors.join().unwrap_or_default();
+
+    match status? {
+        status if status.success() => Ok(()),
+        status => Err(format!("{} ({}): {}", program, status, String::from_utf8_lossy(&errors).trim())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    }
+
+    #[test]
+    fn runs_to_completion() {
+        assert_eq!(run(&mut sh("exit 0"), TOOL_TIMEOUT), Ok(()));
+    }
+
+    #[test]
+    fn reports_the_error_output() {
+        let err = run(&mut sh("echo 'bad input' >&2; exit 3"), TOOL_TIMEOUT).unwrap_err();
+
+        assert!(err.starts_with("sh ("), "{}", err);
+        assert!(err.ends_with(": bad input"), "{}", err);
+    }
+
+    #[test]
+    fn kills_tools_that_run_too_long() {
+        let started = Instant::now();
+
+        let err = run(&mut sh("exec sleep 10"), Duration::from_millis(200)).unwrap_err();
+
+        assert!(err.contains("took longer than"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn reports_missing_tools() {
+        let err = run(&mut Command::new("no-such-tool-here"), TOOL_TIMEOUT).unwrap_err();
+
+        assert!(err.starts_with("no-such-tool-here: "), "{}", err);
+    }
+}
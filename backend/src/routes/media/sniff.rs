@@ -0,0 +1,323 @@
+//! Upload sniffing
+//! Works out what an uploaded file really is from its leading bytes.
+//! The client's file name and content type are never trusted.
+
+/// Largest image file accepted by POST /user/images
+pub const MAX_IMAGE_BYTES: usize = 15 * 1024 * 1024;
+
+/// Largest image accepted, in pixels (width x height)
+pub const MAX_IMAGE_PIXELS: u64 = 40_000_000;
+
+/// Longest side accepted, in pixels
+pub const MAX_IMAGE_SIDE: u32 = 10_000;
+
+/// Shortest side accepted, in pixels; anything smaller looks blurry on a profile card
+pub const MIN_IMAGE_SIDE: u32 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Webp,
+    Heic,
+}
+
+impl ImageFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Heic => "image/heic",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageError {
+    /// Not a JPEG, PNG, WebP or HEIC file
+    Unsupported,
+    /// The header is truncated or its size can't be read
+    Unreadable,
+    TooSmall,
+    TooLarge,
+}
+
+/// Sniff an uploaded image and check its dimensions against the limits
+/// Returns the format and (width, height)
+pub fn check_image(bytes: &[u8]) -> Result<(ImageFormat, (u32, u32)), ImageError> {
+    let format = sniff_image(bytes).ok_or(ImageError::Unsupported)?;
+    let (width, height) = image_dimensions(format, bytes).ok_or(ImageError::Unreadable)?;
+
+    if width == 0 || height == 0 {
+        return Err(ImageError::Unreadable);
+    }
+    if width < MIN_IMAGE_SIDE || height < MIN_IMAGE_SIDE {
+        return Err(ImageError::TooSmall);
+    }
+    if width > MAX_IMAGE_SIDE || height > MAX_IMAGE_SIDE || width as u64 * height as u64 > MAX_IMAGE_PIXELS {
+        return Err(ImageError::TooLarge);
+    }
+
+    Ok((format, (width, height)))
+}
+
+/// Identify the image format from its magic bytes
+pub fn sniff_image(bytes: &[u8]) -> Option<ImageFormat> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(ImageFormat::Jpeg);
+    }
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        return Some(ImageFormat::Png);
+    }
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Some(ImageFormat::Webp);
+    }
+    if is_heic(bytes) {
+        return Some(ImageFormat::Heic);
+    }
+
+    None
+}
+
+/// HEIC files start with an `ftyp` box listing an HEVC image brand
+/// (plain `mif1`/AVIF files without one are rejected)
+fn is_heic(bytes: &[u8]) -> bool {
+    const BRANDS: [&[u8]; 6] = [b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx"];
+
+    if bytes.len() < 16 || &bytes[4..8] != b"ftyp" {
+        return false;
+    }
+
+    let box_len = (read_u32_be(bytes, 0).unwrap_or(0) as usize).min(bytes.len());
+
+    // Major brand at 8, minor version at 12, compatible brands from 16
+    std::iter::once(8)
+        .chain((16..box_len).step_by(4))
+        .filter_map(|at| bytes.get(at..at + 4))
+        .any(|brand| BRANDS.contains(&brand))
+}
+
+/// Read (width, height) from the image header without decoding it
+pub fn image_dimensions(format: ImageFormat, bytes: &[u8]) -> Option<(u32, u32)> {
+    match format {
+        ImageFormat::Jpeg => jpeg_dimensions(bytes),
+        ImageFormat::Png => {
+            // IHDR is always the first chunk
+            if bytes.get(12..16)? != b"IHDR" {
+                return None;
+            }
+            Some((read_u32_be(bytes, 16)?, read_u32_be(bytes, 20)?))
+        }
+        ImageFormat::Webp => webp_dimensions(bytes),
+        ImageFormat::Heic => heic_dimensions(bytes),
+    }
+}
+
+/// Walk the JPEG markers up to the first start-of-frame
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+
+    loop {
+        if *bytes.get(at)? != 0xFF {
+            return None;
+        }
+        // Markers may be padded with extra 0xFF bytes
+        while *bytes.get(at + 1)? == 0xFF {
+            at += 1;
+        }
+
+        let marker = *bytes.get(at + 1)?;
+        at += 2;
+
+        // Standalone markers carry no length
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            continue;
+        }
+        if marker == 0xD9 || marker == 0xDA {
+            return None;
+        }
+
+        let len = read_u16_be(bytes, at)? as usize;
+
+        // SOF0-SOF15, except DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let height = read_u16_be(bytes, at + 3)? as u32;
+            let width = read_u16_be(bytes, at + 5)? as u32;
+            return Some((width, height));
+        }
+
+        at += len;
+    }
+}
+
+fn webp_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    match bytes.get(12..16)? {
+        // Lossy: 14-bit sizes after the frame tag and start code
+        b"VP8 " => Some((
+            (read_u16_le(bytes, 26)? & 0x3FFF) as u32,
+            (read_u16_le(bytes, 28)? & 0x3FFF) as u32,
+        )),
+        // Lossless: two 14-bit (size - 1) values after the signature byte
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(bytes.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        // Extended: 24-bit (size - 1) canvas values
+        b"VP8X" => Some((read_u24_le(bytes, 24)? + 1, read_u24_le(bytes, 27)? + 1)),
+        _ => None,
+    }
+}
+
+/// HEIC sizes live in `ispe` (image spatial extent) properties.
+/// Thumbnails and grid tiles have their own, so the largest one is the full image.
+fn heic_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    bytes
+        .windows(4)
+        .enumerate()
+        .filter(|(_, window)| *window == b"ispe")
+        // Box type, then version/flags, then width and height
+        .filter_map(|(at, _)| Some((read_u32_be(bytes, at + 8)?, read_u32_be(bytes, at + 12)?)))
+        .max_by_key(|(width, height)| *width as u64 * *height as u64)
+}
+
+pub(super) fn read_u16_be(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+pub(super) fn read_u16_le(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+pub(super) fn read_u24_le(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+pub(super) fn read_u32_be(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+pub(super) fn read_u32_le(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+pub(super) fn read_u64_be(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+pub(super) fn read_u64_le(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// APP0 segment, then a baseline start-of-frame
+    fn jpeg(width: u16, height: u16) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        data.extend_from_slice(b"JFIF\0");
+        data.extend_from_slice(&[0; 9]);
+        data.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08]);
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&[0; 10]);
+        data
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13];
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[8, 2, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn reads_jpeg_and_png_sizes() {
+        assert_eq!(check_image(&jpeg(640, 480)), Ok((ImageFormat::Jpeg, (640, 480))));
+        assert_eq!(check_image(&png(1080, 1350)), Ok((ImageFormat::Png, (1080, 1350))));
+    }
+
+    #[test]
+    fn skips_jpeg_fill_bytes_and_standalone_markers() {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xFF, 0xFF, 0x01];
+        data.extend_from_slice(&jpeg(300, 400)[2..]);
+
+        assert_eq!(check_image(&data), Ok((ImageFormat::Jpeg, (300, 400))));
+    }
+
+    #[test]
+    fn reads_webp_sizes() {
+        let mut extended = b"RIFF\0\0\0\0WEBPVP8X".to_vec();
+        extended.extend_from_slice(&[0; 8]);
+        extended.extend_from_slice(&[0xFF, 0x03, 0x00, 0x1F, 0x03, 0x00]);
+
+        assert_eq!(check_image(&extended), Ok((ImageFormat::Webp, (1024, 800))));
+    }
+
+    #[test]
+    fn reads_the_largest_heic_extent() {
+        let mut data = vec![0, 0, 0, 20];
+        data.extend_from_slice(b"ftypmif1\0\0\0\0heic");
+        for (width, height) in [(320u32, 240u32), (4032, 3024)] {
+            data.extend_from_slice(&[0, 0, 0, 20]);
+            data.extend_from_slice(b"ispe\0\0\0\0");
+            data.extend_from_slice(&width.to_be_bytes());
+            data.extend_from_slice(&height.to_be_bytes());
+        }
+
+        assert_eq!(check_image(&data), Ok((ImageFormat::Heic, (4032, 3024))));
+    }
+
+    #[test]
+    fn checks_the_size_limits() {
+        assert_eq!(check_image(&jpeg(199, 800)), Err(ImageError::TooSmall));
+        assert_eq!(check_image(&png(10_001, 800)), Err(ImageError::TooLarge));
+        assert_eq!(check_image(&png(8_000, 8_000)), Err(ImageError::TooLarge));
+        assert_eq!(check_image(&png(0, 800)), Err(ImageError::Unreadable));
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        assert_eq!(check_image(b"GIF89a\x01\x00\x01\x00"), Err(ImageError::Unsupported));
+        assert_eq!(check_image(&[]), Err(ImageError::Unsupported));
+        // AVIF: an ftyp box without an HEVC brand
+        assert_eq!(check_image(b"\0\0\0\x18ftypavif\0\0\0\0mif1avif"), Err(ImageError::Unsupported));
+    }
+
+    #[test]
+    fn rejects_a_jpeg_segment_of_length_zero() {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x00];
+        data.extend_from_slice(&jpeg(640, 480)[2..]);
+
+        assert_eq!(check_image(&data), Err(ImageError::Unreadable));
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        let jpeg = jpeg(640, 480);
+        for len in [3, 6, 25, jpeg.len() - 12] {
+            assert_eq!(check_image(&jpeg[..len]), Err(ImageError::Unreadable), "jpeg cut at {}", len);
+        }
+        assert_eq!(check_image(&png(640, 480)[..20]), Err(ImageError::Unreadable));
+        assert_eq!(check_image(b"RIFF\0\0\0\0WEBPVP8 "), Err(ImageError::Unreadable));
+    }
+
+    #[test]
+    fn stops_at_the_start_of_scan() {
+        let data = [0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x08, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0xE0, 0x02, 0x80];
+
+        assert_eq!(check_image(&data), Err(ImageError::Unreadable));
+    }
+
+    #[test]
+    fn reads_an_ftyp_with_size_one_and_no_64_bit_size_as_unreadable() {
+        let mut data = vec![0, 0, 0, 1];
+        data.extend_from_slice(b"ftypheic\0\0\0\0");
+
+        assert_eq!(check_image(&data), Err(ImageError::Unreadable));
+    }
+}
@@ -0,0 +1,98 @@
+//! Video transcoding
+//! Uploaded clips are stored as they are (metadata blanked); a Transcoder pulls the poster frame out of them,
+//! which then goes through the image pipeline and the image classifier like any photo.
+
+use std::path::Path;
+use std::process::Command;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::process;
+
+/// Decodes uploaded videos
+/// Plug in a different implementation to hand the work to a hosted transcoding service
+#[async_trait]
+pub trait Transcoder: Send + Sync {
+    /// Short name used in logs (VIDEO_TRANSCODER)
+    fn name(&self) -> &str;
+
+    /// Grab the frame `at_ms` into the clip stored at `video` as a PNG, turned upright
+    async fn poster_frame(&self, video: &Path, at_ms: u32) -> Result<Vec<u8>, String>;
+}
+
+/// Runs the `ffmpeg` command line tool (FFMPEG overrides the command)
+#[derive(Debug, Clone)]
+pub struct FfmpegTranscoder {
+    command: String,
+}
+
+impl FfmpegTranscoder {
+    pub fn new(command: String) -> Self {
+        Self { command }
+    }
+
+    /// ffmpeg seeks in the file, so the clip is read from disk and the frame written to a temporary file rather than pipes.
+    /// It applies the rotation phones record portrait clips with on its own, and is killed after process::TOOL_TIMEOUT.
+    fn extract(command: &str, video: &Path, at_ms: u32) -> Result<Vec<u8>, String> {
+        let output = std::env::temp_dir().join(format!("{}.png", Uuid::new_v4()));
+
+        let result = process::run(
+            Command::new(command)
+                .args(["-nostdin", "-v", "error", "-ss"])
+                .arg(format!("{}.{:03}", at_ms / 1000, at_ms % 1000))
+                .arg("-i")
+                .arg(video)
+                .args(["-frames:v", "1", "-c:v", "png", "-y"])
+                .arg(&output),
+            process::TOOL_TIMEOUT,
+        )
+        .and_then(|_| std::fs::read(&output).map_err(|e| e.to_string()));
+
+        let _ = std::fs::remove_file(&output);
+
+        result
+    }
+}
+
+#[async_trait]
+impl Transcoder for FfmpegTranscoder {
+    fn name(&self) -> &str {
+        "ffmpeg"
+    }
+
+    async fn poster_frame(&self, video: &Path, at_ms: u32) -> Result<Vec<u8>, String> {
+        let command = self.command.clone();
+        let video = video.to_path_buf();
+
+        actix_web::web::block(move || Self::extract(&command, &video, at_ms))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+}
+
+/// Turns video uploads off: every clip is refused
+#[derive(Debug, Clone, Default)]
+pub struct DisabledTranscoder;
+
+#[async_trait]
+impl Transcoder for DisabledTranscoder {
+    fn name(&self) -> &str {
+        "none"
+    }
+
+    async fn poster_frame(&self, _video: &Path, _at_ms: u32) -> Result<Vec<u8>, String> {
+        Err("video uploads are disabled".to_string())
+    }
+}
+
+/// Build the transcoder from VIDEO_TRANSCODER (`ffmpeg`, the default, or `none`)
+pub fn transcoder_from_env() -> Box<dyn Transcoder> {
+    match std::env::var("VIDEO_TRANSCODER").ok().filter(|v| !v.is_empty()).as_deref() {
+        Some("ffmpeg") | None => Box::new(FfmpegTranscoder::new(
+            std::env::var("FFMPEG").unwrap_or_else(|_| "ffmpeg".to_string()),
+        )),
+        Some("none") => Box::new(DisabledTranscoder),
+        Some(other) => panic!("Unknown VIDEO_TRANSCODER: {}", other),
+    }
+}
@@ -0,0 +1,166 @@
+//! Video clip checks
+//! Works out what an uploaded clip is and how long it lasts from its own bytes, like sniff does for images.
+//! Accepted: MP4 and QuickTime (MOV) files with an H.264 or HEVC video track, and optionally AAC audio,
+//! which is what phones record and browsers play.
+
+use super::mp4;
+
+/// Largest clip accepted
+pub const MAX_VIDEO_BYTES: usize = 50 * 1024 * 1024;
+
+/// Shortest clip accepted, in milliseconds
+pub const MIN_VIDEO_MS: u32 = 1_000;
+
+/// Longest clip accepted, in milliseconds
+pub const MAX_VIDEO_MS: u32 = 30_000;
+
+/// Where in the clip the poster frame is taken from, in milliseconds (short clips use their middle)
+pub const POSTER_AT_MS: u32 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFormat {
+    Mp4,
+    Mov,
+}
+
+impl VideoFormat {
+    pub const ALL: [VideoFormat; 2] = [VideoFormat::Mp4, VideoFormat::Mov];
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            VideoFormat::Mp4 => "video/mp4",
+            VideoFormat::Mov => "video/quicktime",
+        }
+    }
+
+    /// Extension of the stored file (media::content_type_for maps it back)
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VideoFormat::Mp4 => "mp4",
+            VideoFormat::Mov => "mov",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoError {
+    /// Not an MP4 or MOV file with a video track
+    Unsupported,
+    /// A video track that isn't H.264 or HEVC, or audio that isn't AAC
+    UnsupportedCodec,
+    /// The duration can't be read
+    Unreadable,
+    TooShort,
+    TooLong,
+}
+
+/// Sample entry formats of the video codecs accepted
+const VIDEO_CODECS: [&[u8; 4]; 4] = [b"avc1", b"avc3", b"hvc1", b"hev1"];
+
+/// Sniff an uploaded clip, check its codecs and its length against the limits
+/// Returns the format and the duration in milliseconds
+pub fn check_video(bytes: &[u8]) -> Result<(VideoFormat, u32), VideoError> {
+    let movie = mp4::parse(bytes).ok_or(VideoError::Unsupported)?;
+
+    if movie.tracks_of(b"vide").next().is_none() {
+        return Err(VideoError::Unsupported);
+    }
+
+    let playable_video = movie
+        .tracks_of(b"vide")
+        .all(|t| t.codec.is_some_and(|c| VIDEO_CODECS.contains(&&c)));
+    let playable_audio = movie.tracks_of(b"soun").all(|t| t.codec == Some(*b"mp4a"));
+
+    if !playable_video || !playable_audio {
+        return Err(VideoError::UnsupportedCodec);
+    }
+
+    // QuickTime files name `qt  ` as their major brand
+    let format = match movie.brands.first() {
+        Some(b"qt  ") => VideoFormat::Mov,
+        _ => VideoFormat::Mp4,
+    };
+
+    let duration_ms = movie.duration_ms.ok_or(VideoError::Unreadable)?;
+
+    if duration_ms < MIN_VIDEO_MS {
+        return Err(VideoError::TooShort);
+    }
+    if duration_ms > MAX_VIDEO_MS {
+        return Err(VideoError::TooLong);
+    }
+
+    Ok((format, duration_ms))
+}
+
+/// Remove metadata the camera wrote (location, device), keeping the file playable
+pub fn strip_metadata(bytes: &mut [u8]) {
+    mp4::strip_metadata(bytes);
+}
+
+/// Time to take the poster frame at for a clip of `duration_ms`
+pub fn poster_at_ms(duration_ms: u32) -> u32 {
+    POSTER_AT_MS.min(duration_ms / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mp4::fixtures::{ftyp, movie, mp4_box, mvhd, trak};
+    use super::*;
+
+    #[test]
+    fn accepts_h264_and_hevc_clips() {
+        let mp4 = movie(b"isom", 5_000, &[(b"vide", b"avc1"), (b"soun", b"mp4a")]);
+        assert_eq!(check_video(&mp4), Ok((VideoFormat::Mp4, 5_000)));
+
+        let mov = movie(b"qt  ", 12_000, &[(b"vide", b"hvc1")]);
+        assert_eq!(check_video(&mov), Ok((VideoFormat::Mov, 12_000)));
+    }
+
+    #[test]
+    fn checks_the_length() {
+        assert_eq!(check_video(&movie(b"isom", 999, &[(b"vide", b"avc1")])), Err(VideoError::TooShort));
+        assert_eq!(check_video(&movie(b"isom", 30_001, &[(b"vide", b"avc1")])), Err(VideoError::TooLong));
+    }
+
+    #[test]
+    fn rejects_other_codecs() {
+        assert_eq!(check_video(&movie(b"isom", 5_000, &[(b"vide", b"vp09")])), Err(VideoError::UnsupportedCodec));
+        assert_eq!(
+            check_video(&movie(b"isom", 5_000, &[(b"vide", b"avc1"), (b"soun", b"Opus")])),
+            Err(VideoError::UnsupportedCodec)
+        );
+    }
+
+    #[test]
+    fn rejects_files_without_video() {
+        assert_eq!(check_video(&movie(b"M4A ", 5_000, &[(b"soun", b"mp4a")])), Err(VideoError::Unsupported));
+        assert_eq!(check_video(b"not a video at all"), Err(VideoError::Unsupported));
+        assert_eq!(check_video(&[]), Err(VideoError::Unsupported));
+    }
+
+    #[test]
+    fn rejects_an_ftyp_with_size_one_and_no_64_bit_size() {
+        let mut data = 1u32.to_be_bytes().to_vec();
+        data.extend_from_slice(b"ftypisom");
+
+        assert_eq!(check_video(&data), Err(VideoError::Unsupported));
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let data = movie(b"isom", 5_000, &[(b"vide", b"avc1")]);
+
+        for len in [0, 8, 20, data.len() - 1] {
+            assert_eq!(check_video(&data[..len]), Err(VideoError::Unsupported), "cut at {}", len);
+        }
+    }
+
+    #[test]
+    fn needs_a_readable_duration() {
+        let moov = [mvhd(0, 5_000), trak(b"vide", b"avc1")].concat();
+        let data = [ftyp(b"isom"), mp4_box(b"moov", &moov)].concat();
+
+        assert_eq!(check_video(&data), Err(VideoError::Unreadable));
+    }
+}
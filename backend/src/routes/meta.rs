@@ -0,0 +1,19 @@
+use actix_web::{HttpResponse, Responder, web};
+use sqlx::PgPool;
+
+use crate::models::outputs::{OptionsResponse, StatusResponse};
+use crate::routes::profile::options::ProfileOptions;
+
+/// GET /meta/options - Allowed values for the controlled profile fields
+pub async fn get_options(pool: web::Data<PgPool>) -> impl Responder {
+    match ProfileOptions::load(&pool).await {
+        Ok(options) => HttpResponse::Ok().json(OptionsResponse { options: options.fields }),
+        Err(e) => {
+            println!("Failed to get profile options: {:?}", e);
+            HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to get options".to_string()),
+            })
+        }
+    }
+}
@@ -1,7 +1,12 @@
+pub mod admin;
 pub mod auth;
 pub mod feed;
 pub mod interactions;
+pub mod likes;
 pub mod matches;
+pub mod media;
+pub mod meta;
 pub mod profile;
 pub mod prompts;
+pub mod reports;
 pub mod user;
\ No newline at end of file
@@ -1,16 +1,28 @@
+pub mod birthdate;
+pub mod completeness;
+pub mod options;
+pub mod visibility;
+
 use actix_web::{HttpRequest, HttpResponse, HttpMessage, Responder, web};
-use actix_multipart::form::{MultipartForm, json::Json as MpJson, tempfile::TempFile};
-use serde::Deserialize;
+use actix_multipart::form::{MultipartForm, tempfile::TempFile, text::Text};
 use sqlx::PgPool;
 use uuid::Uuid;
+use chrono::Utc;
 use std::fs;
-use std::path::Path;
 
 use crate::models::outputs::{UserProfile, UserImage, UserPrompt};
-use crate::models::inputs::{UpdateProfileRequest, UploadProfileImageRequest };
+use crate::models::inputs::{ReorderImagesRequest, UpdateImageCaptionRequest, UpdateProfileRequest, UploadProfileImageRequest};
 use crate::jwtauth::Claims;
-use crate::models::outputs::{StatusResponse, FinalizeProfileResponse};
-use crate::db::{profile_queries, prompt_queries, images_queries};
+use crate::models::outputs::{ErrorCode, ErrorResponse, StatusResponse, FinalizeProfileResponse, ImageRenditions, ImageUploadResponse};
+use crate::models::state::AppState;
+use crate::routes::{media, prompts};
+use crate::routes::media::moderation::{self, ModerationStatus};
+use crate::routes::media::pipeline::{self, PipelineError};
+use crate::routes::media::sniff::{self, ImageError};
+use crate::routes::media::video::{self, VideoError};
+use birthdate::BirthdateError;
+use options::ProfileOptions;
+use crate::db::{feed_queries, profile_queries, prompt_queries, images_queries, user_queries};
 
 pub async fn get_profile(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
     let Some(claims) = req.extensions().get::<Claims>().cloned() else {
@@ -33,24 +45,22 @@ pub async fn get_profile(pool: web::Data<PgPool>, req: HttpRequest) -> impl Resp
         Err(_) => None,
     };
 
-    // Get images and map tuples to UserImage structs
+    // Get images with their renditions and, since they're the user's own, moderation status
     let user_images = match images_queries::get_images(&pool, &user_id).await {
-        Ok(rows) => Some(rows.into_iter().map(|(id, url, order)| UserImage {
-            id: id.to_string(),
-            url,
-            order,
+        Ok(rows) => Some(rows.into_iter().map(|row| {
+            let (status, reason) = (row.moderation_status.clone(), row.rejection_reason.clone());
+            UserImage {
+                moderation_status: Some(status),
+                rejection_reason: reason,
+                ..UserImage::from(row)
+            }
         }).collect()),
         Err(_) => None,
     };
 
     // Get prompts and map to UserPrompt structs
     let user_prompts = match prompt_queries::get_user_prompts(&pool, &user_id).await {
-        Ok(rows) => Some(rows.into_iter().map(|(id, question, answer, order)| UserPrompt {
-            id: id.to_string(),
-            question,
-            answer,
-            order,
-        }).collect()),
+        Ok(rows) => Some(rows.into_iter().map(UserPrompt::from).collect()),
         Err(_) => None,
     };
 
@@ -59,6 +69,7 @@ pub async fn get_profile(pool: web::Data<PgPool>, req: HttpRequest) -> impl Resp
         images: user_images,
         prompts: user_prompts,
         details: profile_details,
+        superliked_you: false,
     };
 
     HttpResponse::Ok().json(user_profile)
@@ -69,6 +80,8 @@ pub async fn update_profile(
     body: web::Json<UpdateProfileRequest>,
     pool: web::Data<PgPool>,
 ) -> impl Responder {
+    let mut body = body.into_inner();
+
     println!("Profile: Updating - Name: {:?}", body.name);
 
     // Get the user ID from the claims (stored by JWT middleware)
@@ -93,9 +106,113 @@ pub async fn update_profile(
         }
     };
 
+    // Controlled fields (gender, drinks, ...) must use a value from GET /meta/options
+    let options = match ProfileOptions::load(&pool).await {
+        Ok(options) => options,
+        Err(e) => {
+            println!("Failed to get profile options: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Database error".to_string()),
+            });
+        }
+    };
+
+    if let Err((field, allowed)) = options.normalize(&mut body) {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidOption,
+            &format!("{} must be one of: {}", field, allowed.join(", ")),
+        ));
+    }
+
+    // Visibility flags may only name fields that can be hidden
+    let visibility = match &body.visibility {
+        Some(flags) => match visibility::split(flags) {
+            Ok(split) => Some(split),
+            Err(field) => {
+                return HttpResponse::BadRequest().json(ErrorResponse::new(
+                    ErrorCode::InvalidVisibility,
+                    &format!("{} can't be hidden, hideable fields: {}", field, visibility::HIDEABLE_FIELDS.join(", ")),
+                ));
+            }
+        },
+        None => None,
+    };
+
+    // Birthdate must be a real date for an adult, and can't change once set (unless support unlocks it)
+    let birthdate = match &body.birthdate {
+        Some(value) => match birthdate::parse(value, Utc::now().date_naive()) {
+            Ok(date) => Some(date),
+            Err(BirthdateError::Format) => {
+                return HttpResponse::BadRequest().json(ErrorResponse::new(
+                    ErrorCode::InvalidBirthdate,
+                    "Birthdate must be a date like \"1995-03-15\"",
+                ));
+            }
+            Err(BirthdateError::Implausible) => {
+                return HttpResponse::BadRequest().json(ErrorResponse::new(
+                    ErrorCode::InvalidBirthdate,
+                    "Birthdate is not plausible",
+                ));
+            }
+            Err(BirthdateError::Underage) => {
+                return HttpResponse::BadRequest().json(ErrorResponse::new(
+                    ErrorCode::Underage,
+                    &format!("You must be at least {} to use Aligned", birthdate::MIN_AGE),
+                ));
+            }
+        },
+        None => None,
+    };
+
+    if let Some(date) = birthdate {
+        match profile_queries::get_birthdate_lock(&pool, &user_id).await {
+            Ok(Some((Some(current), false))) if current != date => {
+                return HttpResponse::Conflict().json(ErrorResponse::new(
+                    ErrorCode::BirthdateLocked,
+                    "Birthdate can't be changed, contact support",
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                println!("Failed to get birthdate: {:?}", e);
+                return HttpResponse::InternalServerError().json(StatusResponse {
+                    status: "error".to_string(),
+                    message: Some("Database error".to_string()),
+                });
+            }
+        }
+    }
+
+    // Time zone lives on the users table; it's saved with the profile
+    if let Some(time_zone) = &body.time_zone {
+        match user_queries::is_valid_time_zone(&pool, time_zone).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return HttpResponse::BadRequest().json(ErrorResponse::new(
+                    ErrorCode::InvalidTimeZone,
+                    "Unknown time zone, use an IANA name like \"Europe/London\"",
+                ));
+            }
+            Err(e) => {
+                println!("Failed to check time zone: {:?}", e);
+                return HttpResponse::InternalServerError().json(StatusResponse {
+                    status: "error".to_string(),
+                    message: Some("Database error".to_string()),
+                });
+            }
+        }
+    }
+
     // Create or update profile
-    match profile_queries::upsert_profile(&pool, &user_id, &body).await {
+    let visibility = visibility.as_ref().map(|(hide, show)| (hide.as_slice(), show.as_slice()));
+    match profile_queries::upsert_profile(&pool, &user_id, &body, birthdate, visibility).await {
         Ok(_) => {
+            // Profile fields feed into ranking, for this user's pool and others'
+            if let Err(e) = feed_queries::request_pool_refresh(&pool, &user_id).await {
+                println!("Failed to request feed pool refresh: {:?}", e);
+            }
+
             HttpResponse::Ok().json(StatusResponse {
                 status: "success".to_string(),
                 message: Some("Profile updated successfully".to_string()),
@@ -111,9 +228,14 @@ pub async fn update_profile(
     }
 }
 
-pub async fn upload_profile_images(pool: web::Data<PgPool>, req: HttpRequest, body: web::Json<UploadProfileImageRequest>) -> impl Responder {
-    // Multipart handling would go here
-
+/// POST /profile/images - Add an image that was uploaded through POST /user/images
+/// Only URLs of the user's own files in the media store are accepted
+pub async fn upload_profile_images(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<UploadProfileImageRequest>,
+) -> impl Responder {
     let Some(claims) = req.extensions().get::<Claims>().cloned() else {
         return HttpResponse::Unauthorized().json(StatusResponse {
             status: "error".to_string(),
@@ -131,16 +253,33 @@ pub async fn upload_profile_images(pool: web::Data<PgPool>, req: HttpRequest, bo
         }
     };
 
-    match images_queries::upload_profile_images(&pool, &user_id, &body.image_url).await {
-        Ok(_) => HttpResponse::Ok().json(StatusResponse {
+    let Some(key) = state
+        .media
+        .key_from_url(&body.image_url)
+        .filter(|key| key.starts_with(&media::user_prefix(&user_id)))
+    else {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidImageUrl,
+            "Images must be uploaded through POST /user/images",
+        ));
+    };
+
+    // Uploads are processed and added by POST /user/images, so there's nothing left to do for them;
+    // anything else in the store (e.g. an unprocessed original) is not a profile image
+    match images_queries::find_image_by_url(&pool, &user_id, &state.media.url(&key)).await {
+        Ok(Some(_)) => HttpResponse::Ok().json(StatusResponse {
             status: "success".to_string(),
             message: Some("Profile images uploaded successfully".to_string()),
         }),
+        Ok(None) => HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidImageUrl,
+            "Images must be uploaded through POST /user/images",
+        )),
         Err(e) => {
-            println!("Failed to upload profile images: {:?}", e);
+            println!("Failed to look up image: {:?}", e);
             HttpResponse::InternalServerError().json(StatusResponse {
                 status: "error".to_string(),
-                message: Some(e.to_string()),
+                message: Some("Database error".to_string()),
             })
         }
     }
@@ -167,52 +306,25 @@ pub async fn finalize_profile(req: HttpRequest, pool: web::Data<PgPool>) -> impl
         }
     };
 
-    let mut pending: Vec<String> = Vec::new();
-
-    // CHECK: all the 6 photos uploaded
-    let images_uploaded = match images_queries::count_images(&pool, &user_id).await {
-        Ok(count) => count,
-        Err(e) => {
-            println!("Failed to count images: {:?}", e);
-            return HttpResponse::InternalServerError().json(StatusResponse {
-                status: "error".to_string(),
-                message: Some(e.to_string()),
-            });
-        }
-    };
-
-    if images_uploaded < 6 {
-        pending.push(format!("Upload {} more images", 6 - images_uploaded));
-    }
-
-    // CHECK: all the 3 prompts uploaded
-    let prompts_uploaded = match prompt_queries::count_prompts(&pool, &user_id).await {
-        Ok(count) => count,
+    // CHECK: 6 photos, 3 prompts and all profile details
+    let pending = match completeness::pending_actions(&pool, &user_id).await {
+        Ok(pending) => pending,
         Err(e) => {
-            println!("Failed to count prompts: {:?}", e);
+            println!("Failed to check profile completeness: {:?}", e);
             return HttpResponse::InternalServerError().json(StatusResponse {
                 status: "error".to_string(),
                 message: Some(e.to_string()),
             });
         }
     };
-    if prompts_uploaded < 3 {
-        pending.push(format!("Upload {} more prompts", 3 - prompts_uploaded));
-    }
 
-    // CHECK: all profile details filled
-    let missing_fields = match profile_queries::check_profile_attributes_filled(&pool, &user_id).await {
-        Ok(count) => count,
-        Err(e) => {
-            println!("Failed to check profile attributes: {:?}", e);
-            return HttpResponse::InternalServerError().json(StatusResponse {
-                status: "error".to_string(),
-                message: Some(e.to_string()),
-            });
-        }
-    };
-    if missing_fields > 0 {
-        pending.push(format!("Fill {} more profile details", missing_fields));
+    // Persist the result so /auth/phone/verify and the feed see it
+    if let Err(e) = completeness::set_complete(&pool, &user_id, pending.is_empty()).await {
+        println!("Failed to update profile completeness: {:?}", e);
+        return HttpResponse::InternalServerError().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some(e.to_string()),
+        });
     }
 
     if pending.is_empty() {
@@ -233,6 +345,7 @@ pub async fn finalize_profile(req: HttpRequest, pool: web::Data<PgPool>) -> impl
 pub async fn delete_account(
     req: HttpRequest,
     pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
 ) -> impl Responder {
     // Get user ID from claims
     let claims = match req.extensions().get::<Claims>().cloned() {
@@ -255,9 +368,38 @@ pub async fn delete_account(
         }
     };
 
+    // Collect the stored files first; the rows that point at them go with the account
+    let media_keys = match images_queries::get_storage_keys(&pool, &user_id).await {
+        Ok(keys) => keys,
+        Err(e) => {
+            println!("Failed to list account media: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Database error".to_string()),
+            });
+        }
+    };
+    let recording_keys = match prompt_queries::get_all_media_keys(&pool, &user_id).await {
+        Ok(keys) => keys,
+        Err(e) => {
+            println!("Failed to list account media: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Database error".to_string()),
+            });
+        }
+    };
+
     // Delete user and profile and images and prompts
     match profile_queries::delete_user(&pool, &user_id).await {
         Ok(_) => {
+            for key in &media_keys {
+                media::delete_image_files(state.media.as_ref(), key).await;
+            }
+            for keys in &recording_keys {
+                prompts::delete_recordings(state.media.as_ref(), keys).await;
+            }
+
             HttpResponse::Ok().json(StatusResponse {
                 status: "success".to_string(),
                 message: Some("Account deleted successfully".to_string()),
@@ -273,19 +415,457 @@ pub async fn delete_account(
     }
 }
 
-#[derive(Deserialize, Debug)]
-struct Metadata {
-    name: String,
-}
+/// Longest caption allowed on a profile image, in characters
+pub const MAX_CAPTION_LEN: usize = 150;
 
+/// Size is capped by the route's MultipartFormConfig (sniff::MAX_IMAGE_BYTES)
 #[derive(Debug, MultipartForm)]
 pub struct ImageUpload {
-    #[multipart(limit = "50mb")]
     file: TempFile,
-    metadata: MpJson<Metadata>,
+    caption: Option<Text<String>>,
+}
+
+/// Trim a caption; blank captions are None
+/// Returns Err if it's longer than MAX_CAPTION_LEN
+fn normalize_caption(caption: Option<String>) -> Result<Option<String>, HttpResponse> {
+    let caption = caption.map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+
+    match caption {
+        Some(c) if c.chars().count() > MAX_CAPTION_LEN => Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidCaption,
+            &format!("Captions can be at most {} characters", MAX_CAPTION_LEN),
+        ))),
+        caption => Ok(caption),
+    }
+}
+
+/// Remove stored renditions whose user_images row could not be written
+async fn discard_media(state: &AppState, keys: &[String]) {
+    for key in keys {
+        if let Err(e) = state.media.delete(key).await {
+            println!("Failed to delete orphaned media {}: {}", key, e);
+        }
+    }
+}
+
+/// Check an uploaded image, run it through the pipeline and write its renditions to the media store
+/// Returns the row to save and the stored keys (to discard if saving fails), or the error response
+async fn store_upload(state: &AppState, user_id: &Uuid, file: &TempFile) -> Result<(images_queries::NewImage, Vec<String>), HttpResponse> {
+    let bytes = match fs::read(file.file.path()) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            println!("Failed to read upload: {:?}", e);
+            return Err(HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to upload file".to_string()),
+            }));
+        }
+    };
+
+    let format = match sniff::check_image(&bytes) {
+        Ok((format, _)) => format,
+        Err(ImageError::Unsupported) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::UnsupportedMediaType,
+                "Only JPEG, PNG, WebP and HEIC images are allowed",
+            )))
+        }
+        Err(ImageError::Unreadable) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::UnsupportedMediaType,
+                "The image could not be read",
+            )))
+        }
+        Err(ImageError::TooSmall) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::InvalidImageDimensions,
+                &format!("Images must be at least {0}x{0} pixels", sniff::MIN_IMAGE_SIDE),
+            )))
+        }
+        Err(ImageError::TooLarge) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::InvalidImageDimensions,
+                &format!(
+                    "Images can be at most {} pixels per side and {} megapixels",
+                    sniff::MAX_IMAGE_SIDE,
+                    sniff::MAX_IMAGE_PIXELS / 1_000_000
+                ),
+            )))
+        }
+    };
+
+    process_and_store(state, media::image_key(user_id), format, bytes).await
+}
+
+/// Run an image through the pipeline and write its renditions to the media store under `base_key`
+/// Returns the row to save and the stored keys (to discard if saving fails), or the error response
+async fn process_and_store(
+    state: &AppState,
+    base_key: String,
+    format: sniff::ImageFormat,
+    bytes: Vec<u8>,
+) -> Result<(images_queries::NewImage, Vec<String>), HttpResponse> {
+    let processed = match web::block(move || pipeline::process(format, &bytes)).await {
+        Ok(Ok(processed)) => processed,
+        Ok(Err(PipelineError::Decode(e))) => {
+            println!("Failed to decode upload: {}", e);
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::UnsupportedMediaType,
+                "The image could not be read",
+            )));
+        }
+        Ok(Err(e)) => {
+            println!("Failed to process upload: {}", e);
+            return Err(HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to process image".to_string()),
+            }));
+        }
+        Err(e) => {
+            println!("Image pipeline failed: {:?}", e);
+            return Err(HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to process image".to_string()),
+            }));
+        }
+    };
+
+    let mut stored: Vec<String> = Vec::with_capacity(processed.renditions.len());
+
+    for rendition in processed.renditions {
+        let key = media::rendition_key(&base_key, rendition.name);
+
+        if let Err(e) = state.media.put(&key, rendition.bytes, "image/jpeg").await {
+            println!("Failed to store media {} ({}): {}", key, state.media.name(), e);
+            discard_media(state, &stored).await;
+            return Err(HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to upload file".to_string()),
+            }));
+        }
+
+        stored.push(key);
+    }
+
+    let url_of = |name: &str| state.media.url(&media::rendition_key(&base_key, name));
+    let image = images_queries::NewImage {
+        url: url_of("full"),
+        thumbnail_url: url_of("thumbnail"),
+        card_url: url_of("card"),
+        storage_key: base_key,
+        blurhash: processed.blurhash,
+        width: processed.width as i32,
+        height: processed.height as i32,
+        caption: None,
+        video: None,
+    };
+
+    Ok((image, stored))
+}
+
+/// Check an uploaded clip, take its poster frame with the transcoder and run that through the image pipeline,
+/// then write the clip (metadata blanked) and the poster's renditions to the media store under `base_key`
+/// Used for video slots and video prompts
+/// Returns the poster's row, the stored clip and all stored keys (to discard if saving fails), or the error response
+pub async fn store_video_upload(
+    state: &AppState,
+    base_key: String,
+    file: &TempFile,
+) -> Result<(images_queries::NewImage, images_queries::NewVideo, Vec<String>), HttpResponse> {
+    let mut bytes = match fs::read(file.file.path()) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            println!("Failed to read upload: {:?}", e);
+            return Err(HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to upload file".to_string()),
+            }));
+        }
+    };
+
+    let (format, duration_ms) = match video::check_video(&bytes) {
+        Ok(checked) => checked,
+        Err(VideoError::Unsupported) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::UnsupportedMediaType,
+                "Only MP4 and MOV videos are allowed",
+            )))
+        }
+        Err(VideoError::UnsupportedCodec) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::UnsupportedMediaType,
+                "Videos must be H.264 or HEVC, with AAC audio",
+            )))
+        }
+        Err(VideoError::Unreadable) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::UnsupportedMediaType,
+                "The video could not be read",
+            )))
+        }
+        Err(VideoError::TooShort | VideoError::TooLong) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::InvalidVideoDuration,
+                &format!(
+                    "Videos must be between {} and {} seconds long",
+                    video::MIN_VIDEO_MS / 1000,
+                    video::MAX_VIDEO_MS / 1000
+                ),
+            )))
+        }
+    };
+
+    // The transcoder reads the clip from the upload's temporary file, so that gets the stripped copy too
+    video::strip_metadata(&mut bytes);
+    if let Err(e) = fs::write(file.file.path(), &bytes) {
+        println!("Failed to rewrite upload: {:?}", e);
+        return Err(HttpResponse::InternalServerError().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Failed to upload file".to_string()),
+        }));
+    }
+
+    let poster = match state.transcoder.poster_frame(file.file.path(), video::poster_at_ms(duration_ms)).await {
+        Ok(poster) => poster,
+        Err(e) => {
+            println!("Transcoder {} failed to take a poster frame: {}", state.transcoder.name(), e);
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::UnsupportedMediaType,
+                "The video could not be read",
+            )));
+        }
+    };
+
+    let (poster, mut stored) = process_and_store(state, base_key, sniff::ImageFormat::Png, poster).await?;
+
+    let key = media::video_key(&poster.storage_key, format.extension());
+    if let Err(e) = state.media.put(&key, bytes, format.content_type()).await {
+        println!("Failed to store media {} ({}): {}", key, state.media.name(), e);
+        discard_media(state, &stored).await;
+        return Err(HttpResponse::InternalServerError().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Failed to upload file".to_string()),
+        }));
+    }
+
+    let video = images_queries::NewVideo {
+        url: state.media.url(&key),
+        duration_ms: duration_ms as i32,
+    };
+    stored.push(key);
+
+    Ok((poster, video, stored))
+}
+
+/// Run a freshly saved image past the classifier and build the upload response
+async fn review_and_respond(
+    pool: &PgPool,
+    state: &AppState,
+    user_id: &Uuid,
+    id: &Uuid,
+    order: i32,
+    image: images_queries::NewImage,
+) -> ImageUploadResponse {
+    let (status, reason) = moderation::review(
+        pool,
+        state.media.as_ref(),
+        state.classifier.as_ref(),
+        user_id,
+        id,
+        &image.storage_key,
+    )
+    .await;
+
+    ImageUploadResponse {
+        moderation_status: status.as_str().to_string(),
+        rejection_reason: reason,
+        ..upload_response(id, order, image)
+    }
+}
+
+fn upload_response(id: &Uuid, order: i32, image: images_queries::NewImage) -> ImageUploadResponse {
+    ImageUploadResponse {
+        id: id.to_string(),
+        url: image.url.clone(),
+        order,
+        media_type: image.media_type().to_string(),
+        video_url: image.video.as_ref().map(|v| v.url.clone()),
+        video_duration_ms: image.video.as_ref().map(|v| v.duration_ms),
+        renditions: ImageRenditions {
+            thumbnail: image.thumbnail_url,
+            card: image.card_url,
+            full: image.url,
+        },
+        blurhash: image.blurhash,
+        caption: image.caption,
+        moderation_status: ModerationStatus::Pending.as_str().to_string(),
+        rejection_reason: None,
+    }
+}
+
+/// What an upload to a media slot must be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlotMedia {
+    Image,
+    Video,
+}
+
+/// Check and store an upload for a media slot
+async fn store_slot_upload(
+    state: &AppState,
+    user_id: &Uuid,
+    kind: SlotMedia,
+    file: &TempFile,
+) -> Result<(images_queries::NewImage, Vec<String>), HttpResponse> {
+    match kind {
+        SlotMedia::Image => store_upload(state, user_id, file).await,
+        SlotMedia::Video => {
+            let (mut image, video, stored) = store_video_upload(state, media::image_key(user_id), file).await?;
+            image.video = Some(video);
+            Ok((image, stored))
+        }
+    }
+}
+
+/// POST /user/images - Upload a profile image file (optional `caption` text part)
+/// The file is run through the image pipeline (upright, metadata stripped, resized), its renditions are written
+/// to the media store under a random per-user key and the image is added to the end of the profile,
+/// then the image classifier decides whether other users get to see it
+/// Its type comes from the file's own bytes, never from the client's file name or content type
+pub async fn upload_user_images(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    MultipartForm(form): MultipartForm<ImageUpload>,
+) -> impl Responder {
+    add_to_profile(pool, state, req, form, SlotMedia::Image).await
+}
+
+/// POST /user/videos - Upload a short video as a media slot (optional `caption` text part)
+/// The clip is stored as it is, minus its metadata; its poster frame goes through the image pipeline and the
+/// image classifier like a photo, and is what `url` and the renditions point at
+pub async fn upload_user_video(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    MultipartForm(form): MultipartForm<ImageUpload>,
+) -> impl Responder {
+    add_to_profile(pool, state, req, form, SlotMedia::Video).await
+}
+
+/// Add an uploaded image or video to the end of the profile
+async fn add_to_profile(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    form: ImageUpload,
+    kind: SlotMedia,
+) -> HttpResponse {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        });
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        });
+    };
+
+    let caption = match normalize_caption(form.caption.map(|c| c.into_inner())) {
+        Ok(caption) => caption,
+        Err(response) => return response,
+    };
+
+    // Check before storing anything; add_image checks again in case of concurrent uploads
+    match images_queries::count_images(&pool, &user_id).await {
+        Ok(count) if count >= images_queries::MAX_IMAGES => {
+            return HttpResponse::Conflict().json(ErrorResponse::new(
+                ErrorCode::TooManyImages,
+                "Maximum 6 images allowed",
+            ))
+        }
+        Ok(_) => {}
+        Err(e) => {
+            println!("Failed to count images: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Database error".to_string()),
+            });
+        }
+    }
+
+    let (mut image, stored) = match store_slot_upload(&state, &user_id, kind, &form.file).await {
+        Ok(uploaded) => uploaded,
+        Err(response) => return response,
+    };
+    image.caption = caption;
+
+    match images_queries::add_image(&pool, &user_id, &image).await {
+        Ok(Some((id, order))) => {
+            HttpResponse::Ok().json(review_and_respond(&pool, &state, &user_id, &id, order, image).await)
+        }
+        Ok(None) => {
+            discard_media(&state, &stored).await;
+            HttpResponse::Conflict().json(ErrorResponse::new(
+                ErrorCode::TooManyImages,
+                "Maximum 6 images allowed",
+            ))
+        }
+        Err(e) if images_queries::is_order_conflict(&e) => {
+            discard_media(&state, &stored).await;
+            HttpResponse::Conflict().json(ErrorResponse::new(
+                ErrorCode::ImagesChanged,
+                "Your images changed during the upload, try again",
+            ))
+        }
+        Err(e) => {
+            println!("Failed to save image: {:?}", e);
+            discard_media(&state, &stored).await;
+            HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Database error".to_string()),
+            })
+        }
+    }
+}
+
+/// PUT /profile/images/{id} - Replace the file behind one image, keeping its slot
+/// Same multipart body and checks as POST /user/images; the caption is kept unless a new one is sent
+pub async fn replace_image(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    MultipartForm(form): MultipartForm<ImageUpload>,
+) -> impl Responder {
+    replace_slot(pool, state, req, path, form, SlotMedia::Image).await
 }
 
-pub async fn upload_user_images(pool: web::Data<PgPool>, req: HttpRequest, MultipartForm(form): MultipartForm<ImageUpload>) -> impl Responder {
+/// PUT /profile/images/{id}/video - Put a video in a slot instead of its current image or video
+/// Same multipart body and checks as POST /user/videos; the caption is kept unless a new one is sent
+pub async fn replace_with_video(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    MultipartForm(form): MultipartForm<ImageUpload>,
+) -> impl Responder {
+    replace_slot(pool, state, req, path, form, SlotMedia::Video).await
+}
+
+/// Replace the file behind a media slot with an uploaded image or video
+async fn replace_slot(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    form: ImageUpload,
+    kind: SlotMedia,
+) -> HttpResponse {
     let Some(claims) = req.extensions().get::<Claims>().cloned() else {
         return HttpResponse::Unauthorized().json(StatusResponse {
             status: "error".to_string(),
@@ -300,25 +880,209 @@ pub async fn upload_user_images(pool: web::Data<PgPool>, req: HttpRequest, Multi
         });
     };
 
-    let file_name = form.file.file_name.as_ref().map(|s| s.as_str()).unwrap_or("uploaded_file");
-    let dest_path = Path::new("./uploads/").join(file_name);
+    let Ok(image_id) = Uuid::parse_str(&path.into_inner()) else {
+        return HttpResponse::NotFound().json(ErrorResponse::new(ErrorCode::ImageNotFound, "Image not found"));
+    };
 
-    println!("Path : {:?}", dest_path);
+    let caption = match normalize_caption(form.caption.map(|c| c.into_inner())) {
+        Ok(caption) => caption,
+        Err(response) => return response,
+    };
+
+    // Check before storing anything; replace_image checks again
+    match images_queries::image_belongs_to(&pool, &image_id, &user_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::NotFound().json(ErrorResponse::new(ErrorCode::ImageNotFound, "Image not found"));
+        }
+        Err(e) => {
+            println!("Failed to look up image: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Database error".to_string()),
+            });
+        }
+    }
+
+    let (mut image, stored) = match store_slot_upload(&state, &user_id, kind, &form.file).await {
+        Ok(uploaded) => uploaded,
+        Err(response) => return response,
+    };
+    image.caption = caption;
+
+    match images_queries::replace_image(&pool, &user_id, &image_id, &image).await {
+        Ok(Some((order, old_key))) => {
+            if let Some(old_key) = old_key {
+                media::delete_image_files(state.media.as_ref(), &old_key).await;
+            }
+
+            // The response only carries the caption if one was sent; GET /profile/me has the stored one
+            let response = review_and_respond(&pool, &state, &user_id, &image_id, order, image).await;
+
+            // A replacement the classifier didn't approve straight away takes the profile out of other users' feeds
+            if let Err(e) = completeness::recheck(&pool, &user_id).await {
+                println!("Failed to recheck profile completeness: {:?}", e);
+            }
+
+            HttpResponse::Ok().json(response)
+        }
+        Ok(None) => {
+            discard_media(&state, &stored).await;
+            HttpResponse::NotFound().json(ErrorResponse::new(ErrorCode::ImageNotFound, "Image not found"))
+        }
+        Err(e) => {
+            println!("Failed to replace image: {:?}", e);
+            discard_media(&state, &stored).await;
+            HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Database error".to_string()),
+            })
+        }
+    }
+}
+
+/// PUT /profile/images/order - Reorder the profile images
+/// The body lists every image id once, first image first
+pub async fn reorder_images(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    body: web::Json<ReorderImagesRequest>,
+) -> impl Responder {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        });
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        });
+    };
+
+    let Ok(image_ids) = body.image_ids.iter().map(|id| Uuid::parse_str(id)).collect::<Result<Vec<Uuid>, _>>() else {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidImageOrder,
+            "image_ids must list each of your images exactly once",
+        ));
+    };
+
+    match images_queries::reorder_images(&pool, &user_id, &image_ids).await {
+        Ok(true) => HttpResponse::Ok().json(StatusResponse {
+            status: "success".to_string(),
+            message: Some("Images reordered".to_string()),
+        }),
+        Ok(false) => HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidImageOrder,
+            "image_ids must list each of your images exactly once",
+        )),
+        Err(e) => {
+            println!("Failed to reorder images: {:?}", e);
+            HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Database error".to_string()),
+            })
+        }
+    }
+}
+
+/// PUT /profile/images/{id}/caption - Set or clear (null / blank) an image's caption
+pub async fn update_image_caption(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpdateImageCaptionRequest>,
+) -> impl Responder {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        });
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        });
+    };
+
+    let Ok(image_id) = Uuid::parse_str(&path.into_inner()) else {
+        return HttpResponse::NotFound().json(ErrorResponse::new(ErrorCode::ImageNotFound, "Image not found"));
+    };
+
+    let caption = match normalize_caption(body.into_inner().caption) {
+        Ok(caption) => caption,
+        Err(response) => return response,
+    };
+
+    match images_queries::set_caption(&pool, &user_id, &image_id, caption.as_deref()).await {
+        Ok(true) => HttpResponse::Ok().json(StatusResponse {
+            status: "success".to_string(),
+            message: Some("Caption updated".to_string()),
+        }),
+        Ok(false) => HttpResponse::NotFound().json(ErrorResponse::new(ErrorCode::ImageNotFound, "Image not found")),
+        Err(e) => {
+            println!("Failed to update caption: {:?}", e);
+            HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Database error".to_string()),
+            })
+        }
+    }
+}
+
+/// DELETE /profile/images/{id} - Remove one image and its stored files
+/// The images after it move up a slot
+pub async fn delete_image(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        });
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        });
+    };
+
+    let Ok(image_id) = Uuid::parse_str(&path.into_inner()) else {
+        return HttpResponse::NotFound().json(ErrorResponse::new(ErrorCode::ImageNotFound, "Image not found"));
+    };
+
+    match images_queries::delete_image(&pool, &user_id, &image_id).await {
+        Ok(Some(storage_key)) => {
+            if let Some(key) = storage_key {
+                media::delete_image_files(state.media.as_ref(), &key).await;
+            }
+
+            // Fewer than 6 images takes the profile out of other users' feeds
+            if let Err(e) = completeness::recheck(&pool, &user_id).await {
+                println!("Failed to recheck profile completeness: {:?}", e);
+            }
 
-    match fs::copy(&form.file.file.path(), &dest_path) {
-        Ok(_) => {
-            println!("File Uploaded!!!!");
             HttpResponse::Ok().json(StatusResponse {
                 status: "success".to_string(),
-                message: Some("File uploaded successfully".to_string()),
+                message: Some("Image deleted".to_string()),
             })
-        },
+        }
+        Ok(None) => HttpResponse::NotFound().json(ErrorResponse::new(ErrorCode::ImageNotFound, "Image not found")),
         Err(e) => {
-            println!("Failed to upload file: {:?}", e);
+            println!("Failed to delete image: {:?}", e);
             HttpResponse::InternalServerError().json(StatusResponse {
                 status: "error".to_string(),
-                message: Some("Failed to upload file".to_string()),
+                message: Some("Database error".to_string()),
             })
         }
     }
-}
\ No newline at end of file
+}
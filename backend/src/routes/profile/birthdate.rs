@@ -0,0 +1,116 @@
+//! Birthdate rules
+//! Birthdates are parsed as dates, must make the user an adult, and are only shown to
+//! other users as an age.
+
+use chrono::{Datelike, NaiveDate};
+
+/// Youngest age allowed on the app
+pub const MIN_AGE: i32 = 18;
+
+/// Oldest plausible age; anything older is treated as a typo
+pub const MAX_AGE: i32 = 100;
+
+/// Why a birthdate was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BirthdateError {
+    /// Not a "YYYY-MM-DD" date
+    Format,
+    /// In the future or more than MAX_AGE years ago
+    Implausible,
+    /// Younger than MIN_AGE
+    Underage,
+}
+
+/// Age in whole years on `today`
+pub fn age_on(birthdate: NaiveDate, today: NaiveDate) -> i32 {
+    let mut age = today.year() - birthdate.year();
+    if (today.month(), today.day()) < (birthdate.month(), birthdate.day()) {
+        age -= 1;
+    }
+    age
+}
+
+/// Parse a "YYYY-MM-DD" birthdate and check it against the age rules
+pub fn parse(value: &str, today: NaiveDate) -> Result<NaiveDate, BirthdateError> {
+    let birthdate = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").map_err(|_| BirthdateError::Format)?;
+
+    if birthdate > today {
+        return Err(BirthdateError::Implausible);
+    }
+
+    let age = age_on(birthdate, today);
+    if age > MAX_AGE {
+        return Err(BirthdateError::Implausible);
+    }
+    if age < MIN_AGE {
+        return Err(BirthdateError::Underage);
+    }
+
+    Ok(birthdate)
+}
+
+/// Age from a stored birthdate ("YYYY-MM-DD", as selected with `birthdate::TEXT`)
+pub fn age_from_text(birthdate: &str, today: NaiveDate) -> Option<i32> {
+    NaiveDate::parse_from_str(birthdate, "%Y-%m-%d")
+        .ok()
+        .map(|date| age_on(date, today))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn counts_whole_years() {
+        assert_eq!(age_on(date("1990-06-15"), date("2024-06-14")), 33);
+        assert_eq!(age_on(date("1990-06-15"), date("2024-06-15")), 34);
+        assert_eq!(age_on(date("1990-12-31"), date("2025-01-01")), 34);
+    }
+
+    #[test]
+    fn a_leap_day_birthday_comes_on_march_1st_in_other_years() {
+        assert_eq!(age_on(date("2004-02-29"), date("2022-02-28")), 17);
+        assert_eq!(age_on(date("2004-02-29"), date("2022-03-01")), 18);
+        assert_eq!(age_on(date("2004-02-29"), date("2024-02-29")), 20);
+
+        assert_eq!(parse("2004-02-29", date("2022-02-28")), Err(BirthdateError::Underage));
+        assert_eq!(parse("2004-02-29", date("2022-03-01")), Ok(date("2004-02-29")));
+    }
+
+    #[test]
+    fn accepts_users_turning_18_today() {
+        assert_eq!(parse("2006-10-19", date("2024-10-19")), Ok(date("2006-10-19")));
+        assert_eq!(parse("2006-10-20", date("2024-10-19")), Err(BirthdateError::Underage));
+    }
+
+    #[test]
+    fn rejects_dates_in_the_future() {
+        assert_eq!(parse("2024-10-20", date("2024-10-19")), Err(BirthdateError::Implausible));
+        assert_eq!(parse("2024-10-19", date("2024-10-19")), Err(BirthdateError::Underage));
+    }
+
+    #[test]
+    fn rejects_ages_over_the_upper_bound() {
+        assert_eq!(parse("1924-10-19", date("2024-10-19")), Ok(date("1924-10-19")));
+        assert_eq!(parse("1923-10-20", date("2024-10-19")), Ok(date("1923-10-20")));
+        assert_eq!(parse("1923-10-19", date("2024-10-19")), Err(BirthdateError::Implausible));
+    }
+
+    #[test]
+    fn needs_a_full_date() {
+        assert_eq!(parse(" 1990-06-15 ", date("2024-10-19")), Ok(date("1990-06-15")));
+        assert_eq!(parse("15/06/1990", date("2024-10-19")), Err(BirthdateError::Format));
+        assert_eq!(parse("1990-02-30", date("2024-10-19")), Err(BirthdateError::Format));
+        assert_eq!(parse("", date("2024-10-19")), Err(BirthdateError::Format));
+    }
+
+    #[test]
+    fn reads_stored_birthdates() {
+        assert_eq!(age_from_text("1990-06-15", date("2024-10-19")), Some(34));
+        assert_eq!(age_from_text("not a date", date("2024-10-19")), None);
+    }
+}
@@ -0,0 +1,81 @@
+//! Profile completeness
+//! `users.is_profile_complete` is set by /profile/finalize and cleared again by any edit
+//! that takes the profile below the bar. Incomplete profiles are left out of other users' feeds.
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::{feed_queries, images_queries, profile_queries, prompt_queries, user_queries};
+
+/// Images needed for a complete profile
+pub const REQUIRED_IMAGES: i64 = 6;
+
+/// Prompts needed for a complete profile
+pub const REQUIRED_PROMPTS: i64 = 3;
+
+/// What the user still has to do before the profile counts as complete (empty when complete)
+pub async fn pending_actions(pool: &PgPool, user_id: &Uuid) -> Result<Vec<String>, sqlx::Error> {
+    let mut pending: Vec<String> = Vec::new();
+
+    // Every image has to be APPROVED: rejected ones hold a slot but have to be replaced,
+    // pending ones are listed until moderation gets to them
+    let images_uploaded = images_queries::count_images(pool, user_id).await?;
+    if images_uploaded < REQUIRED_IMAGES {
+        pending.push(format!("Upload {} more images", REQUIRED_IMAGES - images_uploaded));
+    }
+
+    for (order, reason) in images_queries::get_rejected_images(pool, user_id).await? {
+        pending.push(match reason {
+            Some(reason) => format!("Replace image {}, it was rejected: {}", order + 1, reason),
+            None => format!("Replace image {}, it was rejected", order + 1),
+        });
+    }
+
+    for order in images_queries::get_pending_images(pool, user_id).await? {
+        pending.push(format!("Wait for image {} to be reviewed", order + 1));
+    }
+
+    let prompts_uploaded = prompt_queries::count_prompts(pool, user_id).await?;
+    if prompts_uploaded < REQUIRED_PROMPTS {
+        pending.push(format!("Upload {} more prompts", REQUIRED_PROMPTS - prompts_uploaded));
+    }
+
+    // Same for prompt videos, which other users only get once they're APPROVED
+    for (order, status, reason) in prompt_queries::get_unapproved_videos(pool, user_id).await? {
+        pending.push(match (status.as_str(), reason) {
+            ("REJECTED", Some(reason)) => format!("Replace the video of prompt {}, it was rejected: {}", order + 1, reason),
+            ("REJECTED", None) => format!("Replace the video of prompt {}, it was rejected", order + 1),
+            _ => format!("Wait for the video of prompt {} to be reviewed", order + 1),
+        });
+    }
+
+    let missing_fields = profile_queries::check_profile_attributes_filled(pool, user_id).await?;
+    if missing_fields > 0 {
+        pending.push(format!("Fill {} more profile details", missing_fields));
+    }
+
+    Ok(pending)
+}
+
+/// Store whether the profile is complete; feeds that include (or should include) the user are rebuilt on a change
+pub async fn set_complete(pool: &PgPool, user_id: &Uuid, complete: bool) -> Result<(), sqlx::Error> {
+    if user_queries::set_profile_complete(pool, user_id, complete).await? {
+        feed_queries::request_pool_refresh(pool, user_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Clear the complete flag if an edit took the profile below the bar
+/// Only ever lowers the flag; raising it again goes through /profile/finalize
+pub async fn recheck(pool: &PgPool, user_id: &Uuid) -> Result<(), sqlx::Error> {
+    if !user_queries::is_profile_complete(pool, user_id).await? {
+        return Ok(());
+    }
+
+    if !pending_actions(pool, user_id).await?.is_empty() {
+        set_complete(pool, user_id, false).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,116 @@
+//! Controlled vocabularies for profile fields
+//! The allowed values live in the `profile_options` table and are listed by GET /meta/options.
+
+use std::collections::BTreeMap;
+
+use sqlx::PgPool;
+
+use crate::db::option_queries;
+use crate::models::inputs::UpdateProfileRequest;
+
+/// Allowed values per field, in display order
+#[derive(Debug, Clone, Default)]
+pub struct ProfileOptions {
+    pub fields: BTreeMap<String, Vec<String>>,
+}
+
+impl ProfileOptions {
+    pub async fn load(pool: &PgPool) -> Result<Self, sqlx::Error> {
+        let mut fields: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (field, value) in option_queries::get_profile_options(pool).await? {
+            fields.entry(field).or_default().push(value);
+        }
+
+        Ok(Self { fields })
+    }
+
+    /// The stored spelling of `value` for `field` (matched case-insensitively)
+    pub fn canonical(&self, field: &str, value: &str) -> Option<&str> {
+        self.fields
+            .get(field)?
+            .iter()
+            .find(|allowed| allowed.eq_ignore_ascii_case(value.trim()))
+            .map(String::as_str)
+    }
+
+    /// Replace every controlled field in the request with its stored spelling
+    /// Returns the first field whose value isn't allowed, with the allowed values
+    pub fn normalize(&self, req: &mut UpdateProfileRequest) -> Result<(), (&'static str, Vec<String>)> {
+        let fields: [(&'static str, &mut Option<String>); 8] = [
+            ("gender", &mut req.gender),
+            ("sexuality", &mut req.sexuality),
+            ("politics", &mut req.politics),
+            ("religion", &mut req.religion),
+            ("drinks", &mut req.drinks),
+            ("smokes", &mut req.smokes),
+            ("relationship_type", &mut req.relationship_type),
+            ("dating_intention", &mut req.dating_intention),
+        ];
+
+        for (field, value) in fields {
+            let Some(v) = value else {
+                continue;
+            };
+
+            match self.canonical(field, v) {
+                Some(allowed) => *v = allowed.to_string(),
+                None => return Err((field, self.fields.get(field).cloned().unwrap_or_default())),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> ProfileOptions {
+        let mut fields = BTreeMap::new();
+        fields.insert("drinks".to_string(), vec!["Yes".to_string(), "Sometimes".to_string(), "No".to_string()]);
+        fields.insert("gender".to_string(), vec!["Man".to_string(), "Woman".to_string(), "Non-binary".to_string()]);
+        ProfileOptions { fields }
+    }
+
+    fn request(body: serde_json::Value) -> UpdateProfileRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn finds_the_stored_spelling() {
+        let options = options();
+
+        assert_eq!(options.canonical("gender", "Woman"), Some("Woman"));
+        assert_eq!(options.canonical("gender", " non-BINARY "), Some("Non-binary"));
+        assert_eq!(options.canonical("drinks", "socially"), None);
+        assert_eq!(options.canonical("religion", "Other"), None);
+    }
+
+    #[test]
+    fn normalizes_every_controlled_field() {
+        let mut req = request(serde_json::json!({ "name": "ana", "gender": "woman", "drinks": "NO" }));
+
+        assert_eq!(options().normalize(&mut req), Ok(()));
+        assert_eq!(req.gender.as_deref(), Some("Woman"));
+        assert_eq!(req.drinks.as_deref(), Some("No"));
+        assert_eq!(req.name.as_deref(), Some("ana"));
+        assert_eq!(req.smokes, None);
+    }
+
+    #[test]
+    fn reports_the_first_value_not_allowed() {
+        let mut req = request(serde_json::json!({ "gender": "Man", "drinks": "Socially" }));
+
+        let (field, allowed) = options().normalize(&mut req).unwrap_err();
+        assert_eq!(field, "drinks");
+        assert_eq!(allowed, ["Yes", "Sometimes", "No"]);
+    }
+
+    #[test]
+    fn rejects_values_for_fields_without_options() {
+        let mut req = request(serde_json::json!({ "politics": "Liberal" }));
+
+        assert_eq!(options().normalize(&mut req), Err(("politics", Vec::new())));
+    }
+}
@@ -0,0 +1,75 @@
+//! Per-field visibility
+//! Users can hide some profile fields from other users. Hidden fields stay in the database
+//! and are still used for preference filtering and ranking; they are only left out of
+//! public profile views (see `SuggestionProfile::into_user_profile`).
+
+use std::collections::HashMap;
+
+/// Fields that can be hidden; name, bio and age are always shown
+pub const HIDEABLE_FIELDS: [&str; 14] = [
+    "pronouns",
+    "gender",
+    "sexuality",
+    "height",
+    "job",
+    "company",
+    "school",
+    "ethnicity",
+    "politics",
+    "religion",
+    "relationship_type",
+    "dating_intention",
+    "drinks",
+    "smokes",
+];
+
+/// Split visibility flags into (fields to hide, fields to show)
+/// Returns the first field that can't be hidden
+pub fn split(visibility: &HashMap<String, bool>) -> Result<(Vec<String>, Vec<String>), String> {
+    let mut hide = Vec::new();
+    let mut show = Vec::new();
+
+    for (field, visible) in visibility {
+        if !HIDEABLE_FIELDS.contains(&field.as_str()) {
+            return Err(field.clone());
+        }
+
+        if *visible {
+            show.push(field.clone());
+        } else {
+            hide.push(field.clone());
+        }
+    }
+
+    Ok((hide, show))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(pairs: &[(&str, bool)]) -> HashMap<String, bool> {
+        pairs.iter().map(|(field, visible)| (field.to_string(), *visible)).collect()
+    }
+
+    #[test]
+    fn splits_hidden_and_visible_fields() {
+        let (mut hide, mut show) = split(&flags(&[("religion", false), ("height", true), ("politics", false)])).unwrap();
+        hide.sort();
+        show.sort();
+
+        assert_eq!(hide, ["politics", "religion"]);
+        assert_eq!(show, ["height"]);
+    }
+
+    #[test]
+    fn accepts_no_flags() {
+        assert_eq!(split(&HashMap::new()), Ok((Vec::new(), Vec::new())));
+    }
+
+    #[test]
+    fn rejects_fields_that_are_always_shown() {
+        assert_eq!(split(&flags(&[("name", false)])), Err("name".to_string()));
+        assert_eq!(split(&flags(&[("drinks", true), ("birthdate", true)])), Err("birthdate".to_string()));
+    }
+}
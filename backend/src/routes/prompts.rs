@@ -2,10 +2,24 @@ use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, web};
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::db::prompt_queries;
+use actix_multipart::form::{MultipartForm, tempfile::TempFile, text::Text};
+use std::fs;
+
+use crate::db::prompt_queries::{self, NewAudio, NewPromptVideo, PromptMediaKeys, PromptRecording, QuestionKind};
 use crate::jwtauth::Claims;
-use crate::models::inputs::{CreatePromptRequest, UpdatePromptRequest};
-use crate::models::outputs::{StatusResponse, UserPrompt};
+use crate::models::inputs::{CreatePollRequest, CreatePromptRequest, UpdatePollRequest, UpdatePromptRequest};
+use crate::models::outputs::{ErrorCode, ErrorResponse, PromptCatalogResponse, PromptCategory, PromptQuestion, StatusResponse, UserPrompt};
+use crate::models::state::AppState;
+use crate::routes::media::audio::{self, AudioError};
+use crate::routes::media::{self, MediaStore, moderation};
+use crate::routes::profile::{self, completeness};
+
+/// Number of options a poll can have
+const MIN_POLL_OPTIONS: usize = 2;
+const MAX_POLL_OPTIONS: usize = 4;
+
+/// Longest poll option, in characters
+const MAX_POLL_OPTION_CHARS: usize = 80;
 
 /// GET /prompts - Get all prompts for the current user
 pub async fn get_prompts(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
@@ -25,15 +39,7 @@ pub async fn get_prompts(pool: web::Data<PgPool>, req: HttpRequest) -> impl Resp
 
     match prompt_queries::get_user_prompts(&pool, &user_id).await {
         Ok(rows) => {
-            let prompts: Vec<UserPrompt> = rows
-                .into_iter()
-                .map(|(id, question, answer, order)| UserPrompt {
-                    id: id.to_string(),
-                    question,
-                    answer,
-                    order,
-                })
-                .collect();
+            let prompts: Vec<UserPrompt> = rows.into_iter().map(UserPrompt::from).collect();
             HttpResponse::Ok().json(prompts)
         }
         Err(e) => {
@@ -46,6 +52,69 @@ pub async fn get_prompts(pool: web::Data<PgPool>, req: HttpRequest) -> impl Resp
     }
 }
 
+/// GET /prompts/catalog - Questions users can answer, grouped by category
+pub async fn get_catalog(pool: web::Data<PgPool>) -> impl Responder {
+    let rows = match prompt_queries::get_catalog(&pool).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Failed to get prompt catalog: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to retrieve prompt catalog".to_string()),
+            });
+        }
+    };
+
+    // Rows come ordered by category, so each category's questions are contiguous
+    let mut categories: Vec<PromptCategory> = Vec::new();
+    for row in rows {
+        if categories.last().is_none_or(|c| c.id != row.category_id) {
+            categories.push(PromptCategory {
+                id: row.category_id,
+                name: row.category_name,
+                questions: Vec::new(),
+            });
+        }
+
+        if let Some(category) = categories.last_mut() {
+            category.questions.push(PromptQuestion { id: row.id, text: row.text, kind: row.kind });
+        }
+    }
+
+    HttpResponse::Ok().json(PromptCatalogResponse { categories })
+}
+
+/// Resolve the question a create/update request picked to an active catalog id of the given kind
+/// Ok(None) when the request names no question
+async fn resolve_question(
+    pool: &PgPool,
+    question_id: Option<&str>,
+    question: Option<&str>,
+    kind: QuestionKind,
+) -> Result<Option<String>, HttpResponse> {
+    if question_id.is_none() && question.is_none() {
+        return Ok(None);
+    }
+
+    match prompt_queries::find_active_question(pool, question_id, question, kind).await {
+        Ok(Some(id)) => Ok(Some(id)),
+        Ok(None) => Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidPromptQuestion,
+            match kind {
+                QuestionKind::Text => "Pick a question from GET /prompts/catalog",
+                QuestionKind::Poll => "Pick a POLL question from GET /prompts/catalog",
+            },
+        ))),
+        Err(e) => {
+            eprintln!("Failed to look up prompt question: {:?}", e);
+            Err(HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Database error".to_string()),
+            }))
+        }
+    }
+}
+
 /// POST /prompts - Create a new prompt (max 3)
 pub async fn create_prompt(
     pool: web::Data<PgPool>,
@@ -67,13 +136,6 @@ pub async fn create_prompt(
     };
 
     // Validate input
-    if body.question.trim().is_empty() {
-        return HttpResponse::BadRequest().json(StatusResponse {
-            status: "error".to_string(),
-            message: Some("Question cannot be empty".to_string()),
-        });
-    }
-
     if body.answer.trim().is_empty() {
         return HttpResponse::BadRequest().json(StatusResponse {
             status: "error".to_string(),
@@ -81,7 +143,18 @@ pub async fn create_prompt(
         });
     }
 
-    match prompt_queries::insert_prompt(&pool, &user_id, &body.question, &body.answer).await {
+    let question_id = match resolve_question(&pool, body.question_id.as_deref(), body.question.as_deref(), QuestionKind::Text).await {
+        Ok(Some(id)) => id,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::InvalidPromptQuestion,
+                "question_id is required",
+            ));
+        }
+        Err(response) => return response,
+    };
+
+    match prompt_queries::insert_prompt(&pool, &user_id, &question_id, Some(&body.answer), None, None).await {
         Ok(_) => HttpResponse::Created().json(StatusResponse {
             status: "success".to_string(),
             message: Some("Prompt created successfully".to_string()),
@@ -136,13 +209,6 @@ pub async fn update_prompt(
     }
 
     // Validate input
-    if body.question.trim().is_empty() {
-        return HttpResponse::BadRequest().json(StatusResponse {
-            status: "error".to_string(),
-            message: Some("Question cannot be empty".to_string()),
-        });
-    }
-
     if body.answer.trim().is_empty() {
         return HttpResponse::BadRequest().json(StatusResponse {
             status: "error".to_string(),
@@ -150,11 +216,29 @@ pub async fn update_prompt(
         });
     }
 
+    // Without a new question the prompt keeps its own, even if it has since been retired from the catalog
+    let question_id = match resolve_question(&pool, body.question_id.as_deref(), body.question.as_deref(), QuestionKind::Text).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    match prompt_queries::is_poll(&pool, &user_id, display_order).await {
+        Ok(Some(true)) => return poll_not_editable(),
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Failed to update prompt: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to update prompt".to_string()),
+            });
+        }
+    }
+
     match prompt_queries::update_prompt(
         &pool,
         &user_id,
         display_order,
-        &body.question,
+        question_id.as_deref(),
         &body.answer,
     )
     .await
@@ -176,6 +260,7 @@ pub async fn update_prompt(
 /// DELETE /prompts/{order} - Delete a prompt by order
 pub async fn delete_prompt(
     pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
     req: HttpRequest,
     path: web::Path<i32>,
 ) -> impl Responder {
@@ -204,10 +289,19 @@ pub async fn delete_prompt(
     }
 
     match prompt_queries::delete_prompt(&pool, &user_id, display_order).await {
-        Ok(_) => HttpResponse::Ok().json(StatusResponse {
-            status: "success".to_string(),
-            message: Some("Prompt deleted successfully".to_string()),
-        }),
+        Ok(keys) => {
+            delete_recordings(state.media.as_ref(), &keys).await;
+
+            // Fewer than 3 prompts takes the profile out of other users' feeds
+            if let Err(e) = completeness::recheck(&pool, &user_id).await {
+                eprintln!("Failed to recheck profile completeness: {:?}", e);
+            }
+
+            HttpResponse::Ok().json(StatusResponse {
+                status: "success".to_string(),
+                message: Some("Prompt deleted successfully".to_string()),
+            })
+        }
         Err(e) => {
             eprintln!("Failed to delete prompt: {:?}", e);
             HttpResponse::InternalServerError().json(StatusResponse {
@@ -217,3 +311,509 @@ pub async fn delete_prompt(
         }
     }
 }
+
+/// Delete the stored files of a prompt's recordings, only logging failures
+pub async fn delete_recordings(store: &dyn MediaStore, keys: &PromptMediaKeys) {
+    if let Some(key) = &keys.audio_key {
+        media::delete_file(store, key).await;
+    }
+    if let Some(key) = &keys.video_key {
+        media::delete_image_files(store, key).await;
+    }
+}
+
+/// Recording upload: the voice note or video, plus the same question fields as CreatePromptRequest and an optional text answer
+/// Size is capped by the route's MultipartFormConfig (audio::MAX_AUDIO_BYTES or video::MAX_VIDEO_BYTES)
+#[derive(Debug, MultipartForm)]
+pub struct RecordingUpload {
+    file: TempFile,
+    question_id: Option<Text<String>>,
+    question: Option<Text<String>>,
+    answer: Option<Text<String>>,
+}
+
+fn text_field(field: &Option<Text<String>>) -> Option<&str> {
+    field.as_deref().map(String::as_str)
+}
+
+/// What a recording upload must be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecordingKind {
+    Voice,
+    Video,
+}
+
+/// Check an uploaded recording and write it to the media store
+/// Returns the stored recording, or the error response
+async fn store_recording(
+    state: &AppState,
+    user_id: &Uuid,
+    kind: RecordingKind,
+    file: &TempFile,
+) -> Result<PromptRecording, HttpResponse> {
+    match kind {
+        RecordingKind::Voice => store_voice(state, user_id, file).await.map(PromptRecording::Voice),
+        RecordingKind::Video => {
+            // The poster's card rendition is what the prompt shows before the clip plays
+            // Every stored file sits under the poster's key, so recording.media_keys() covers them if saving fails
+            let (poster, video, _stored) = profile::store_video_upload(state, media::prompt_video_key(user_id), file).await?;
+
+            Ok(PromptRecording::Video(NewPromptVideo {
+                key: poster.storage_key,
+                url: video.url,
+                poster_url: poster.card_url,
+                duration_ms: video.duration_ms,
+            }))
+        }
+    }
+}
+
+/// Run a saved video's poster frame past the classifier; other users don't get the video until it's APPROVED
+/// The owner sees the outcome on their prompt (video_moderation_status)
+async fn review_video(pool: &PgPool, state: &AppState, user_id: &Uuid, recording: &PromptRecording) {
+    if let PromptRecording::Video(video) = recording {
+        moderation::review_prompt_video(pool, state.media.as_ref(), state.classifier.as_ref(), user_id, &video.key).await;
+    }
+}
+
+/// Check an uploaded voice note and write it to the media store
+/// Returns the stored voice note, or the error response
+async fn store_voice(state: &AppState, user_id: &Uuid, file: &TempFile) -> Result<NewAudio, HttpResponse> {
+    let mut bytes = match fs::read(file.file.path()) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to read upload: {:?}", e);
+            return Err(HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to upload file".to_string()),
+            }));
+        }
+    };
+
+    let (format, duration_ms) = match audio::check_audio(&bytes) {
+        Ok(checked) => checked,
+        Err(AudioError::Unsupported) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::UnsupportedMediaType,
+                "Voice notes must be M4A (AAC), Ogg (Opus or Vorbis) or WAV recordings",
+            )))
+        }
+        Err(AudioError::Unreadable) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::UnsupportedMediaType,
+                "The recording could not be read",
+            )))
+        }
+        Err(AudioError::TooShort | AudioError::TooLong) => {
+            return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::InvalidAudioDuration,
+                &format!(
+                    "Voice notes must be between {} and {} seconds long",
+                    audio::MIN_AUDIO_MS / 1000,
+                    audio::MAX_AUDIO_MS / 1000
+                ),
+            )))
+        }
+    };
+
+    audio::strip_metadata(format, &mut bytes);
+
+    let key = media::prompt_audio_key(user_id, format.extension());
+    if let Err(e) = state.media.put(&key, bytes, format.content_type()).await {
+        eprintln!("Failed to store media {} ({}): {}", key, state.media.name(), e);
+        return Err(HttpResponse::InternalServerError().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Failed to upload file".to_string()),
+        }));
+    }
+
+    Ok(NewAudio {
+        url: state.media.url(&key),
+        key,
+        duration_ms: duration_ms as i32,
+    })
+}
+
+/// POST /prompts/voice - Answer a prompt with a voice note (max 3 prompts)
+/// Multipart: `file`, `question_id` (or `question`) and an optional text `answer`
+pub async fn create_voice_prompt(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    MultipartForm(form): MultipartForm<RecordingUpload>,
+) -> impl Responder {
+    create_recorded_prompt(pool, state, req, form, RecordingKind::Voice).await
+}
+
+/// POST /prompts/video - Answer a prompt with a short video (max 3 prompts)
+/// Same multipart body as POST /prompts/voice
+pub async fn create_video_prompt(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    MultipartForm(form): MultipartForm<RecordingUpload>,
+) -> impl Responder {
+    create_recorded_prompt(pool, state, req, form, RecordingKind::Video).await
+}
+
+async fn create_recorded_prompt(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    form: RecordingUpload,
+    kind: RecordingKind,
+) -> HttpResponse {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        });
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        });
+    };
+
+    let question_id = match resolve_question(&pool, text_field(&form.question_id), text_field(&form.question), QuestionKind::Text).await {
+        Ok(Some(id)) => id,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::InvalidPromptQuestion,
+                "question_id is required",
+            ));
+        }
+        Err(response) => return response,
+    };
+    let answer = form.answer.map(|a| a.into_inner().trim().to_string()).filter(|a| !a.is_empty());
+
+    // Check before storing anything; insert_prompt checks again
+    match prompt_queries::count_prompts(&pool, &user_id).await {
+        Ok(count) if count >= 3 => {
+            return HttpResponse::BadRequest().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Maximum 3 prompts allowed".to_string()),
+            });
+        }
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Failed to count prompts: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to create prompt".to_string()),
+            });
+        }
+    }
+
+    let recording = match store_recording(&state, &user_id, kind, &form.file).await {
+        Ok(recording) => recording,
+        Err(response) => return response,
+    };
+
+    match prompt_queries::insert_prompt(&pool, &user_id, &question_id, answer.as_deref(), Some(&recording), None).await {
+        Ok(_) => {
+            review_video(&pool, &state, &user_id, &recording).await;
+
+            HttpResponse::Created().json(StatusResponse {
+                status: "success".to_string(),
+                message: Some("Prompt created successfully".to_string()),
+            })
+        }
+        Err(e) => {
+            delete_recordings(state.media.as_ref(), &recording.media_keys()).await;
+
+            if e.to_string().contains("Maximum 3 prompts allowed") {
+                HttpResponse::BadRequest().json(StatusResponse {
+                    status: "error".to_string(),
+                    message: Some("Maximum 3 prompts allowed".to_string()),
+                })
+            } else {
+                eprintln!("Failed to create prompt: {:?}", e);
+                HttpResponse::InternalServerError().json(StatusResponse {
+                    status: "error".to_string(),
+                    message: Some("Failed to create prompt".to_string()),
+                })
+            }
+        }
+    }
+}
+
+/// PUT /prompts/{order}/voice - Record a new voice note for an existing prompt, replacing its voice note or video
+/// Same multipart body as POST /prompts/voice; the question is kept unless a new one is sent,
+/// the text answer is left as it is (use PUT /prompts/{order} to change it)
+pub async fn update_voice_prompt(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    MultipartForm(form): MultipartForm<RecordingUpload>,
+) -> impl Responder {
+    update_recorded_prompt(pool, state, req, path, form, RecordingKind::Voice).await
+}
+
+/// PUT /prompts/{order}/video - Record a new video for an existing prompt, replacing its voice note or video
+/// Same rules as PUT /prompts/{order}/voice
+pub async fn update_video_prompt(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    MultipartForm(form): MultipartForm<RecordingUpload>,
+) -> impl Responder {
+    update_recorded_prompt(pool, state, req, path, form, RecordingKind::Video).await
+}
+
+async fn update_recorded_prompt(
+    pool: web::Data<PgPool>,
+    state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    form: RecordingUpload,
+    kind: RecordingKind,
+) -> HttpResponse {
+    let display_order = path.into_inner();
+
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        });
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        });
+    };
+
+    // Validate order range (0-2)
+    if !(0..=2).contains(&display_order) {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Display order must be between 0 and 2".to_string()),
+        });
+    }
+
+    let question_id = match resolve_question(&pool, text_field(&form.question_id), text_field(&form.question), QuestionKind::Text).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    // Check before storing anything
+    match prompt_queries::is_poll(&pool, &user_id, display_order).await {
+        Ok(Some(false)) => {}
+        Ok(Some(true)) => return poll_not_editable(),
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse::new(ErrorCode::PromptNotFound, "No prompt at that order"));
+        }
+        Err(e) => {
+            eprintln!("Failed to update prompt: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to update prompt".to_string()),
+            });
+        }
+    }
+
+    let recording = match store_recording(&state, &user_id, kind, &form.file).await {
+        Ok(recording) => recording,
+        Err(response) => return response,
+    };
+
+    match prompt_queries::set_prompt_recording(&pool, &user_id, display_order, question_id.as_deref(), &recording).await {
+        Ok(Some(old_keys)) => {
+            delete_recordings(state.media.as_ref(), &old_keys).await;
+            review_video(&pool, &state, &user_id, &recording).await;
+
+            HttpResponse::Ok().json(StatusResponse {
+                status: "success".to_string(),
+                message: Some("Prompt updated successfully".to_string()),
+            })
+        }
+        Ok(None) => {
+            delete_recordings(state.media.as_ref(), &recording.media_keys()).await;
+            HttpResponse::NotFound().json(ErrorResponse::new(ErrorCode::PromptNotFound, "No prompt at that order"))
+        }
+        Err(e) => {
+            eprintln!("Failed to update prompt: {:?}", e);
+            delete_recordings(state.media.as_ref(), &recording.media_keys()).await;
+            HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to update prompt".to_string()),
+            })
+        }
+    }
+}
+
+/// A poll can only be changed into another poll, and a text or recorded prompt never into one
+fn poll_not_editable() -> HttpResponse {
+    HttpResponse::Conflict().json(ErrorResponse::new(
+        ErrorCode::WrongPromptType,
+        "This prompt is a poll, change its options with PUT /prompts/{order}/poll",
+    ))
+}
+
+/// Trim a poll's options and check their number, length and that no two are the same
+fn check_poll_options(options: &[String]) -> Result<Vec<String>, HttpResponse> {
+    let options: Vec<String> = options.iter().map(|o| o.trim().to_string()).collect();
+
+    let invalid = |message: &str| {
+        Err(HttpResponse::BadRequest().json(ErrorResponse::new(ErrorCode::InvalidPollOptions, message)))
+    };
+
+    if !(MIN_POLL_OPTIONS..=MAX_POLL_OPTIONS).contains(&options.len()) {
+        return invalid(&format!("A poll needs {} to {} options", MIN_POLL_OPTIONS, MAX_POLL_OPTIONS));
+    }
+    if options.iter().any(|o| o.is_empty() || o.chars().count() > MAX_POLL_OPTION_CHARS) {
+        return invalid(&format!("Poll options must be 1 to {} characters long", MAX_POLL_OPTION_CHARS));
+    }
+
+    let mut seen: Vec<String> = Vec::new();
+    for option in &options {
+        let folded = option.to_lowercase();
+        if seen.contains(&folded) {
+            return invalid("Poll options must all be different");
+        }
+        seen.push(folded);
+    }
+
+    Ok(options)
+}
+
+/// POST /prompts/poll - Ask a poll question with 2-4 options (max 3 prompts)
+pub async fn create_poll_prompt(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    body: web::Json<CreatePollRequest>,
+) -> impl Responder {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        });
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        });
+    };
+
+    let options = match check_poll_options(&body.options) {
+        Ok(options) => options,
+        Err(response) => return response,
+    };
+
+    let question_id = match resolve_question(&pool, body.question_id.as_deref(), body.question.as_deref(), QuestionKind::Poll).await {
+        Ok(Some(id)) => id,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(ErrorResponse::new(
+                ErrorCode::InvalidPromptQuestion,
+                "question_id is required",
+            ));
+        }
+        Err(response) => return response,
+    };
+
+    match prompt_queries::insert_prompt(&pool, &user_id, &question_id, None, None, Some(&options)).await {
+        Ok(_) => HttpResponse::Created().json(StatusResponse {
+            status: "success".to_string(),
+            message: Some("Prompt created successfully".to_string()),
+        }),
+        Err(e) => {
+            if e.to_string().contains("Maximum 3 prompts allowed") {
+                HttpResponse::BadRequest().json(StatusResponse {
+                    status: "error".to_string(),
+                    message: Some("Maximum 3 prompts allowed".to_string()),
+                })
+            } else {
+                eprintln!("Failed to create prompt: {:?}", e);
+                HttpResponse::InternalServerError().json(StatusResponse {
+                    status: "error".to_string(),
+                    message: Some("Failed to create prompt".to_string()),
+                })
+            }
+        }
+    }
+}
+
+/// PUT /prompts/{order}/poll - Change a poll's options (and optionally its question)
+/// Likes already voted keep the option they picked
+pub async fn update_poll_prompt(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<UpdatePollRequest>,
+) -> impl Responder {
+    let display_order = path.into_inner();
+
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        });
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        });
+    };
+
+    // Validate order range (0-2)
+    if !(0..=2).contains(&display_order) {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Display order must be between 0 and 2".to_string()),
+        });
+    }
+
+    let options = match check_poll_options(&body.options) {
+        Ok(options) => options,
+        Err(response) => return response,
+    };
+
+    let question_id = match resolve_question(&pool, body.question_id.as_deref(), body.question.as_deref(), QuestionKind::Poll).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let updated = match prompt_queries::update_poll(&pool, &user_id, display_order, question_id.as_deref(), &options).await {
+        Ok(updated) => updated,
+        Err(e) => {
+            eprintln!("Failed to update prompt: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to update prompt".to_string()),
+            });
+        }
+    };
+
+    if updated {
+        return HttpResponse::Ok().json(StatusResponse {
+            status: "success".to_string(),
+            message: Some("Prompt updated successfully".to_string()),
+        });
+    }
+
+    // Nothing updated: either there's no prompt there or it isn't a poll
+    match prompt_queries::is_poll(&pool, &user_id, display_order).await {
+        Ok(Some(_)) => HttpResponse::Conflict().json(ErrorResponse::new(
+            ErrorCode::WrongPromptType,
+            "This prompt isn't a poll, delete it and create a poll instead",
+        )),
+        Ok(None) => HttpResponse::NotFound().json(ErrorResponse::new(ErrorCode::PromptNotFound, "No prompt at that order")),
+        Err(e) => {
+            eprintln!("Failed to update prompt: {:?}", e);
+            HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to update prompt".to_string()),
+            })
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, Responder, web};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::{report_queries, user_queries};
+use crate::jwtauth::Claims;
+use crate::models::inputs::{CreateReportRequest, ReportContextType, ReportReason};
+use crate::models::outputs::{CreateReportResponse, ErrorCode, ErrorResponse, StatusResponse};
+
+/// POST /reports - Report a user, optionally pointing at one of their images, prompts or messages
+/// The report goes to the moderation queue; the reported user is not told
+pub async fn create_report(
+    body: web::Json<CreateReportRequest>,
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+) -> impl Responder {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        });
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        });
+    };
+
+    let Ok(target_user_id) = Uuid::parse_str(&body.target_user_id) else {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidTarget,
+            "Invalid target user ID",
+        ));
+    };
+
+    if target_user_id == user_id {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::SelfInteraction,
+            "You can't report yourself",
+        ));
+    }
+
+    let Some(reason) = ReportReason::parse(&body.reason) else {
+        return HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidReason,
+            "Reason must be INAPPROPRIATE_CONTENT, FAKE_PROFILE, HARASSMENT, SPAM, UNDERAGE, SCAM or OTHER",
+        ));
+    };
+
+    match user_queries::user_exists(&pool, &target_user_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::NotFound().json(ErrorResponse::new(
+                ErrorCode::TargetNotFound,
+                "Target user not found",
+            ));
+        }
+        Err(e) => {
+            println!("Failed to check target user: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to submit report".to_string()),
+            });
+        }
+    }
+
+    // The reported image, prompt or message must be the target's
+    let context = match &body.context {
+        Some(ctx) => {
+            let (Some(context_type), Ok(context_id)) = (ReportContextType::parse(&ctx.r#type), Uuid::parse_str(&ctx.id)) else {
+                return HttpResponse::BadRequest().json(ErrorResponse::new(
+                    ErrorCode::InvalidContext,
+                    "Context must have type IMAGE, PROMPT or MESSAGE and a valid id",
+                ));
+            };
+
+            match report_queries::get_context_snapshot(&pool, context_type, &context_id, &target_user_id, &user_id).await {
+                Ok(Some(snapshot)) => Some((context_type, context_id, snapshot)),
+                Ok(None) => {
+                    return HttpResponse::NotFound().json(ErrorResponse::new(
+                        ErrorCode::ContextNotFound,
+                        "Reported image, prompt or message not found",
+                    ));
+                }
+                Err(e) => {
+                    println!("Failed to check report context: {:?}", e);
+                    return HttpResponse::InternalServerError().json(StatusResponse {
+                        status: "error".to_string(),
+                        message: Some("Failed to submit report".to_string()),
+                    });
+                }
+            }
+        }
+        None => None,
+    };
+
+    let details = body.details.as_deref().map(str::trim).filter(|d| !d.is_empty());
+
+    match report_queries::create_report(&pool, &user_id, &target_user_id, context, reason, details).await {
+        Ok(report_id) => HttpResponse::Ok().json(CreateReportResponse {
+            status: "success".to_string(),
+            report_id: report_id.to_string(),
+        }),
+        Err(e) => {
+            println!("Failed to create report: {:?}", e);
+            HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to submit report".to_string()),
+            })
+        }
+    }
+}
@@ -3,10 +3,10 @@ use sqlx::PgPool;
 use uuid::Uuid;
 use serde_json::json;
 
-use crate::db::user_queries;
+use crate::db::{block_queries, feed_queries, user_queries};
 use crate::jwtauth::Claims;
 use crate::models::inputs::Preferences;
-use crate::models::outputs::StatusResponse;
+use crate::models::outputs::{ErrorCode, ErrorResponse, StatusResponse};
 
 pub async fn update_user_preference(pool: web::Data<PgPool>, req: HttpRequest, body: web::Json<Preferences>) -> impl Responder {
     let Some(claims) = req.extensions().get::<Claims>().cloned() else {
@@ -36,13 +36,121 @@ pub async fn update_user_preference(pool: web::Data<PgPool>, req: HttpRequest, b
     });
 
     match user_queries::update_user_preferences(&pool, &user_id, preferences_json).await {
-        Ok(_) => HttpResponse::Ok().json(StatusResponse {
-            status: "success".to_string(),
-            message: Some("User preferences updated successfully".to_string()),
-        }),
+        Ok(_) => {
+            // Rebuild the user's feed pool with the new preferences
+            if let Err(e) = feed_queries::request_pool_refresh(&pool, &user_id).await {
+                println!("Failed to request feed pool refresh: {:?}", e);
+            }
+
+            HttpResponse::Ok().json(StatusResponse {
+                status: "success".to_string(),
+                message: Some("User preferences updated successfully".to_string()),
+            })
+        }
         Err(e) => HttpResponse::InternalServerError().json(StatusResponse {
             status: "error".to_string(),
             message: Some(format!("Failed to update user preferences: {}", e)),
         }),
     }
-}
\ No newline at end of file
+}
+
+/// Resolve the caller and the `{id}` path user for the block routes
+fn block_pair(req: &HttpRequest, path: &str) -> Result<(Uuid, Uuid), HttpResponse> {
+    let Some(claims) = req.extensions().get::<Claims>().cloned() else {
+        return Err(HttpResponse::Unauthorized().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("No authentication claims found".to_string()),
+        }));
+    };
+
+    let Ok(user_id) = Uuid::parse_str(&claims.sub) else {
+        return Err(HttpResponse::BadRequest().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Invalid user ID format".to_string()),
+        }));
+    };
+
+    let Ok(target_user_id) = Uuid::parse_str(path) else {
+        return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::InvalidTarget,
+            "Invalid target user ID",
+        )));
+    };
+
+    if target_user_id == user_id {
+        return Err(HttpResponse::BadRequest().json(ErrorResponse::new(
+            ErrorCode::SelfInteraction,
+            "You can't block yourself",
+        )));
+    }
+
+    Ok((user_id, target_user_id))
+}
+
+/// POST /users/{id}/block - Block a user
+/// Removes any match and hides both users from each other; the blocked user is not notified
+pub async fn block_user(pool: web::Data<PgPool>, req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    let (user_id, target_user_id) = match block_pair(&req, &path) {
+        Ok(pair) => pair,
+        Err(response) => return response,
+    };
+
+    match user_queries::user_exists(&pool, &target_user_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::NotFound().json(ErrorResponse::new(
+                ErrorCode::TargetNotFound,
+                "Target user not found",
+            ));
+        }
+        Err(e) => {
+            println!("Failed to check target user: {:?}", e);
+            return HttpResponse::InternalServerError().json(StatusResponse {
+                status: "error".to_string(),
+                message: Some("Failed to block user".to_string()),
+            });
+        }
+    }
+
+    if let Err(e) = block_queries::block_user(&pool, &user_id, &target_user_id).await {
+        println!("Failed to block user: {:?}", e);
+        return HttpResponse::InternalServerError().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Failed to block user".to_string()),
+        });
+    }
+
+    // Drop each user from the other's precomputed feed
+    for id in [&user_id, &target_user_id] {
+        if let Err(e) = feed_queries::request_pool_refresh(&pool, id).await {
+            println!("Failed to request feed pool refresh: {:?}", e);
+        }
+    }
+
+    HttpResponse::Ok().json(StatusResponse {
+        status: "success".to_string(),
+        message: Some("User blocked".to_string()),
+    })
+}
+
+/// DELETE /users/{id}/block - Unblock a user
+/// The removed match is not restored
+pub async fn unblock_user(pool: web::Data<PgPool>, req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    let (user_id, target_user_id) = match block_pair(&req, &path) {
+        Ok(pair) => pair,
+        Err(response) => return response,
+    };
+
+    if let Err(e) = block_queries::unblock_user(&pool, &user_id, &target_user_id).await {
+        println!("Failed to unblock user: {:?}", e);
+        return HttpResponse::InternalServerError().json(StatusResponse {
+            status: "error".to_string(),
+            message: Some("Failed to unblock user".to_string()),
+        });
+    }
+
+    HttpResponse::Ok().json(StatusResponse {
+        status: "success".to_string(),
+        message: Some("User unblocked".to_string()),
+    })
+}
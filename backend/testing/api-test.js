@@ -178,10 +178,17 @@ async function testGetPrompts() {
     return result.status === 200;
 }
 
+// 8b. Prompt Catalog
+async function testGetPromptCatalog() {
+    const result = await apiCall('GET', '/prompts/catalog', null, true);
+    logResult('GET /prompts/catalog - Get Prompt Catalog', result);
+    return result.status === 200;
+}
+
 // 9. Create Prompt
 async function testCreatePrompt() {
     const result = await apiCall('POST', '/prompts', {
-        question: 'What is your favorite hobby?',
+        question_id: 'geek_out_on',
         answer: 'I love coding and hiking!'
     }, true);
     logResult('POST /prompts - Create Prompt', result);
@@ -191,7 +198,7 @@ async function testCreatePrompt() {
 // 10. Update Prompt
 async function testUpdatePrompt(order = 0) {
     const result = await apiCall('PUT', `/prompts/${order}`, {
-        question: 'What is your favorite hobby?',
+        question_id: 'geek_out_on',
         answer: 'Updated: I love coding, hiking, and coffee!'
     }, true);
     logResult(`PUT /prompts/${order} - Update Prompt`, result);
@@ -284,6 +291,7 @@ async function runAllTests() {
     console.log('-'.repeat(40));
     
     await testGetPrompts();
+    await testGetPromptCatalog();
     await testCreatePrompt();
     await testUpdatePrompt(2);
     // await testDeletePrompt(0);  // Uncomment to test deletion
@@ -87,7 +87,7 @@ export const profiles: Profile[] = [
     politics: "Liberal",
     relationshipType: "Monogamy",
     datingIntention: "Long-term relationship",
-    drinks: "Socially",
+    drinks: "Sometimes",
     smokes: "No",
     images: [images.scarlett, images.scarlett, images.scarlett],
     prompts: [
@@ -247,7 +247,7 @@ export const profiles: Profile[] = [
     politics: "Liberal",
     relationshipType: "Monogamy",
     datingIntention: "Long-term relationship",
-    drinks: "Socially",
+    drinks: "Sometimes",
     smokes: "No",
     images: [images.chrisEvans, images.chrisEvans, images.chrisEvans],
     prompts: [
@@ -407,7 +407,7 @@ export const profiles: Profile[] = [
     politics: "Moderate",
     relationshipType: "Monogamy",
     datingIntention: "Long-term relationship",
-    drinks: "Socially",
+    drinks: "Sometimes",
     smokes: "No",
     images: [images.alexCosta, images.alexCosta, images.alexCosta],
     prompts: [